@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use crate::error::EcsError;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Entity(pub u64);
@@ -30,17 +31,17 @@ impl EntityGenerator {
 
     /// Add an entity to the dead entries if it is currently alive
     pub(crate) fn desapwn(&mut self, entity: Entity){
-        if self.is_alive(entity){
+        if self.is_alive(entity) == Ok(true){
             self.dead_entities.insert(entity);
         }
     }
 
     /// Checks if an entity has been created and is currently in use
-    pub(crate) fn is_alive(&self, entity: Entity) -> bool{
+    pub(crate) fn is_alive(&self, entity: Entity) -> Result<bool, EcsError>{
 
         if entity.0 >= self.next_id{
-            panic!("Attemted to use an entity that has not been spawned yet!");
+            return Err(EcsError::EntityNotSpawned(entity));
         }
-        self.dead_entities.contains(&entity) == false
+        Ok(self.dead_entities.contains(&entity) == false)
     }
 }
\ No newline at end of file
@@ -0,0 +1,23 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use crate::entity::Entity;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EcsError {
+    /// The entity id has never been handed out by the world.
+    EntityNotSpawned(Entity),
+    /// The entity has been spawned but was removed from the world since.
+    EntityNotFound(Entity),
+}
+
+impl fmt::Display for EcsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EcsError::EntityNotSpawned(entity) => write!(f, "Entity {} has not been spawned yet", entity.0),
+            EcsError::EntityNotFound(entity) => write!(f, "Entity {} does not exist in the world", entity.0),
+        }
+    }
+}
+
+impl Error for EcsError {}
@@ -0,0 +1,39 @@
+/// Collection of events of a single type that have been sent during the current frame.
+/// The events are stored as a resource inside the world and get cleared at the end of every frame.
+pub struct Events<T> {
+    events: Vec<T>,
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self {
+            events: Vec::new(),
+        }
+    }
+
+    /// Queue a new event, it can be read by every system until the events get cleared.
+    pub fn send(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
+        self.events.iter()
+    }
+
+    /// Take all events out of the collection, leaving it empty.
+    pub fn drain(&mut self) -> impl Iterator<Item=T> + '_ {
+        self.events.drain(..)
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
@@ -6,4 +6,8 @@ extern crate core;
 mod component;
 pub mod entity;
 pub mod world;
-mod archetype;
\ No newline at end of file
+mod archetype;
+pub mod event;
+pub mod schedule;
+pub mod state;
+pub mod error;
\ No newline at end of file
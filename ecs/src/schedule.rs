@@ -0,0 +1,188 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+use crate::state::{StateMachine, StateRunner};
+use crate::world::World;
+
+pub type System = Box<dyn FnMut(&mut World)>;
+
+/// The stages of a frame in the order they are executed by the schedule.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Stage {
+    PreUpdate,
+    Update,
+    PostUpdate,
+}
+
+impl Stage {
+    pub fn get_stages_in_order() -> Vec<Self> {
+        vec![Stage::PreUpdate, Stage::Update, Stage::PostUpdate]
+    }
+}
+
+/// CPU time a single system took during the last run of the schedule.
+/// State transition systems are not part of a stage, their stage is None.
+#[derive(Clone, Debug)]
+pub struct SystemTiming {
+    pub name: String,
+    pub stage: Option<Stage>,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct StageTiming {
+    pub stage: Stage,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ScheduleTimings {
+    pub stages: Vec<StageTiming>,
+    pub systems: Vec<SystemTiming>,
+}
+
+pub(crate) struct SystemEntry {
+    pub(crate) name: String,
+    pub(crate) system: System,
+}
+
+impl SystemEntry {
+    fn new(name: &str, system: impl FnMut(&mut World) + 'static) -> Self {
+        Self {
+            name: name.to_string(),
+            system: Box::new(system),
+        }
+    }
+
+    /// Run the system and record its duration if timings are collected.
+    pub(crate) fn run(&mut self, world: &mut World, stage: Option<Stage>, timings: &mut Option<ScheduleTimings>) {
+        let timings = match timings {
+            Some(timings) => timings,
+            None => {
+                (self.system)(world);
+                return;
+            }
+        };
+
+        let start = Instant::now();
+        (self.system)(world);
+        timings.systems.push(SystemTiming {
+            name: self.name.clone(),
+            stage,
+            start,
+            duration: start.elapsed(),
+        });
+    }
+}
+
+/// Holds all systems of the application and runs them stage by stage on the world.
+/// State transitions are applied before the first stage, the update systems of the active states run
+/// at the end of the update stage.
+pub struct Schedule {
+    stages: Vec<(Stage, Vec<SystemEntry>)>,
+    state_machines: Vec<Box<dyn StateRunner>>,
+    timings: Option<ScheduleTimings>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self {
+            stages: Stage::get_stages_in_order().into_iter().map(|stage| (stage, Vec::new())).collect(),
+            state_machines: Vec::new(),
+            timings: None,
+        }
+    }
+
+    /// Enable or disable the collection of system and stage timings.
+    pub fn set_timings_enabled(&mut self, enabled: bool) {
+        self.timings = if enabled { Some(ScheduleTimings::default()) } else { None };
+    }
+
+    /// Get the timings of the last run, if timings are enabled.
+    pub fn get_timings(&self) -> Option<&ScheduleTimings> {
+        self.timings.as_ref()
+    }
+
+    /// Add a system to the given stage. Systems of the same stage run in the order they have been added.
+    pub fn add_system(&mut self, stage: Stage, name: &str, system: impl FnMut(&mut World) + 'static) {
+        let (_, systems) = self.stages.iter_mut().find(|(s, _)| *s == stage).unwrap();
+        systems.push(SystemEntry::new(name, system));
+    }
+
+    /// Register the state type S with its initial value. The State<S> and NextState<S> resources are inserted
+    /// into the world, the enter systems of the initial state run in the first frame.
+    pub fn add_state<S: Copy + Eq + Hash + Debug + 'static>(&mut self, world: &mut World, initial: S) {
+        if self.get_state_machine::<S>().is_some() {
+            panic!("Attempted to add the state {:?} twice!", initial);
+        }
+        self.state_machines.push(Box::new(StateMachine::new(world, initial)));
+    }
+
+    /// Add a system that runs once every time the state is entered.
+    pub fn add_system_on_enter<S: Copy + Eq + Hash + Debug + 'static>(&mut self, state: S, name: &str, system: impl FnMut(&mut World) + 'static) {
+        self.get_state_machine_or_panic(state).add_on_enter(state, SystemEntry::new(name, system));
+    }
+
+    /// Add a system that runs every frame while the state is active.
+    pub fn add_system_on_update<S: Copy + Eq + Hash + Debug + 'static>(&mut self, state: S, name: &str, system: impl FnMut(&mut World) + 'static) {
+        self.get_state_machine_or_panic(state).add_on_update(state, SystemEntry::new(name, system));
+    }
+
+    /// Add a system that runs once every time the state is left.
+    pub fn add_system_on_exit<S: Copy + Eq + Hash + Debug + 'static>(&mut self, state: S, name: &str, system: impl FnMut(&mut World) + 'static) {
+        self.get_state_machine_or_panic(state).add_on_exit(state, SystemEntry::new(name, system));
+    }
+
+    pub fn get_system_names(&self, stage: Stage) -> Vec<&str> {
+        match self.stages.iter().find(|(s, _)| *s == stage) {
+            Some((_, systems)) => systems.iter().map(|entry| entry.name.as_str()).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Run all systems of all stages once.
+    pub fn run(&mut self, world: &mut World) {
+        if let Some(timings) = self.timings.as_mut() {
+            timings.stages.clear();
+            timings.systems.clear();
+        }
+
+        for state_machine in self.state_machines.iter_mut() {
+            state_machine.apply_transition(world, &mut self.timings);
+        }
+
+        for (stage, systems) in self.stages.iter_mut() {
+            let stage_start = Instant::now();
+            for entry in systems.iter_mut() {
+                entry.run(world, Some(*stage), &mut self.timings);
+            }
+
+            if *stage == Stage::Update {
+                for state_machine in self.state_machines.iter_mut() {
+                    state_machine.run_update(world, &mut self.timings);
+                }
+            }
+
+            if let Some(timings) = self.timings.as_mut() {
+                timings.stages.push(StageTiming {
+                    stage: *stage,
+                    start: stage_start,
+                    duration: stage_start.elapsed(),
+                });
+            }
+        }
+    }
+
+    fn get_state_machine<S: Copy + Eq + Hash + Debug + 'static>(&mut self) -> Option<&mut StateMachine<S>> {
+        self.state_machines.iter_mut().find_map(|state_machine| state_machine.as_any_mut().downcast_mut::<StateMachine<S>>())
+    }
+
+    fn get_state_machine_or_panic<S: Copy + Eq + Hash + Debug + 'static>(&mut self, state: S) -> &mut StateMachine<S> {
+        match self.get_state_machine::<S>() {
+            Some(state_machine) => state_machine,
+            None => panic!("Attempted to add a system to the state {:?} which has not been added to the schedule!", state),
+        }
+    }
+}
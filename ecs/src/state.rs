@@ -0,0 +1,125 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use log::{debug, warn};
+use crate::entity::Entity;
+use crate::schedule::{ScheduleTimings, Stage, SystemEntry};
+use crate::world::World;
+
+/// Resource holding the currently active value of the state type S.
+pub struct State<S> {
+    current: S,
+}
+
+impl<S: Copy> State<S> {
+    pub fn get(&self) -> S {
+        self.current
+    }
+}
+
+/// Resource to request a transition of the state type S. The transition is applied at the beginning of the next frame.
+pub struct NextState<S> {
+    next: Option<S>,
+}
+
+impl<S> NextState<S> {
+    pub fn set(&mut self, state: S) {
+        self.next = Some(state);
+    }
+}
+
+/// Component marking an entity as belonging to a state. The entity will be despawned when the state is left.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct StateScoped<S>(pub S);
+
+pub(crate) trait StateRunner {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn apply_transition(&mut self, world: &mut World, timings: &mut Option<ScheduleTimings>);
+    fn run_update(&mut self, world: &mut World, timings: &mut Option<ScheduleTimings>);
+}
+
+/// Holds the enter, update and exit systems of all values of the state type S.
+pub(crate) struct StateMachine<S> {
+    initial: Option<S>,
+    on_enter: HashMap<S, Vec<SystemEntry>>,
+    on_update: HashMap<S, Vec<SystemEntry>>,
+    on_exit: HashMap<S, Vec<SystemEntry>>,
+}
+
+impl<S: Copy + Eq + Hash + Debug + 'static> StateMachine<S> {
+    pub(crate) fn new(world: &mut World, initial: S) -> Self {
+        world.insert_resource(State { current: initial });
+        world.insert_resource(NextState::<S> { next: None });
+        Self {
+            initial: Some(initial),
+            on_enter: HashMap::new(),
+            on_update: HashMap::new(),
+            on_exit: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn add_on_enter(&mut self, state: S, system: SystemEntry) {
+        self.on_enter.entry(state).or_insert_with(Vec::new).push(system);
+    }
+
+    pub(crate) fn add_on_update(&mut self, state: S, system: SystemEntry) {
+        self.on_update.entry(state).or_insert_with(Vec::new).push(system);
+    }
+
+    pub(crate) fn add_on_exit(&mut self, state: S, system: SystemEntry) {
+        self.on_exit.entry(state).or_insert_with(Vec::new).push(system);
+    }
+
+    fn run_systems(systems: &mut HashMap<S, Vec<SystemEntry>>, state: S, world: &mut World, stage: Option<Stage>, timings: &mut Option<ScheduleTimings>) {
+        if let Some(systems) = systems.get_mut(&state) {
+            for entry in systems.iter_mut() {
+                entry.run(world, stage, timings);
+            }
+        }
+    }
+
+    fn despawn_scoped_entities(world: &mut World, state: S) {
+        let entities: Vec<Entity> = match world.get_all_components_of_type::<StateScoped<S>>() {
+            Some(scoped) => scoped.iter().filter(|(scope, _)| scope.0 == state).map(|(_, entity)| *entity).collect(),
+            None => return,
+        };
+        for entity in entities {
+            if let Err(e) = world.remove_entity(entity) {
+                warn!("Could not despawn the entity scoped to state {:?}: {}", state, e);
+            }
+        }
+    }
+}
+
+impl<S: Copy + Eq + Hash + Debug + 'static> StateRunner for StateMachine<S> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn apply_transition(&mut self, world: &mut World, timings: &mut Option<ScheduleTimings>) {
+        if let Some(initial) = self.initial.take() {
+            StateMachine::run_systems(&mut self.on_enter, initial, world, None, timings);
+        }
+
+        let next = match world.get_resource_mut::<NextState<S>>().and_then(|next| next.next.take()) {
+            Some(next) => next,
+            None => return,
+        };
+        let current = world.get_resource::<State<S>>().unwrap().current;
+        if next == current {
+            return;
+        }
+
+        debug!("Transition from state {:?} to {:?}", current, next);
+        StateMachine::run_systems(&mut self.on_exit, current, world, None, timings);
+        StateMachine::despawn_scoped_entities(world, current);
+        world.get_resource_mut::<State<S>>().unwrap().current = next;
+        StateMachine::run_systems(&mut self.on_enter, next, world, None, timings);
+    }
+
+    fn run_update(&mut self, world: &mut World, timings: &mut Option<ScheduleTimings>) {
+        let current = world.get_resource::<State<S>>().unwrap().current;
+        StateMachine::run_systems(&mut self.on_update, current, world, Some(Stage::Update), timings);
+    }
+}
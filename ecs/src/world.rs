@@ -1,11 +1,17 @@
+use std::any::{type_name, Any, TypeId};
+use log::warn;
 use std::collections::HashMap;
 use crate::archetype::Archetype;
 use crate::entity::{Entity, EntityGenerator};
+use crate::error::EcsError;
+use crate::event::Events;
 
 pub struct World {
     entity_generator: EntityGenerator,
     entity_location_map: HashMap<Entity, usize>,
     archetypes: Vec<Archetype>,
+    resources: HashMap<TypeId, Box<dyn Any>>,
+    event_clearers: Vec<fn(&mut World)>,
 }
 
 impl World {
@@ -14,6 +20,8 @@ impl World {
             entity_generator: EntityGenerator::new(),
             entity_location_map: HashMap::new(),
             archetypes: Vec::new(),
+            resources: HashMap::new(),
+            event_clearers: Vec::new(),
         }
     }
 
@@ -39,24 +47,18 @@ impl World {
         entity_id
     }
 
-    pub fn remove_entity(&mut self, entity: Entity) {
-        match self.entity_location_map.get(&entity) {
-            Some(index) => {
-                self.archetypes[*index].remove_entity(&entity);
-            }
-            None => panic!("Attempted to delete an entity which is not referenced by any archetype!"),
-        };
+    pub fn remove_entity(&mut self, entity: Entity) -> Result<(), EcsError> {
+        let archetype_index = self.get_archetype_index(&entity)?;
+        self.archetypes[archetype_index].remove_entity(&entity);
 
         self.entity_location_map.remove_entry(&entity);
 
         self.entity_generator.desapwn(entity);
+        Ok(())
     }
 
-    pub fn add_component<ComponentType: 'static>(&mut self, entity: &Entity, component: ComponentType) {
-        let old_archetype_index = match self.entity_location_map.get(entity) {
-            Some(index) => *index,
-            None => panic!("Attemted to access a non existing entity!"),
-        };
+    pub fn add_component<ComponentType: 'static>(&mut self, entity: &Entity, component: ComponentType) -> Result<(), EcsError> {
+        let old_archetype_index = self.get_archetype_index(entity)?;
 
         let new_archetype = Archetype::new_from_add::<ComponentType>(&self.archetypes[old_archetype_index]);
 
@@ -67,13 +69,11 @@ impl World {
         target_archetype.migrate_entity_from(old_archetype, entity);
         target_archetype.set_component_instance(component);
         *self.entity_location_map.get_mut(entity).unwrap() = migration_target_index;
+        Ok(())
     }
 
-    pub fn remove_component<ComponentType: 'static>(&mut self, entity: &Entity) {
-        let old_archetype_index = match self.entity_location_map.get(entity) {
-            Some(index) => *index,
-            None => panic!("Attemted to access a non existing entity!"),
-        };
+    pub fn remove_component<ComponentType: 'static>(&mut self, entity: &Entity) -> Result<(), EcsError> {
+        let old_archetype_index = self.get_archetype_index(entity)?;
 
         let new_archetype = Archetype::new_from_remove::<ComponentType>(&self.archetypes[old_archetype_index]);
 
@@ -83,6 +83,7 @@ impl World {
 
         target_archetype.migrate_entity_from(old_archetype, entity);
         *self.entity_location_map.get_mut(entity).unwrap() = migration_target_index;
+        Ok(())
     }
 
     pub fn get_component<ComponentType: 'static>(&self, entity: &Entity) -> Option<&ComponentType>{
@@ -141,6 +142,65 @@ impl World {
         Some(all_instances)
     }
 
+    /// Insert a resource into the world. A resource is a unique instance of a type that is not bound to an entity.
+    /// If a resource of the same type already exists, it will be replaced.
+    pub fn insert_resource<ResourceType: 'static>(&mut self, resource: ResourceType) {
+        self.resources.insert(TypeId::of::<ResourceType>(), Box::new(resource));
+    }
+
+    pub fn remove_resource<ResourceType: 'static>(&mut self) -> Option<ResourceType> {
+        let resource = self.resources.remove(&TypeId::of::<ResourceType>())?;
+        Some(*resource.downcast::<ResourceType>().ok()?)
+    }
+
+    pub fn has_resource<ResourceType: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<ResourceType>())
+    }
+
+    pub fn get_resource<ResourceType: 'static>(&self) -> Option<&ResourceType> {
+        self.resources.get(&TypeId::of::<ResourceType>())?.downcast_ref::<ResourceType>()
+    }
+
+    pub fn get_resource_mut<ResourceType: 'static>(&mut self) -> Option<&mut ResourceType> {
+        self.resources.get_mut(&TypeId::of::<ResourceType>())?.downcast_mut::<ResourceType>()
+    }
+
+    /// Register a new event type. The events of this type are stored as resource and will be cleared
+    /// every time clear_events is called.
+    pub fn add_event<EventType: 'static>(&mut self) {
+        if self.has_resource::<Events<EventType>>() {
+            return;
+        }
+        self.insert_resource(Events::<EventType>::new());
+        self.event_clearers.push(World::clear_events_of_type::<EventType>);
+    }
+
+    /// Send an event of an already registered event type. Events of unregistered types will be dropped.
+    pub fn send_event<EventType: 'static>(&mut self, event: EventType) {
+        match self.get_resource_mut::<Events<EventType>>() {
+            Some(events) => events.send(event),
+            None => warn!("Dropped an event of the unregistered type {}", type_name::<EventType>()),
+        }
+    }
+
+    pub fn get_events<EventType: 'static>(&self) -> Option<&Events<EventType>> {
+        self.get_resource::<Events<EventType>>()
+    }
+
+    /// Remove all events of all registered event types. Should be called once at the end of every frame.
+    pub fn clear_events(&mut self) {
+        let clearers = self.event_clearers.clone();
+        for clear in clearers {
+            clear(self);
+        }
+    }
+
+    fn clear_events_of_type<EventType: 'static>(world: &mut World) {
+        if let Some(events) = world.get_resource_mut::<Events<EventType>>() {
+            events.clear();
+        }
+    }
+
     /* pub fn remove_entity(&mut self, entity: Entity){
          for component_vec in self.component_vecs.iter_mut(){
              component_vec.set_none(entity)
@@ -198,6 +258,17 @@ impl World {
 
  */
 
+    /// Get the index of the archetype the entity belongs to, or why the entity is not part of the world.
+    fn get_archetype_index(&self, entity: &Entity) -> Result<usize, EcsError> {
+        match self.entity_location_map.get(entity) {
+            Some(index) => Ok(*index),
+            None => {
+                self.entity_generator.is_alive(*entity)?;
+                Err(EcsError::EntityNotFound(*entity))
+            }
+        }
+    }
+
     fn find_matching_archetype_or_create_new(&mut self, new_archetype: Archetype) -> usize {
         let matching_archetype_index = match self.archetypes.iter().position(|at| at.type_id == new_archetype.type_id) {
             Some(index) => index,
@@ -240,10 +311,10 @@ mod tests {
         let entity_a = world.new_entity();
         let _entity_b = world.new_entity();
 
-        world.remove_entity(entity_a);
+        world.remove_entity(entity_a).unwrap();
 
         assert_eq!(world.archetypes.len(), 1);
-        assert_eq!(world.entity_generator.is_alive(entity_a), false);
+        assert_eq!(world.entity_generator.is_alive(entity_a), Ok(false));
         assert_eq!(world.archetypes[0].entities.len(), 1);
     }
 
@@ -257,9 +328,9 @@ mod tests {
         let entity_c = world.new_entity();
 
         let mock = Mock(42);
-        world.add_component(&entity_a, mock);
+        world.add_component(&entity_a, mock).unwrap();
         let mock2 = Mock(2);
-        world.add_component(&entity_c, mock2);
+        world.add_component(&entity_c, mock2).unwrap();
 
         assert_eq!(world.archetypes.len(), 2);
         assert!(world.archetypes[0].entities.contains(&entity_b));
@@ -276,10 +347,10 @@ mod tests {
         let mock_a = Mock(10);
         let mock_b = Mock(20);
 
-        world.add_component(&entity_a, mock_a);
-        world.add_component(&entity_b, mock_b);
+        world.add_component(&entity_a, mock_a).unwrap();
+        world.add_component(&entity_b, mock_b).unwrap();
 
-        world.remove_component::<Mock>(&entity_a);
+        world.remove_component::<Mock>(&entity_a).unwrap();
 
         assert_eq!(world.archetypes.len(), 2);
         assert!(world.archetypes[0].entities.contains(&entity_a));
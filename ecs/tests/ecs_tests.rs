@@ -1,5 +1,10 @@
 #[cfg(test)]
 mod ecs_tests {
+    use std::sync::{Mutex, Once};
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use resa_ecs::error::EcsError;
+    use resa_ecs::schedule::{Schedule, Stage};
+    use resa_ecs::state::{NextState, State, StateScoped};
     use resa_ecs::world::World;
 
     struct Demo {
@@ -10,6 +15,39 @@ mod ecs_tests {
         val: String,
     }
 
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+    enum AppState {
+        Menu,
+        Game,
+    }
+
+    /// Logger keeping all records, so tests can assert on the diagnostics of the ecs.
+    struct CaptureLogger {
+        records: Mutex<Vec<(Level, String, String)>>,
+    }
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.records.lock().unwrap().push((record.level(), record.target().to_string(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger { records: Mutex::new(Vec::new()) };
+    static INIT_LOGGER: Once = Once::new();
+
+    fn init_capture_logger() {
+        INIT_LOGGER.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(LevelFilter::Trace);
+        });
+    }
+
     #[test]
     fn check_component_data() {
         let mut world = World::new();
@@ -22,10 +60,10 @@ mod ecs_tests {
         let demo_c = Demo { val: 100};
         let demo_c2 = Demo2{ val: "Nothing".to_string()};
 
-        world.add_component(&entity_a, demo_a);
-        world.add_component(&entity_b, demo_b);
-        world.add_component(&entity_c, demo_c);
-        world.add_component(&entity_c, demo_c2);
+        world.add_component(&entity_a, demo_a).unwrap();
+        world.add_component(&entity_b, demo_b).unwrap();
+        world.add_component(&entity_c, demo_c).unwrap();
+        world.add_component(&entity_c, demo_c2).unwrap();
 
         let received_demo_a = world.get_component::<Demo>(&entity_a).unwrap();
         let received_demo_b = world.get_component::<Demo>(&entity_b).unwrap();
@@ -45,8 +83,8 @@ mod ecs_tests {
         let demo_a = Demo { val: 42 };
         let demo_b = Demo { val: 24 };
 
-        world.add_component(&entity_a, demo_a);
-        world.add_component(&entity_b, demo_b);
+        world.add_component(&entity_a, demo_a).unwrap();
+        world.add_component(&entity_b, demo_b).unwrap();
 
         let mut received_demo_a = world.get_component_mut::<Demo>(&entity_a).unwrap();
 
@@ -69,10 +107,10 @@ mod ecs_tests {
         let demo_c = Demo{val: 25};
         let demo_c2 = Demo2{val: "Test02".to_string()};
 
-        world.add_component(&entity_a, demo_a);
-        world.add_component(&entity_b, demo_b);
-        world.add_component(&entity_c, demo_c);
-        world.add_component(&entity_c, demo_c2);
+        world.add_component(&entity_a, demo_a).unwrap();
+        world.add_component(&entity_b, demo_b).unwrap();
+        world.add_component(&entity_c, demo_c).unwrap();
+        world.add_component(&entity_c, demo_c2).unwrap();
 
         let demo_result = world.get_all_components_of_type::<Demo>().unwrap();
         let demo2_result = world.get_all_components_of_type::<Demo2>().unwrap();
@@ -86,4 +124,142 @@ mod ecs_tests {
         assert_eq!(demo2_result.len(), 2);
         assert!(demo2_result[0].0.val == "Test01".to_string() && demo2_result[1].0.val == "Test02".to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn insert_and_change_resource() {
+        let mut world = World::new();
+        world.insert_resource(Demo { val: 1 });
+
+        world.get_resource_mut::<Demo>().unwrap().val = 5;
+
+        assert_eq!(world.get_resource::<Demo>().unwrap().val, 5);
+        assert!(world.get_resource::<Demo2>().is_none());
+        assert_eq!(world.remove_resource::<Demo>().unwrap().val, 5);
+        assert!(!world.has_resource::<Demo>());
+    }
+
+    #[test]
+    fn send_and_clear_events() {
+        let mut world = World::new();
+        world.add_event::<Demo>();
+
+        world.send_event(Demo { val: 1 });
+        world.send_event(Demo { val: 2 });
+        let values: Vec<u32> = world.get_events::<Demo>().unwrap().iter().map(|event| event.val).collect();
+        assert_eq!(values, vec![1, 2]);
+
+        world.clear_events();
+        assert!(world.get_events::<Demo>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn run_systems_in_stage_order() {
+        let mut world = World::new();
+        world.insert_resource(Demo2 { val: String::new() });
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::PostUpdate, "post", |world: &mut World| world.get_resource_mut::<Demo2>().unwrap().val.push('c'));
+        schedule.add_system(Stage::Update, "update_a", |world: &mut World| world.get_resource_mut::<Demo2>().unwrap().val.push('a'));
+        schedule.add_system(Stage::Update, "update_b", |world: &mut World| world.get_resource_mut::<Demo2>().unwrap().val.push('b'));
+        schedule.run(&mut world);
+
+        assert_eq!(world.get_resource::<Demo2>().unwrap().val, "abc");
+        assert_eq!(schedule.get_system_names(Stage::Update), vec!["update_a", "update_b"]);
+    }
+
+    #[test]
+    fn collect_system_timings() {
+        let mut world = World::new();
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, "first", |_: &mut World| {});
+        schedule.add_system(Stage::PostUpdate, "second", |_: &mut World| {});
+
+        schedule.run(&mut world);
+        assert!(schedule.get_timings().is_none());
+
+        schedule.set_timings_enabled(true);
+        schedule.run(&mut world);
+        let timings = schedule.get_timings().unwrap();
+
+        assert_eq!(timings.stages.len(), 3);
+        let system_names: Vec<&str> = timings.systems.iter().map(|timing| timing.name.as_str()).collect();
+        assert_eq!(system_names, vec!["first", "second"]);
+        assert_eq!(timings.systems[1].stage, Some(Stage::PostUpdate));
+    }
+
+    #[test]
+    fn run_state_systems_on_transition() {
+        let mut world = World::new();
+        world.insert_resource(Demo2 { val: String::new() });
+
+        let mut schedule = Schedule::new();
+        schedule.add_state(&mut world, AppState::Menu);
+        schedule.add_system_on_enter(AppState::Menu, "enter_menu", |world: &mut World| world.get_resource_mut::<Demo2>().unwrap().val.push_str("menu+"));
+        schedule.add_system_on_update(AppState::Menu, "update_menu", |world: &mut World| world.get_resource_mut::<Demo2>().unwrap().val.push_str("menu "));
+        schedule.add_system_on_exit(AppState::Menu, "exit_menu", |world: &mut World| world.get_resource_mut::<Demo2>().unwrap().val.push_str("menu-"));
+        schedule.add_system_on_enter(AppState::Game, "enter_game", |world: &mut World| world.get_resource_mut::<Demo2>().unwrap().val.push_str("game+"));
+        schedule.add_system_on_update(AppState::Game, "update_game", |world: &mut World| world.get_resource_mut::<Demo2>().unwrap().val.push_str("game "));
+
+        schedule.run(&mut world);
+        world.get_resource_mut::<NextState<AppState>>().unwrap().set(AppState::Game);
+        schedule.run(&mut world);
+
+        assert_eq!(world.get_resource::<State<AppState>>().unwrap().get(), AppState::Game);
+        assert_eq!(world.get_resource::<Demo2>().unwrap().val, "menu+menu menu-game+game ");
+    }
+
+    #[test]
+    fn despawn_state_scoped_entities() {
+        let mut world = World::new();
+        let mut schedule = Schedule::new();
+        schedule.add_state(&mut world, AppState::Menu);
+
+        let menu_entity = world.new_entity();
+        world.add_component(&menu_entity, StateScoped(AppState::Menu)).unwrap();
+        let game_entity = world.new_entity();
+        world.add_component(&game_entity, StateScoped(AppState::Game)).unwrap();
+        let global_entity = world.new_entity();
+        world.add_component(&global_entity, Demo { val: 1 }).unwrap();
+
+        schedule.run(&mut world);
+        world.get_resource_mut::<NextState<AppState>>().unwrap().set(AppState::Game);
+        schedule.run(&mut world);
+
+        let scoped = world.get_all_components_of_type::<StateScoped<AppState>>().unwrap();
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].1, game_entity);
+        assert_eq!(world.get_component::<Demo>(&global_entity).unwrap().val, 1);
+    }
+
+    #[test]
+    fn access_removed_entity() {
+        let mut world = World::new();
+        let entity = world.new_entity();
+        world.remove_entity(entity).unwrap();
+
+        assert_eq!(world.add_component(&entity, Demo { val: 1 }), Err(EcsError::EntityNotFound(entity)));
+        assert_eq!(world.remove_entity(entity), Err(EcsError::EntityNotFound(entity)));
+    }
+
+    #[test]
+    fn access_entity_that_was_never_spawned() {
+        let mut world = World::new();
+        let entity = resa_ecs::entity::Entity(7);
+
+        assert_eq!(world.add_component(&entity, Demo { val: 1 }), Err(EcsError::EntityNotSpawned(entity)));
+        assert_eq!(world.remove_component::<Demo>(&entity), Err(EcsError::EntityNotSpawned(entity)));
+    }
+
+    #[test]
+    fn log_dropped_events() {
+        init_capture_logger();
+        struct Unregistered;
+
+        let mut world = World::new();
+        world.send_event(Unregistered);
+
+        let records = LOGGER.records.lock().unwrap();
+        assert!(records.iter().any(|(level, target, message)|
+            *level == Level::Warn && target == "resa_ecs::world" && message.contains("Unregistered")));
+    }
+}
@@ -0,0 +1,96 @@
+/// Axis aligned bounding box, also used as bounding sphere around its center.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds {
+	pub min: [f32; 3],
+	pub max: [f32; 3],
+}
+
+impl Bounds {
+	/// The bounds of the vertex positions, an empty vertex list results in bounds around the origin.
+	pub fn from_positions(positions: &[[f32; 3]]) -> Self {
+		if positions.is_empty() {
+			return Bounds { min: [0.0; 3], max: [0.0; 3] };
+		}
+
+		let mut bounds = Bounds { min: [f32::MAX; 3], max: [f32::MIN; 3] };
+		for position in positions.iter() {
+			for axis in 0..3 {
+				bounds.min[axis] = bounds.min[axis].min(position[axis]);
+				bounds.max[axis] = bounds.max[axis].max(position[axis]);
+			}
+		}
+		bounds
+	}
+
+	pub fn get_center(&self) -> [f32; 3] {
+		[0, 1, 2].map(|axis| (self.min[axis] + self.max[axis]) * 0.5)
+	}
+
+	/// Radius of the bounding sphere around the center.
+	pub fn get_radius(&self) -> f32 {
+		let [x, y, z] = [0, 1, 2].map(|axis| (self.max[axis] - self.min[axis]) * 0.5);
+		(x * x + y * y + z * z).sqrt()
+	}
+
+	/// The axis aligned box around the transformed box. The matrix is column major.
+	pub fn transform(&self, matrix: &[[f32; 4]; 4]) -> Self {
+		let mut min = [matrix[3][0], matrix[3][1], matrix[3][2]];
+		let mut max = min;
+		for row in 0..3 {
+			for column in 0..3 {
+				let a = matrix[column][row] * self.min[column];
+				let b = matrix[column][row] * self.max[column];
+				min[row] += a.min(b);
+				max[row] += a.max(b);
+			}
+		}
+		Bounds { min, max }
+	}
+}
+
+/// The six planes of the view volume of a camera, with the normals pointing inside.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+	planes: [[f32; 4]; 6],
+}
+
+impl Frustum {
+	/// Extract the planes from the combined view projection matrix. The matrices are column major.
+	pub fn from_view_projection(view: &[[f32; 4]; 4], projection: &[[f32; 4]; 4]) -> Self {
+		let mut clip = [[0.0f32; 4]; 4];
+		for column in 0..4 {
+			for row in 0..4 {
+				clip[column][row] = (0..4).map(|k| projection[k][row] * view[column][k]).sum();
+			}
+		}
+
+		let row = |index: usize| [clip[0][index], clip[1][index], clip[2][index], clip[3][index]];
+		let add = |a: [f32; 4], b: [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+		let sub = |a: [f32; 4], b: [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+		let planes = [
+			add(row(3), row(0)),
+			sub(row(3), row(0)),
+			add(row(3), row(1)),
+			sub(row(3), row(1)),
+			// The near plane of a -1 to 1 depth range, which also contains the near plane of a 0 to 1 depth range
+			add(row(3), row(2)),
+			sub(row(3), row(2)),
+		];
+
+		Frustum {
+			planes: planes.map(|plane| {
+				let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+				if length > 0.0 { plane.map(|value| value / length) } else { plane }
+			}),
+		}
+	}
+
+	/// Test if the box is at least partly inside. Boxes close to the corners of the frustum may pass although they are outside.
+	pub fn intersects(&self, bounds: &Bounds) -> bool {
+		self.planes.iter().all(|plane| {
+			// The corner of the box furthest along the normal of the plane
+			let corner = [0, 1, 2].map(|axis| if plane[axis] >= 0.0 { bounds.max[axis] } else { bounds.min[axis] });
+			plane[0] * corner[0] + plane[1] * corner[1] + plane[2] * corner[2] + plane[3] >= 0.0
+		})
+	}
+}
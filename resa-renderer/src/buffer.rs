@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::mem::size_of;
 use std::ptr;
@@ -8,7 +9,8 @@ use gfx_hal::buffer::Usage;
 use gfx_hal::device::Device;
 use gfx_hal::memory::{Properties, Segment, SparseFlags};
 use crate::core::{CoreDevice};
-use crate::image_buffer::Dimensions;
+use crate::image_buffer::{Dimensions, TextureLevel};
+use crate::texture_image::TexelBlock;
 
 pub struct Buffer<B: Backend> {
 	memory: Option<B::Memory>,
@@ -61,6 +63,54 @@ impl<B: Backend> Buffer<B> {
 		}
 	}
 
+	/// Create a cpu visible buffer without initial data, e.g. as target of a copy from the gpu.
+	pub fn new_empty(device_ptr: Rc<RefCell<CoreDevice<B>>>, size: u64, usage: Usage, memory_types: &[MemoryType]) -> Self {
+		let mut buffer: B::Buffer;
+		let memory: B::Memory;
+		let allocated_size: u64;
+
+		unsafe {
+			let device = &device_ptr.borrow().device;
+			buffer = device.create_buffer(size, usage, SparseFlags::empty()).unwrap();
+			let mem_req = device.get_buffer_requirements(&buffer);
+
+			let memory_type = memory_types
+				.iter()
+				.enumerate()
+				.position(|(id, mem_type)| {
+					mem_req.type_mask & (1 << id) != 0 && mem_type.properties.contains(Properties::CPU_VISIBLE | Properties::COHERENT)
+				})
+				.unwrap()
+				.into();
+
+			memory = device.allocate_memory(memory_type, mem_req.size).unwrap();
+			device.bind_buffer_memory(&memory, 0, &mut buffer).unwrap();
+			allocated_size = mem_req.size;
+		}
+
+		Buffer {
+			memory: Some(memory),
+			buffer: Some(buffer),
+			device: device_ptr,
+			size: allocated_size,
+		}
+	}
+
+	/// Copy the first size bytes of the buffer to the cpu.
+	pub fn read_data(&mut self, size: u64) -> Vec<u8> {
+		let device = &self.device.borrow().device;
+		assert!(size <= self.size);
+		let memory = self.memory.as_mut().unwrap();
+
+		let mut data = vec![0u8; size as usize];
+		unsafe {
+			let mapping = device.map_memory(memory, Segment { offset: 0, size: Some(size) }).unwrap();
+			ptr::copy_nonoverlapping(mapping, data.as_mut_ptr(), size as usize);
+			device.unmap_memory(memory);
+		}
+		data
+	}
+
 	pub fn update_data<T>(&mut self, offset: u64, data_source: &[T]) where T: Copy {
 		let device = &self.device.borrow().device;
 		let stride = size_of::<T>();
@@ -76,14 +126,27 @@ impl<B: Backend> Buffer<B> {
 		}
 	}
 
-	pub fn new_texture(device_ptr: Rc<RefCell<CoreDevice<B>>>, img: &image::ImageBuffer<::image::Rgba<u8>, Vec<u8>>,adapter_limits: &Limits, memory_types: &[MemoryType], usage: Usage) -> (Self, Dimensions<u32>, u32, usize) {
-		let (width, height) = img.dimensions();
-
-		let row_alignment_mask = adapter_limits.optimal_buffer_copy_pitch_alignment as u32 - 1;
-		let stride = 4usize;
-
-		let row_pitch = (width * stride as u32 + row_alignment_mask) & !row_alignment_mask;
-		let upload_size = (height * row_pitch) as u64;
+	/// Staging buffer with the tightly packed levels of a texture, one after another. Every row and level starts at the
+	/// alignment the gpu copies fastest from. The rows of compressed textures are rows of blocks.
+	pub fn new_texture(device_ptr: Rc<RefCell<CoreDevice<B>>>, levels: &[Cow<[u8]>], dimensions: Dimensions<u32>, block: TexelBlock, adapter_limits: &Limits, memory_types: &[MemoryType], usage: Usage) -> (Self, Vec<TextureLevel>) {
+		// The rows and levels have to start at a multiple of the block size as well
+		let row_alignment_mask = (adapter_limits.optimal_buffer_copy_pitch_alignment as u32).max(block.size) - 1;
+		let offset_alignment_mask = (adapter_limits.optimal_buffer_copy_offset_alignment as u64).max(block.size as u64) - 1;
+
+		let mut texture_levels = vec![];
+		let mut upload_size = 0u64;
+		for (level, level_data) in levels.iter().enumerate() {
+			let width = (dimensions.width >> level).max(1);
+			let height = (dimensions.height >> level).max(1);
+			let row_size = ((width + block.width - 1) / block.width) * block.size;
+			let rows = (height + block.height - 1) / block.height;
+			assert_eq!(level_data.len(), (row_size * rows) as usize);
+
+			let offset = (upload_size + offset_alignment_mask) & !offset_alignment_mask;
+			let row_pitch = (row_size + row_alignment_mask) & !row_alignment_mask;
+			texture_levels.push(TextureLevel { offset, row_pitch, row_size, rows, dimensions: Dimensions { width, height } });
+			upload_size = offset + (rows * row_pitch) as u64;
+		}
 
 		let mut memroy: B::Memory;
 		let mut buffer: B::Buffer;
@@ -107,13 +170,16 @@ impl<B: Backend> Buffer<B> {
 			size = mem_reqs.size;
 
 			let mapping = device.map_memory(&mut memroy, Segment::ALL).unwrap();
-			for y in 0..height as usize {
-				let data_source_slice = &(**img)[y * (width as usize) * stride..(y + 1) * (width as usize) * stride];
-				ptr::copy_nonoverlapping(
-					data_source_slice.as_ptr(),
-					mapping.offset(y as isize * row_pitch as isize),
-					data_source_slice.len(),
-				);
+			for (level_data, texture_level) in levels.iter().zip(texture_levels.iter()) {
+				let row_size = texture_level.row_size as usize;
+				for row in 0..texture_level.rows as usize {
+					let data_source_slice = &level_data[row * row_size..(row + 1) * row_size];
+					ptr::copy_nonoverlapping(
+						data_source_slice.as_ptr(),
+						mapping.offset((texture_level.offset + row as u64 * texture_level.row_pitch as u64) as isize),
+						data_source_slice.len(),
+					);
+				}
 			}
 			device.unmap_memory(&mut memroy);
 		}
@@ -125,9 +191,7 @@ impl<B: Backend> Buffer<B> {
 				device: device_ptr,
 				size,
 			},
-			Dimensions { width, height },
-			row_pitch,
-			stride,
+			texture_levels,
 		)
 	}
 }
@@ -1,52 +1,56 @@
 use std::collections::{BinaryHeap, HashMap};
-use std::mem::ManuallyDrop;
-use std::ptr;
-use gfx_hal::{Backend, Instance, Limits};
+use gfx_hal::{Backend, Features, Instance, Limits};
 use gfx_hal::adapter::{Adapter, DeviceType, MemoryType, PhysicalDevice};
 use gfx_hal::format::{Format, ImageFeature};
 use gfx_hal::image::Tiling;
 use gfx_hal::prelude::{QueueFamily, Surface};
 use gfx_hal::queue::QueueGroup;
+use log::{debug, info};
 use winit::window::Window;
+use crate::error::RenderError;
 
+/// Connection to the gpu. The surface is only created if the renderer draws to a window.
 pub struct Core<B: Backend> {
-    pub surface: ManuallyDrop<B::Surface>,
+    pub surface: Option<B::Surface>,
     pub adapter: CoreAdapter<B>,
     pub instance: B::Instance,
 }
 
 impl<B: Backend> Core<B> {
-    pub fn create(window: &Window) -> Option<Self> {
-        let instance_result = Instance::create("RESA", 1);
-        if instance_result.is_err() {
-            println!("Creating an instance failed due to unsupported backend!");
-            return None;
-        }
-        let instance: B::Instance = instance_result.unwrap();
+    pub fn create(window: Option<&Window>) -> Result<Self, RenderError> {
+        let instance: B::Instance = Instance::create("RESA", 1).map_err(|_| RenderError::UnsupportedBackend)?;
 
         // Create the surface to render on
-        let surface_result = unsafe { instance.create_surface(&window) };
-        if surface_result.is_err() {
-            println!("Failed to create surface!");
-            return None;
-        }
-        let surface = surface_result.unwrap();
+        let surface = match window {
+            Some(window) => Some(unsafe { instance.create_surface(window) }.map_err(|_| RenderError::SurfaceCreationFailed)?),
+            None => None,
+        };
 
         let mut adapters = instance.enumerate_adapters();
+        let adapter = match CoreAdapter::new(&mut adapters) {
+            Ok(adapter) => adapter,
+            Err(e) => {
+                if let Some(surface) = surface {
+                    unsafe { instance.destroy_surface(surface) };
+                }
+                return Err(e);
+            }
+        };
 
-        Some(Self {
+        Ok(Self {
             instance,
-            adapter: CoreAdapter::new(&mut adapters),
-            surface: ManuallyDrop::new(surface),
+            adapter,
+            surface,
         })
     }
 }
 
 impl<B: Backend> Drop for Core<B> {
     fn drop(&mut self) {
-        unsafe {
-            let surface = ManuallyDrop::into_inner(ptr::read(&self.surface));
-            self.instance.destroy_surface(surface);
+        if let Some(surface) = self.surface.take() {
+            unsafe {
+                self.instance.destroy_surface(surface);
+            }
         }
     }
 }
@@ -58,14 +62,14 @@ pub struct CoreAdapter<B: Backend> {
 }
 
 impl<B: Backend> CoreAdapter<B> {
-    pub fn new(adapters: &mut Vec<Adapter<B>>) -> Self {
+    pub fn new(adapters: &mut Vec<Adapter<B>>) -> Result<Self, RenderError> {
 
         let mut adapter_map = HashMap::<u8, usize>::new();
         let mut adapter_prio = BinaryHeap::<u8>::new();
         for (index, adapter) in adapters.iter().enumerate() {
             let name = &adapter.info.name;
             let adapter_type = &adapter.info.device_type;
-            println!("GPU {} at index {}", name, index);
+            debug!("GPU {} at index {}", name, index);
             let type_value: u8 = match adapter_type{
                 DeviceType::Other => 0,
                 DeviceType::IntegratedGpu => 3,
@@ -77,12 +81,12 @@ impl<B: Backend> CoreAdapter<B> {
             adapter_prio.push(type_value.clone());
         }
 
-        let highest_prio = adapter_prio.pop().unwrap();
+        let highest_prio = adapter_prio.pop().ok_or(RenderError::NoAdapterFound)?;
         let adapter_index = adapter_map.get(&highest_prio).unwrap();
 
-        println!("Selected GPU at index {}", adapter_index);
+        info!("Selected GPU {} at index {}", adapters[*adapter_index].info.name, adapter_index);
 
-        CoreAdapter::<B>::new_adapter(adapters.remove(adapter_index.clone()))
+        Ok(CoreAdapter::<B>::new_adapter(adapters.remove(adapter_index.clone())))
     }
 
     fn new_adapter(adapter: Adapter<B>) -> Self {
@@ -98,29 +102,34 @@ impl<B: Backend> CoreAdapter<B> {
 
 pub struct CoreDevice<B: Backend> {
     pub device: B::Device,
+    /// The optional features which were enabled, if the gpu supports them.
+    pub features: Features,
     pub physical_device: B::PhysicalDevice,
     pub queues: QueueGroup<B>,
 }
 
 impl <B: Backend> CoreDevice<B> {
-    pub fn new(adapter: Adapter<B>, surface: &B::Surface) -> Self{
+    pub fn new(adapter: Adapter<B>, surface: Option<&B::Surface>) -> Self{
         let family = adapter
             .queue_families
             .iter()
             .find(|family|{
-                surface.supports_queue_family(family) && family.queue_type().supports_graphics()
+                let supports_surface = surface.map_or(true, |surface| surface.supports_queue_family(family));
+                supports_surface && family.queue_type().supports_graphics()
             })
             .unwrap();
 
+        let features = adapter.physical_device.features() & (Features::NON_FILL_POLYGON_MODE | Features::SAMPLER_ANISOTROPY | Features::FORMAT_BC);
         let mut gpu = unsafe{
             adapter
                 .physical_device
-                .open(&[(family, &[1.0])], gfx_hal::Features::empty())
+                .open(&[(family, &[1.0])], features)
                 .unwrap()
         };
 
         CoreDevice{
             device: gpu.device,
+            features,
             queues: gpu.queue_groups.pop().unwrap(),
             physical_device: adapter.physical_device,
         }
@@ -0,0 +1,117 @@
+use std::cmp::Ordering;
+use gfx_hal::Backend;
+use crate::material::MaterialRef;
+use crate::pipelines::PipelineKey;
+use crate::render_resources::material_library::MaterialLibrary;
+use crate::render_resources::mesh_library::MeshLibrary;
+use crate::render_stage::RenderStage;
+use crate::vertex::InstanceData;
+
+/// Consecutive objects of the draw list which share mesh and material, drawn with one instanced call.
+pub(crate) struct DrawBatch {
+	pub pipeline: PipelineKey,
+	pub mesh_id: u64,
+	pub material: MaterialRef,
+	pub first_instance: u32,
+	pub instance_count: u32,
+}
+
+struct DrawItem {
+	stage: RenderStage,
+	stage_order: usize,
+	pipeline: PipelineKey,
+	mesh_id: u64,
+	material: MaterialRef,
+	depth: f32,
+	model: [[f32; 4]; 4],
+}
+
+/// The render objects of a frame, sorted by stage and pipeline, then
+/// - opaque objects by pipeline, material, mesh and front to back, so state changes are minimal and hidden fragments are rejected early
+/// - transparent objects back to front, so they blend correctly
+/// - ui objects in the given order
+/// and grouped into batches. The instances of a batch are stored next to each other.
+pub(crate) struct DrawList {
+	stages: Vec<(RenderStage, Vec<DrawBatch>)>,
+}
+
+impl DrawList {
+	/// The instance data is appended to the given instances, so the draw lists of multiple views can share one instance buffer.
+	/// Objects with an unknown mesh or material are skipped.
+	pub(crate) fn new<B: Backend>(render_objects: &[(u64, MaterialRef, [[f32; 4]; 4])], view_mat: &[[f32; 4]; 4], materials: &MaterialLibrary<B>, meshes: &MeshLibrary<B>, instances: &mut Vec<InstanceData>) -> Self {
+		let stage_priority = RenderStage::get_stages_in_order_of_priority();
+		let mut items: Vec<DrawItem> = render_objects.iter().filter_map(|(mesh_id, material, model)| {
+			let material_data = materials.get_material(material)?;
+			let topology = meshes.get_mesh_topology(mesh_id)?;
+			let stage = material_data.render_stage;
+			let stage_order = stage_priority.iter().position(|priority_stage| priority_stage == &stage)?;
+			Some(DrawItem {
+				stage,
+				stage_order,
+				pipeline: PipelineKey::from_material(material_data).with_topology(topology),
+				mesh_id: *mesh_id,
+				material: *material,
+				depth: get_view_depth(view_mat, model),
+				model: *model,
+			})
+		}).collect();
+
+		items.sort_by(|a, b| a.stage_order.cmp(&b.stage_order).then_with(|| match a.stage {
+			RenderStage::Opaque => a.pipeline.shader_id.cmp(&b.pipeline.shader_id)
+				.then(a.pipeline.state.cmp(&b.pipeline.state))
+				.then(a.pipeline.topology.cmp(&b.pipeline.topology))
+				.then(a.material.0.cmp(&b.material.0))
+				.then(a.mesh_id.cmp(&b.mesh_id))
+				.then(compare_depth(a.depth, b.depth)),
+			RenderStage::Transparent => compare_depth(b.depth, a.depth),
+			_ => Ordering::Equal,
+		}));
+
+		let mut stages: Vec<(RenderStage, Vec<DrawBatch>)> = vec![];
+		instances.reserve(items.len());
+		for item in items {
+			if stages.last().map_or(true, |(stage, _)| stage != &item.stage) {
+				stages.push((item.stage, vec![]));
+			}
+			let batches = &mut stages.last_mut().unwrap().1;
+			match batches.last_mut() {
+				Some(batch) if batch.mesh_id == item.mesh_id && batch.material == item.material => batch.instance_count += 1,
+				_ => batches.push(DrawBatch {
+					pipeline: item.pipeline,
+					mesh_id: item.mesh_id,
+					material: item.material,
+					first_instance: instances.len() as u32,
+					instance_count: 1,
+				}),
+			}
+			instances.push(InstanceData { model: item.model });
+		}
+
+		DrawList {
+			stages,
+		}
+	}
+
+	pub(crate) fn get_pipeline_keys(&self) -> impl Iterator<Item = &PipelineKey> {
+		self.stages.iter().flat_map(|(_, batches)| batches.iter().map(|batch| &batch.pipeline))
+	}
+
+	pub(crate) fn get_stage_batches(&self, stage: &RenderStage) -> &[DrawBatch] {
+		match self.stages.iter().find(|(batch_stage, _)| batch_stage == stage) {
+			Some((_, batches)) => batches,
+			None => &[],
+		}
+	}
+}
+
+/// Distance of the objects origin to the camera along the view direction.
+fn get_view_depth(view_mat: &[[f32; 4]; 4], model: &[[f32; 4]; 4]) -> f32 {
+	let [x, y, z, _] = model[3];
+	let view_z = view_mat[0][2] * x + view_mat[1][2] * y + view_mat[2][2] * z + view_mat[3][2];
+	// The camera looks along the negative z axis
+	-view_z
+}
+
+fn compare_depth(a: f32, b: f32) -> Ordering {
+	a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
@@ -0,0 +1,108 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::io;
+use std::path::PathBuf;
+use crate::render_stage::RenderStage;
+use crate::vertex::{AttributeKind, VertexAttribute};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RenderError {
+	/// The graphics backend is not supported on this system.
+	UnsupportedBackend,
+	SurfaceCreationFailed,
+	NoAdapterFound,
+	InvalidRenderStageIndex(usize),
+	MeshNotFound(u64),
+	ShaderNotFound(u32),
+	/// The shaders or the fixed function state of the pipeline were rejected by the gpu.
+	PipelineCreationFailed(RenderStage),
+	/// No frame was drawn yet, or the readback of a swapchain frame was not requested before it was drawn.
+	NoFrameToRead,
+	AttributeKindMismatch { attribute: VertexAttribute, expected: AttributeKind, actual: AttributeKind },
+	/// An attribute needs one value per vertex of the mesh.
+	AttributeLengthMismatch { attribute: VertexAttribute, expected: usize, actual: usize },
+	/// A heightmap needs at least 2 by 2 samples and one sample per column and row.
+	InvalidHeightmap { columns: u32, rows: u32, samples: usize },
+}
+
+impl fmt::Display for RenderError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			RenderError::UnsupportedBackend => write!(f, "Creating an instance failed due to an unsupported backend"),
+			RenderError::SurfaceCreationFailed => write!(f, "Failed to create the surface of the window"),
+			RenderError::NoAdapterFound => write!(f, "No graphics adapter found"),
+			RenderError::InvalidRenderStageIndex(index) => write!(f, "{} is not a valid render stage index", index),
+			RenderError::MeshNotFound(mesh_id) => write!(f, "Mesh with id {} does not exist", mesh_id),
+			RenderError::ShaderNotFound(shader_id) => write!(f, "Shader with id {} does not exist", shader_id),
+			RenderError::PipelineCreationFailed(stage) => write!(f, "The gpu could not create the pipeline for the {}", stage),
+			RenderError::NoFrameToRead => write!(f, "There is no drawn frame which can be read back"),
+			RenderError::AttributeKindMismatch { attribute, expected, actual } =>
+				write!(f, "The {} attribute needs {:?} values, but {:?} values were given", attribute, expected, actual),
+			RenderError::AttributeLengthMismatch { attribute, expected, actual } =>
+				write!(f, "The {} attribute needs {} values, one per vertex, but {} values were given", attribute, expected, actual),
+			RenderError::InvalidHeightmap { columns, rows, samples } =>
+				write!(f, "A heightmap of {} by {} samples needs at least 2 by 2 samples and {} heights, but {} were given", columns, rows, *columns as u64 * *rows as u64, samples),
+		}
+	}
+}
+
+impl Error for RenderError {}
+
+/// Errors while creating a shader from its glsl sources.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ShaderError {
+	/// The glsl source of a stage, e.g. vert, could not be compiled to spir-v.
+	CompilationFailed { shader: String, stage: String, reason: String },
+	/// A shader needs a vertex and a fragment stage.
+	MissingStage { shader: String, stage: String },
+	/// The vertex shader reads locations which do not belong to any vertex attribute.
+	UnknownVertexLocations { shader: String, locations: Vec<u32> },
+}
+
+impl fmt::Display for ShaderError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			ShaderError::CompilationFailed { shader, stage, reason } => write!(f, "Could not compile the {} stage of shader {}: {}", stage, shader, reason),
+			ShaderError::MissingStage { shader, stage } => write!(f, "Shader {} has no {} stage", shader, stage),
+			ShaderError::UnknownVertexLocations { shader, locations } =>
+				write!(f, "Shader {} reads the locations {:?}, which do not belong to any vertex attribute", shader, locations),
+		}
+	}
+}
+
+impl Error for ShaderError {}
+
+/// Errors while saving a captured frame as image.
+#[derive(Debug)]
+pub enum CaptureError {
+	Render(RenderError),
+	Io { path: PathBuf, source: io::Error },
+	Encoding { path: PathBuf, source: image::ImageError },
+}
+
+impl fmt::Display for CaptureError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			CaptureError::Render(e) => write!(f, "Could not read the frame: {}", e),
+			CaptureError::Io { path, source } => write!(f, "Could not create {}: {}", path.display(), source),
+			CaptureError::Encoding { path, source } => write!(f, "Could not write the frame to {}: {}", path.display(), source),
+		}
+	}
+}
+
+impl Error for CaptureError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			CaptureError::Render(e) => Some(e),
+			CaptureError::Io { source, .. } => Some(source),
+			CaptureError::Encoding { source, .. } => Some(source),
+		}
+	}
+}
+
+impl From<RenderError> for CaptureError {
+	fn from(e: RenderError) -> Self {
+		CaptureError::Render(e)
+	}
+}
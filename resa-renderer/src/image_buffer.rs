@@ -1,12 +1,12 @@
 use std::cell::RefCell;
 use std::iter;
 use std::rc::Rc;
-use gfx_hal::{Backend, buffer, Limits};
-use gfx_hal::adapter::MemoryType;
-use gfx_hal::command::{BufferImageCopy, CommandBuffer, CommandBufferFlags, Level};
+use gfx_hal::{Backend, buffer, Features, Limits};
+use gfx_hal::adapter::{MemoryType, PhysicalDevice};
+use gfx_hal::command::{BufferImageCopy, CommandBuffer, CommandBufferFlags, ImageBlit, Level};
 use gfx_hal::device::Device;
-use gfx_hal::format::{Aspects, Format, Swizzle};
-use gfx_hal::image::{Access, Extent, Filter, Kind, Layout, Offset, SamplerDesc, SubresourceLayers, SubresourceRange, Tiling, Usage, ViewCapabilities, ViewKind, WrapMode};
+use gfx_hal::format::{Aspects, Format, ImageFeature, Swizzle};
+use gfx_hal::image::{Access, BorderColor, Extent, Filter, Kind, Layout, Offset, SamplerDesc, SubresourceLayers, SubresourceRange, Tiling, Usage, ViewCapabilities, ViewKind, WrapMode};
 use gfx_hal::memory::{Barrier, Dependencies, Properties, SparseFlags};
 use gfx_hal::pool::CommandPool;
 use gfx_hal::pso::{Descriptor, PipelineStage};
@@ -14,12 +14,27 @@ use gfx_hal::queue::Queue;
 use crate::buffer::Buffer;
 use crate::core::{CoreDevice};
 use crate::descriptors::{DescSet, DescSetWrite};
+use crate::material::{self, SamplerSettings, TextureFilter, TextureWrap};
+use crate::mipmap::get_mip_level_count;
+use crate::texture_image::TextureImage;
 
 pub struct Dimensions<T> {
 	pub width: T,
 	pub height: T,
 }
 
+/// Where a level of a texture is in its staging buffer.
+pub struct TextureLevel {
+	pub offset: u64,
+	/// Bytes from one row to the next
+	pub row_pitch: u32,
+	/// Bytes of the pixels or blocks of a row
+	pub row_size: u32,
+	pub rows: u32,
+	/// Size of the level in pixels
+	pub dimensions: Dimensions<u32>,
+}
+
 pub struct ImageBuffer<B: Backend> {
 	pub(crate) desc: DescSet<B>,
 	buffer: Option<Buffer<B>>,
@@ -39,24 +54,39 @@ pub struct Image<B: Backend> {
 impl<B: Backend> ImageBuffer<B> {
 	pub fn new(
 		mut desc: DescSet<B>,
-		img: &image::ImageBuffer<::image::Rgba<u8>, Vec<u8>>,
+		texture: &TextureImage,
+		sampler_settings: &SamplerSettings,
 		adapter_limits: &Limits,
 		memory_types: &[MemoryType],
 		usage: buffer::Usage,
 		device_ptr: Rc<RefCell<CoreDevice<B>>>,
 		staging_pool: &mut B::CommandPool) -> Self {
-		let (buffer, dimensions, row_pitch, stride) = Buffer::new_texture(Rc::clone(&device_ptr), img, &adapter_limits, memory_types, usage);
+		let format = texture.get_format();
+		let (width, height) = texture.get_dimensions();
+		let block = texture.get_texel_block();
+		let (mip_levels, blit_mip_levels) = match texture.get_level_count() {
+			Some(level_count) => (level_count, false),
+			None => {
+				let mip_levels = if sampler_settings.mipmaps { get_mip_level_count(width, height) } else { 1 };
+				// The gpu blits every level to the next one if it can filter the format, otherwise the levels are uploaded with the image
+				let blit_features = ImageFeature::BLIT_SRC | ImageFeature::BLIT_DST | ImageFeature::SAMPLED_LINEAR;
+				(mip_levels, mip_levels > 1 && device_ptr.borrow().physical_device.format_properties(Some(format)).optimal_tiling.contains(blit_features))
+			}
+		};
+		let levels = texture.get_levels(mip_levels > 1 && !blit_mip_levels);
+
+		let (buffer, texture_levels) = Buffer::new_texture(Rc::clone(&device_ptr), &levels, Dimensions { width, height }, block, &adapter_limits, memory_types, usage);
 
 		let buffer = Some(buffer);
-		let dimensions = Extent { width: dimensions.width, height: dimensions.height, depth: 1 };
-		let image = Image::new(Rc::clone(&device_ptr), &memory_types, dimensions, Format::Rgba8Srgb, Tiling::Optimal, Usage::TRANSFER_DST | Usage::SAMPLED, Properties::DEVICE_LOCAL, Aspects::COLOR, Usage::SAMPLED);
+		let dimensions = Extent { width, height, depth: 1 };
+		let image = Image::with_mip_levels(Rc::clone(&device_ptr), &memory_types, dimensions, mip_levels, format, Tiling::Optimal, Usage::TRANSFER_SRC | Usage::TRANSFER_DST | Usage::SAMPLED, Properties::DEVICE_LOCAL, Aspects::COLOR, Usage::SAMPLED);
 
 		let device_ref = &mut device_ptr.borrow_mut();
 		// let device = &mut device_ref.device;
 
 		let image_buffer = unsafe {
-			let sampler = device_ref.device.create_sampler(
-				&SamplerDesc::new(Filter::Linear, WrapMode::Clamp)).expect(" Cannot create sampler!");
+			let sampler_desc = get_sampler_desc(sampler_settings, &device_ref.features, adapter_limits);
+			let sampler = device_ref.device.create_sampler(&sampler_desc).expect(" Cannot create sampler!");
 
 			desc.write_to_state(DescSetWrite {
 				binding: 0,
@@ -103,24 +133,78 @@ impl<B: Backend> ImageBuffer<B> {
 					buffer.as_ref().unwrap().get(),
 					image.image.as_ref().unwrap(),
 					Layout::TransferDstOptimal,
-					iter::once(BufferImageCopy {
-						buffer_offset: 0,
-						buffer_width: row_pitch / (stride as u32),
-						buffer_height: dimensions.height as u32,
+					texture_levels.iter().enumerate().map(|(level, texture_level)| BufferImageCopy {
+						buffer_offset: texture_level.offset,
+						// In pixels, the rows of compressed textures are rows of blocks
+						buffer_width: texture_level.row_pitch / block.size * block.width,
+						buffer_height: texture_level.rows * block.height,
 						image_layers: SubresourceLayers {
 							aspects: Aspects::COLOR,
-							level: 0,
+							level: level as u8,
 							layers: 0..1,
 						},
 						image_offset: Offset { x: 0, y: 0, z: 0 },
 						image_extent: Extent {
-							width: dimensions.width,
-							height: dimensions.height,
+							width: texture_level.dimensions.width,
+							height: texture_level.dimensions.height,
 							depth: 1,
 						},
 					}),
 				);
 
+				// Every level is read by the blit to the next one, and then by the shaders
+				let mut first_written_level = 0;
+				if blit_mip_levels {
+					for level in 1..mip_levels {
+						let source_range = SubresourceRange {
+							aspects: Aspects::COLOR,
+							level_start: level - 1,
+							level_count: Some(1),
+							..Default::default()
+						};
+						let image_barrier = Barrier::Image {
+							states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
+								..(Access::TRANSFER_READ, Layout::TransferSrcOptimal),
+							target: image.image.as_ref().unwrap(),
+							families: None,
+							range: source_range.clone(),
+						};
+						command_buffer.pipeline_barrier(
+							PipelineStage::TRANSFER..PipelineStage::TRANSFER,
+							Dependencies::empty(),
+							iter::once(image_barrier),
+						);
+
+						command_buffer.blit_image(
+							image.image.as_ref().unwrap(),
+							Layout::TransferSrcOptimal,
+							image.image.as_ref().unwrap(),
+							Layout::TransferDstOptimal,
+							Filter::Linear,
+							iter::once(ImageBlit {
+								src_subresource: SubresourceLayers { aspects: Aspects::COLOR, level: level - 1, layers: 0..1 },
+								src_bounds: Offset { x: 0, y: 0, z: 0 }..get_level_size(&dimensions, level - 1),
+								dst_subresource: SubresourceLayers { aspects: Aspects::COLOR, level, layers: 0..1 },
+								dst_bounds: Offset { x: 0, y: 0, z: 0 }..get_level_size(&dimensions, level),
+							}),
+						);
+
+						let image_barrier = Barrier::Image {
+							states: (Access::TRANSFER_READ, Layout::TransferSrcOptimal)
+								..(Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
+							target: image.image.as_ref().unwrap(),
+							families: None,
+							range: source_range,
+						};
+						command_buffer.pipeline_barrier(
+							PipelineStage::TRANSFER..PipelineStage::FRAGMENT_SHADER,
+							Dependencies::empty(),
+							iter::once(image_barrier),
+						);
+					}
+					first_written_level = mip_levels - 1;
+				}
+
 				let image_barrier = Barrier::Image {
 					states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
 						..(Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
@@ -128,6 +212,7 @@ impl<B: Backend> ImageBuffer<B> {
 					families: None,
 					range: SubresourceRange {
 						aspects: Aspects::COLOR,
+						level_start: first_written_level,
 						..Default::default()
 					},
 				};
@@ -173,14 +258,65 @@ impl<B: Backend> ImageBuffer<B> {
 	}
 }
 
+/// Size of a mip level as the end of the area a blit reads or writes.
+fn get_level_size(dimensions: &Extent, level: u8) -> Offset {
+	Offset {
+		x: (dimensions.width >> level).max(1) as i32,
+		y: (dimensions.height >> level).max(1) as i32,
+		z: 1,
+	}
+}
+
+fn get_sampler_desc(settings: &SamplerSettings, features: &Features, adapter_limits: &Limits) -> SamplerDesc {
+	let get_filter = |filter: TextureFilter| match filter {
+		TextureFilter::Nearest => Filter::Nearest,
+		TextureFilter::Linear => Filter::Linear,
+	};
+	let wrap = match settings.wrap {
+		TextureWrap::Repeat => WrapMode::Tile,
+		TextureWrap::MirroredRepeat => WrapMode::Mirror,
+		TextureWrap::Clamp => WrapMode::Clamp,
+		TextureWrap::Border => WrapMode::Border,
+	};
+
+	let mut sampler_desc = SamplerDesc::new(get_filter(settings.min_filter), wrap);
+	sampler_desc.mag_filter = get_filter(settings.mag_filter);
+	sampler_desc.mip_filter = get_filter(settings.mip_filter);
+	sampler_desc.border = match settings.border_color {
+		material::BorderColor::TransparentBlack => BorderColor::TransparentBlack,
+		material::BorderColor::OpaqueBlack => BorderColor::OpaqueBlack,
+		material::BorderColor::OpaqueWhite => BorderColor::OpaqueWhite,
+	};
+	// Anisotropic filtering is an optional feature of the gpu
+	if settings.anisotropy > 1 && features.contains(Features::SAMPLER_ANISOTROPY) {
+		sampler_desc.anisotropy_clamp = Some(settings.anisotropy.min(adapter_limits.max_sampler_anisotropy as u8));
+	}
+	sampler_desc
+}
+
+impl<B: Backend> Drop for ImageBuffer<B> {
+	fn drop(&mut self) {
+		let device = &self.desc.layout.device.borrow().device;
+		unsafe {
+			device.destroy_sampler(self.sampler.take().unwrap());
+			device.destroy_fence(self.transferred_image_fence.take().unwrap());
+		}
+	}
+}
+
 impl<B: Backend> Image<B> {
 	pub fn new(device_ptr: Rc<RefCell<CoreDevice<B>>>, memory_types: &[MemoryType], dimensions: Extent, format: Format, tiling: Tiling, usage: Usage, memory_properties: Properties, aspects: Aspects, view_usage: Usage) -> Self {
+		Image::with_mip_levels(device_ptr, memory_types, dimensions, 1, format, tiling, usage, memory_properties, aspects, view_usage)
+	}
+
+	/// Image whose view covers all of its mip levels.
+	pub fn with_mip_levels(device_ptr: Rc<RefCell<CoreDevice<B>>>, memory_types: &[MemoryType], dimensions: Extent, mip_levels: u8, format: Format, tiling: Tiling, usage: Usage, memory_properties: Properties, aspects: Aspects, view_usage: Usage) -> Self {
 		let device = &device_ptr.borrow().device;
 		let kind = Kind::D2(dimensions.width, dimensions.height, 1, 1);
 		let mut image = unsafe {
 			device.create_image(
 				kind,
-				1,
+				mip_levels,
 				format,
 				tiling,
 				usage,
@@ -213,7 +349,7 @@ impl<B: Backend> Image<B> {
 				SubresourceRange {
 					aspects,
 					level_start: 0,
-					level_count: Some(1),
+					level_count: Some(mip_levels),
 					layer_start: 0,
 					layer_count: Some(1),
 				},
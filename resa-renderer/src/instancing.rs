@@ -0,0 +1,42 @@
+use std::cell::RefCell;
+use std::mem::size_of;
+use std::rc::Rc;
+use gfx_hal::Backend;
+use gfx_hal::adapter::MemoryType;
+use gfx_hal::buffer::Usage;
+use crate::buffer::Buffer;
+use crate::core::CoreDevice;
+use crate::vertex::InstanceData;
+
+/// Vertex buffer holding the instance data of a frame. It only grows, so it is reallocated
+/// if a frame has more instances than any frame before.
+pub(crate) struct InstanceBuffer<B: Backend> {
+	buffer: Option<Buffer<B>>,
+	capacity: usize,
+}
+
+impl<B: Backend> InstanceBuffer<B> {
+	pub(crate) fn new() -> Self {
+		InstanceBuffer {
+			buffer: None,
+			capacity: 0,
+		}
+	}
+
+	/// Copy the instances into the buffer. The previous frame must not be in flight anymore.
+	pub(crate) fn upload(&mut self, device: Rc<RefCell<CoreDevice<B>>>, memory_types: &[MemoryType], instances: &[InstanceData]) {
+		if instances.is_empty() {
+			return;
+		}
+		if instances.len() > self.capacity {
+			self.capacity = instances.len().next_power_of_two();
+			let size = (self.capacity * size_of::<InstanceData>()) as u64;
+			self.buffer = Some(Buffer::new_empty(device, size, Usage::VERTEX, memory_types));
+		}
+		self.buffer.as_mut().unwrap().update_data(0, instances);
+	}
+
+	pub(crate) fn get(&self) -> Option<&B::Buffer> {
+		self.buffer.as_ref().map(|buffer| buffer.get())
+	}
+}
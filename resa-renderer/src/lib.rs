@@ -1,10 +1,19 @@
+use std::path::PathBuf;
 use gfx_hal::window::Extent2D;
 use winit::dpi::PhysicalSize;
 use winit::window::Window;
-use crate::material::{Material, MaterialRef};
-use crate::mesh::Mesh;
+use log::{error, info};
+use crate::bounds::Bounds;
+use crate::error::RenderError;
+use crate::material::{Material, MaterialRef, SamplerSettings, TextureFormat};
+use crate::mesh::{Mesh, MeshHandle, MeshSharing, MeshUsage};
+use crate::pipelines::PipelineKey;
+use crate::profiling::RenderTiming;
+use crate::readback::FramePixels;
 use crate::render_resources::RenderResources;
+use crate::render_resources::texture_library::{TBORef, TextureLibrary};
 use crate::renderer::Renderer;
+use crate::render_view::RenderView;
 use crate::shader::ShaderRef;
 
 mod renderer;
@@ -15,58 +24,133 @@ mod buffer;
 mod descriptors;
 mod uniform;
 mod image_buffer;
+pub mod mipmap;
+mod texture_image;
+pub mod texture_container;
 pub mod mesh;
+pub mod mesh_builder;
+pub mod shapes;
 pub mod material;
 mod helper;
+mod instancing;
+mod draw_list;
 pub mod shader;
 mod render_resources;
 pub mod pipelines;
 mod render_passes;
 pub mod render_stage;
+pub mod profiling;
+pub mod error;
+pub mod bounds;
+mod render_target;
+pub mod readback;
+pub mod render_view;
 
 pub struct RendererConfig {
 	pub extent: PhysicalSize<u32>,
 	pub shaders: Vec<ShaderRef>,
+	pub present_mode: PresentMode,
+}
+
+/// Defines how rendered frames are handed to the display.
+/// - Fifo waits for the vertical blank and is always supported (vsync)
+/// - Mailbox replaces the queued frame with the newest one without tearing
+/// - Immediate presents right away and may tear
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PresentMode {
+	Fifo,
+	Mailbox,
+	Immediate,
 }
 
 
 pub struct ResaRenderer {
 	renderer: Renderer<backend::Backend>,
 	render_resources: RenderResources<backend::Backend>,
+	pending_captures: Vec<PathBuf>,
 }
 
 impl ResaRenderer {
 	/// Create a new instance of the renderer
-	pub fn new(window: &Window, config: RendererConfig) -> Self {
+	pub fn new(window: &Window, config: RendererConfig) -> Result<Self, RenderError> {
+		ResaRenderer::create(Some(window), config)
+	}
+
+	/// Create a renderer without window, which draws into an offscreen image of the configured extent.
+	/// The frames can be read with read_pixels, e.g. to compare them against reference images.
+	pub fn new_offscreen(config: RendererConfig) -> Result<Self, RenderError> {
+		ResaRenderer::create(None, config)
+	}
+
+	fn create(window: Option<&Window>, config: RendererConfig) -> Result<Self, RenderError> {
 		let extent = Extent2D { width: config.extent.width, height: config.extent.height };
-		let renderer = Renderer::new(window, extent);
+		let renderer = Renderer::new(window, extent, config.present_mode)?;
 		let render_resources = RenderResources::new(config.shaders, &renderer);
 
-		ResaRenderer {
+		Ok(ResaRenderer {
 			renderer,
 			render_resources,
-		}
+			pending_captures: vec![],
+		})
 	}
 
-	pub fn register_mesh(&mut self, mesh: Mesh) -> u64 {
-		self.render_resources.mesh_lib.add_mesh(mesh)
+	/// Upload a mesh, or get a handle to an existing mesh with the same content if both are shared.
+	/// The render views refer to the mesh by the id of the handle, it is freed when the last handle is dropped.
+	pub fn register_mesh(&mut self, mesh: Mesh, sharing: MeshSharing) -> MeshHandle {
+		self.render_resources.mesh_lib.add_mesh(mesh, sharing, MeshUsage::Static)
+	}
+
+	/// Upload a mesh which is updated often, e.g. debug geometry or particles which change every frame.
+	/// Dynamic meshes are never shared.
+	pub fn register_dynamic_mesh(&mut self, mesh: Mesh) -> MeshHandle {
+		self.render_resources.mesh_lib.add_mesh(mesh, MeshSharing::Unique, MeshUsage::Dynamic)
+	}
+
+	/// Replace the content of a mesh. The mesh may grow, and the frame which is still drawn is not affected.
+	pub fn update_mesh(&mut self, mesh: &MeshHandle, new_mesh: Mesh) -> Result<(), RenderError> {
+		self.render_resources.mesh_lib.update_mesh(&mesh.get_id(), new_mesh)
+	}
+
+	pub fn get_mesh_bounds(&self, mesh_id: &u64) -> Option<Bounds> {
+		self.render_resources.mesh_lib.get_mesh_bounds(mesh_id).copied()
 	}
 
 
 	pub fn register_materials(&mut self, materials: &[Material]) -> Vec<MaterialRef> {
-		let mut materials_and_stage_ids = vec![];
-		for mat in materials {
-			let stage_index = self.renderer.get_material_render_stage_index(mat, &self.render_resources);
-			materials_and_stage_ids.push((mat.clone(), stage_index));
+		let material_refs = self.render_resources.material_lib.add_materials(materials);
+		// Create the pipelines up front, so the first frame does not have to
+		for material in materials {
+			self.renderer.prepare_pipeline(&PipelineKey::from_material(material), &self.render_resources);
 		}
+		material_refs
+	}
 
-		self.render_resources.material_lib.add_materials(&materials_and_stage_ids)
+	/// Register a texture which materials reference with `Texture::Named`. It is uploaded once, when the first
+	/// material uses it. Register the textures before the materials, until then the materials show the missing texture.
+	/// Registering a texture again replaces its image and sampler settings.
+	pub fn register_texture(&mut self, name: &str, data: Vec<u8>, format: TextureFormat, sampler: SamplerSettings) {
+		self.render_resources.material_lib.add_texture(name, data, format, sampler);
+	}
+
+	/// Replace the image of a texture for all materials which use it, it keeps its sampler settings.
+	pub fn update_texture(&mut self, name: &str, data: Vec<u8>, format: TextureFormat) {
+		self.render_resources.material_lib.update_texture(name, data, format);
 	}
 
 	pub fn get_material_ref_from_name(&self, name: &str) -> Option<MaterialRef> {
 		self.render_resources.material_lib.get_material_ref_from_name(name)
 	}
 
+	/// The texture a material is drawn with, the missing texture while its texture is not registered or could not be uploaded.
+	pub fn get_texture_ref(&self, material_id: &MaterialRef) -> Option<TBORef> {
+		self.render_resources.material_lib.get_texture_ref(material_id)
+	}
+
+	/// The magenta checker which is drawn instead of missing textures.
+	pub fn get_missing_texture_ref(&self) -> TBORef {
+		TextureLibrary::<backend::Backend>::get_missing_ref()
+	}
+
 	pub fn get_material_mut(&mut self, material_id: &MaterialRef) -> &mut Material {
 		self.render_resources.material_lib.get_material_mut(&material_id).unwrap()
 	}
@@ -77,15 +161,76 @@ impl ResaRenderer {
 
 	/// Refresh the renderers swapchain setting e.g. after a surface size change
 	pub fn refresh(&mut self) {
-		self.renderer.recreate_swapchain = true;
+		self.renderer.recreate_render_target = true;
+	}
+
+	/// Set the new size of the surface. The render target will be recreated before the next frame is drawn.
+	pub fn resize(&mut self, extent: PhysicalSize<u32>) {
+		self.renderer.set_extent(Extent2D { width: extent.width, height: extent.height });
+	}
+
+	/// Change the way frames are presented. Falls back to fifo if the mode is not supported by the surface.
+	pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+		self.renderer.set_present_mode(present_mode);
 	}
 
-	/// Render all given meshes to the given output device
-	pub fn render(&mut self, render_objects: &[(u64, MaterialRef, [[f32; 4]; 4])], view_mat: [[f32; 4]; 4], projection_mat: [[f32; 4]; 4]) {
-		self.renderer.draw(render_objects, view_mat, projection_mat, &self.render_resources);
+	/// Render the views to the given output device, later views are drawn on top of earlier ones
+	pub fn render(&mut self, views: &[RenderView]) {
+		// The previous frame might still read the buffers and images, the draw waits for it anyway before it records the next one
+		if self.render_resources.mesh_lib.has_unused_buffers() || self.render_resources.material_lib.has_unused_textures() {
+			self.renderer.wait_for_last_frame();
+			self.render_resources.mesh_lib.free_unused_buffers();
+			self.render_resources.material_lib.free_unused_textures();
+		}
+		self.renderer.draw(views, &self.render_resources);
+		self.render_resources.mesh_lib.finish_frame();
+		self.write_captures();
+	}
+
+	/// Save the next rendered frame as png to the given path.
+	/// The frame is read back and encoded right after it was drawn, errors are logged.
+	pub fn capture_frame(&mut self, path: PathBuf) {
+		self.renderer.request_readback();
+		self.pending_captures.push(path);
+	}
+
+	fn write_captures(&mut self) {
+		if self.pending_captures.is_empty() {
+			return;
+		}
+
+		let frame = match self.renderer.read_pixels() {
+			Ok(frame) => frame,
+			Err(e) => {
+				error!("Could not capture the frame: {}", e);
+				self.pending_captures.clear();
+				return;
+			}
+		};
+		for path in self.pending_captures.drain(..) {
+			match frame.save_png(&path) {
+				Ok(_) => info!("Captured frame to {}", path.display()),
+				Err(e) => error!("{}", e),
+			}
+		}
+	}
+
+	/// Copy the next rendered frame to the cpu. Required before read_pixels can read a frame of the window.
+	pub fn request_readback(&mut self) {
+		self.renderer.request_readback();
+	}
+
+	/// Read the last rendered frame as RGBA pixels.
+	pub fn read_pixels(&mut self) -> Result<FramePixels, RenderError> {
+		self.renderer.read_pixels()
 	}
 
 	pub fn get_fps(&self) -> f32 {
 		self.renderer.get_fps()
 	}
+
+	/// Get the CPU timings of the acquire, the render stages, the submit and the present of the last drawn frame.
+	pub fn get_frame_timings(&self) -> &[RenderTiming] {
+		self.renderer.get_frame_timings()
+	}
 }
@@ -1,6 +1,7 @@
+use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use crate::render_resources::texture_buffer_library::TBORef;
+use crate::render_resources::texture_library::TBORef;
 use crate::render_stage::RenderStage;
 
 #[derive(Hash)]
@@ -36,17 +37,212 @@ impl Color {
 	}
 }
 
-#[derive(Clone, Hash)]
+/// The texture of a material.
+/// - None draws the material color only
+/// - Pending is an image of the material itself, which is uploaded with it and sampled with the settings
+/// - Named references a texture registered with `ResaRenderer::register_texture`, shared by all materials which use it
+/// - Some references an uploaded texture
+#[derive(Clone, Hash, PartialEq)]
 pub enum Texture{
 	None,
-	Pending(Vec<u8>, TextureFormat),
+	Pending(Vec<u8>, TextureFormat, SamplerSettings),
+	Named(String),
 	Some(TBORef)
 }
 
-#[derive(Clone, Hash)]
+/// The encoding of the data of a texture.
+/// - Custom is raw rgba with eight bits per channel and the given width and height
+/// - Png, Jpeg, Tga and Bmp are decoded to eight bits per channel
+/// - Hdr and OpenExr are decoded to linear floats
+/// - Ktx2 and Dds hold block compressed data, which is uploaded as it is if the gpu supports its format
+#[derive(Clone, Debug, Hash, PartialEq)]
 pub enum TextureFormat{
 	Custom((u32,u32)),
 	Png,
+	Jpeg,
+	Tga,
+	Bmp,
+	Hdr,
+	OpenExr,
+	Ktx2,
+	Dds,
+}
+
+impl TextureFormat {
+	/// The format of an image file with the extension, e.g. jpg.
+	pub fn from_extension(extension: &str) -> Option<Self> {
+		match extension.to_ascii_lowercase().as_str() {
+			"png" => Some(TextureFormat::Png),
+			"jpg" | "jpeg" => Some(TextureFormat::Jpeg),
+			"tga" => Some(TextureFormat::Tga),
+			"bmp" => Some(TextureFormat::Bmp),
+			"hdr" => Some(TextureFormat::Hdr),
+			"exr" => Some(TextureFormat::OpenExr),
+			"ktx2" => Some(TextureFormat::Ktx2),
+			"dds" => Some(TextureFormat::Dds),
+			_ => None,
+		}
+	}
+}
+
+/// Whether the colors of a texture are srgb, which the sampler converts to linear, or already linear.
+/// Color textures are usually srgb, normal maps and other data are linear. Float textures are always linear.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ColorSpace {
+	Srgb,
+	Linear,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TextureFilter {
+	Nearest,
+	Linear,
+}
+
+/// How uvs outside of 0 to 1 are sampled.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TextureWrap {
+	Repeat,
+	MirroredRepeat,
+	/// The pixels at the edge are repeated
+	Clamp,
+	/// The border color is sampled
+	Border,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum BorderColor {
+	TransparentBlack,
+	OpaqueBlack,
+	OpaqueWhite,
+}
+
+/// How a texture is sampled. Every texture has its own settings, materials which share a texture share them too.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SamplerSettings {
+	/// Filter of textures drawn smaller than their size
+	pub min_filter: TextureFilter,
+	/// Filter of textures drawn larger than their size
+	pub mag_filter: TextureFilter,
+	/// Filter between the mip levels
+	pub mip_filter: TextureFilter,
+	pub wrap: TextureWrap,
+	/// Highest anisotropy of the filtering, 1 turns anisotropic filtering off.
+	/// It is limited to what the gpu supports.
+	pub anisotropy: u8,
+	pub border_color: BorderColor,
+	/// Generate the mip levels of the texture when it is uploaded, so distant surfaces do not shimmer.
+	/// Compressed textures keep the levels they have.
+	pub mipmaps: bool,
+	pub color_space: ColorSpace,
+}
+
+impl Default for SamplerSettings {
+	fn default() -> Self {
+		SamplerSettings {
+			min_filter: TextureFilter::Linear,
+			mag_filter: TextureFilter::Linear,
+			mip_filter: TextureFilter::Linear,
+			wrap: TextureWrap::Clamp,
+			anisotropy: 1,
+			border_color: BorderColor::TransparentBlack,
+			mipmaps: true,
+			color_space: ColorSpace::Srgb,
+		}
+	}
+}
+
+/// How the fragments of a material are combined with the color already in the render target.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum BlendMode {
+	/// The fragment color replaces the target color
+	Opaque,
+	Alpha,
+	Additive,
+	/// Alpha blending for colors which are already multiplied with their alpha
+	Premultiplied,
+	Multiply,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum CullMode {
+	None,
+	Front,
+	Back,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum PolygonMode {
+	Fill,
+	/// Only the edges of the triangles are drawn. Falls back to fill if the gpu does not support it.
+	Wireframe,
+}
+
+/// Offset added to the depth of the fragments, e.g. to draw decals on top of a surface without z-fighting.
+#[derive(Copy, Clone, Debug)]
+pub struct DepthBias {
+	pub constant_factor: f32,
+	pub slope_factor: f32,
+	/// The largest offset, no limit if zero
+	pub clamp: f32,
+}
+
+impl DepthBias {
+	fn to_bits(&self) -> (u32, u32, u32) {
+		(self.constant_factor.to_bits(), self.slope_factor.to_bits(), self.clamp.to_bits())
+	}
+}
+
+// The factors are compared by their bits, so the depth bias can be part of the pipeline key
+impl PartialEq for DepthBias {
+	fn eq(&self, other: &Self) -> bool {
+		self.to_bits() == other.to_bits()
+	}
+}
+
+impl Eq for DepthBias {}
+
+impl Hash for DepthBias {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.to_bits().hash(state);
+	}
+}
+
+impl PartialOrd for DepthBias {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for DepthBias {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.to_bits().cmp(&other.to_bits())
+	}
+}
+
+/// The fixed function state of the pipeline a material is drawn with.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct PipelineState {
+	pub blend: BlendMode,
+	/// Fragments behind the depth buffer are discarded
+	pub depth_test: bool,
+	pub depth_write: bool,
+	pub cull: CullMode,
+	pub polygon: PolygonMode,
+	pub depth_bias: Option<DepthBias>,
+}
+
+impl Default for PipelineState {
+	fn default() -> Self {
+		PipelineState {
+			blend: BlendMode::Alpha,
+			depth_test: true,
+			depth_write: true,
+			cull: CullMode::None,
+			polygon: PolygonMode::Fill,
+			depth_bias: None,
+		}
+	}
 }
 
 #[derive(Eq, PartialEq, Hash, Copy, Clone, Default)]
@@ -58,6 +254,7 @@ pub struct Material {
 	pub name: String,
 	pub shader_id: u32,
 	pub render_stage: RenderStage,
+	pub pipeline_state: PipelineState,
 	pub color: Color,
 	pub texture: Texture,
 }
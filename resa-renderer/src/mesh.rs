@@ -1,50 +1,256 @@
+use std::cell::RefCell;
 use std::hash::{Hash, Hasher};
+use std::rc::{Rc, Weak};
 use glm::{Vector2, Vector3};
-use crate::vertex::Vertex;
+use crate::error::RenderError;
+use crate::shapes;
+use crate::vertex::{AttributeData, Vertex, VertexAttribute, VertexLayout};
 
+/// How the vertices of a mesh are assembled into primitives.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Topology {
+	PointList,
+	LineList,
+	LineStrip,
+	TriangleList,
+	TriangleStrip,
+}
+
+impl Default for Topology {
+	fn default() -> Self {
+		Topology::TriangleList
+	}
+}
+
+/// The type of the indices in the index buffer of a mesh, picked when the mesh is registered.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum IndexFormat {
+	U16,
+	U32,
+}
+
+/// Vertices with one stream of values per vertex attribute, drawn as triangle list unless another topology is set.
+/// Every mesh has positions, the other attributes and the indices are optional.
 // #[derive(Copy, Clone)]
 pub struct Mesh {
-	pub vertices: Vec<Vertex>,
-	pub indices: Vec<u16>,
+	positions: Vec<[f32; 3]>,
+	attributes: Vec<(VertexAttribute, AttributeData)>,
+	/// Without indices the vertices are drawn in their order.
+	indices: Option<Vec<u32>>,
+	topology: Topology,
 }
 
 impl Mesh {
-	pub fn new(vertices: Vec<Vertex>, indices: Vec<u16>) -> Self {
+	/// Mesh with the positions and first uv set of the vertices.
+	pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+		let uvs = vertices.iter().map(|vertex| [vertex.uv.x, vertex.uv.y]).collect();
+		let mut mesh = Mesh::from_positions(vertices.iter().map(|vertex| [vertex.position.x, vertex.position.y, vertex.position.z]).collect(), indices);
+		mesh.attributes.push((VertexAttribute::Uv0, AttributeData::Float2(uvs)));
+		mesh
+	}
+
+	pub fn from_positions(positions: Vec<[f32; 3]>, indices: Vec<u32>) -> Self {
+		Mesh {
+			positions,
+			attributes: vec![],
+			indices: Some(indices),
+			topology: Topology::default(),
+		}
+	}
+
+	pub fn non_indexed(positions: Vec<[f32; 3]>) -> Self {
 		Mesh {
-			vertices,
-			indices,
+			positions,
+			attributes: vec![],
+			indices: None,
+			topology: Topology::default(),
+		}
+	}
+
+	/// The index type of the index buffer is picked when the mesh is registered, u16 if all indices fit into it.
+	pub fn set_indices(&mut self, indices: Option<Vec<u32>>) {
+		self.indices = indices;
+	}
+
+	pub fn get_indices(&self) -> Option<&[u32]> {
+		self.indices.as_deref()
+	}
+
+	pub fn set_topology(&mut self, topology: Topology) {
+		self.topology = topology;
+	}
+
+	pub fn get_topology(&self) -> Topology {
+		self.topology
+	}
+
+	/// Add an attribute stream or replace the existing one. It needs one value of the type of the attribute per vertex.
+	pub fn set_attribute(&mut self, attribute: VertexAttribute, data: AttributeData) -> Result<(), RenderError> {
+		if attribute == VertexAttribute::Position {
+			return match data {
+				AttributeData::Float3(positions) if positions.len() == self.positions.len() => {
+					self.positions = positions;
+					Ok(())
+				}
+				data => Err(self.get_attribute_error(attribute, &data)),
+			};
+		}
+		if data.get_kind() != attribute.get_kind() || data.len() != self.positions.len() {
+			return Err(self.get_attribute_error(attribute, &data));
+		}
+
+		self.attributes.retain(|(existing, _)| existing != &attribute);
+		self.attributes.push((attribute, data));
+		Ok(())
+	}
+
+	pub fn remove_attribute(&mut self, attribute: VertexAttribute) {
+		self.attributes.retain(|(existing, _)| existing != &attribute);
+	}
+
+	pub fn get_positions(&self) -> &[[f32; 3]] {
+		&self.positions
+	}
+
+	/// The values of an attribute other than the position.
+	pub fn get_attribute(&self, attribute: VertexAttribute) -> Option<&AttributeData> {
+		self.attributes.iter().find(|(existing, _)| existing == &attribute).map(|(_, data)| data)
+	}
+
+	pub fn get_vertex_count(&self) -> usize {
+		self.positions.len()
+	}
+
+	/// The number of indices, or of vertices if the mesh is not indexed.
+	pub fn get_draw_count(&self) -> usize {
+		match &self.indices {
+			Some(indices) => indices.len(),
+			None => self.positions.len(),
+		}
+	}
+
+	/// The smallest index type all indices fit into, u16 indices take half the memory. None if the mesh is not indexed.
+	pub fn get_index_format(&self) -> Option<IndexFormat> {
+		let indices = self.indices.as_ref()?;
+		if indices.iter().all(|index| *index <= u16::MAX as u32) { Some(IndexFormat::U16) } else { Some(IndexFormat::U32) }
+	}
+
+	pub fn get_layout(&self) -> VertexLayout {
+		let mut attributes = vec![VertexAttribute::Position];
+		attributes.extend(self.attributes.iter().map(|(attribute, _)| *attribute));
+		VertexLayout::new(&attributes)
+	}
+
+	/// Whether both meshes have the same vertices, indices and topology, regardless of the order the attributes were set in.
+	/// The floats are compared by their bits, so unlike with `==` NaN equals itself and 0.0 differs from -0.0.
+	pub fn has_same_content(&self, other: &Mesh) -> bool {
+		let same_positions = self.positions.len() == other.positions.len()
+			&& self.positions.iter().zip(other.positions.iter()).all(|(a, b)| a.map(f32::to_bits) == b.map(f32::to_bits));
+		let same_attributes = self.attributes.len() == other.attributes.len()
+			&& self.get_sorted_attributes().into_iter().zip(other.get_sorted_attributes())
+				.all(|((attribute, data), (other_attribute, other_data))| attribute == other_attribute && data.to_bits() == other_data.to_bits());
+		same_positions && same_attributes && self.indices == other.indices && self.topology == other.topology
+	}
+
+	fn get_sorted_attributes(&self) -> Vec<&(VertexAttribute, AttributeData)> {
+		let mut attributes: Vec<_> = self.attributes.iter().collect();
+		attributes.sort_by_key(|(attribute, _)| attribute.get_location());
+		attributes
+	}
+
+	fn get_attribute_error(&self, attribute: VertexAttribute, data: &AttributeData) -> RenderError {
+		if data.get_kind() != attribute.get_kind() {
+			RenderError::AttributeKindMismatch { attribute, expected: attribute.get_kind(), actual: data.get_kind() }
+		} else {
+			RenderError::AttributeLengthMismatch { attribute, expected: self.positions.len(), actual: data.len() }
 		}
 	}
 }
 
+/// Hashes the whole content, consistent with `has_same_content`.
 impl Hash for Mesh {
 	fn hash<H: Hasher>(&self, state: &mut H) {
-		self.vertices.len().hash(state);
+		for position in self.positions.iter() {
+			position.map(f32::to_bits).hash(state);
+		}
+		for (attribute, data) in self.get_sorted_attributes() {
+			attribute.hash(state);
+			data.to_bits().hash(state);
+		}
 		self.indices.hash(state);
+		self.topology.hash(state);
+	}
+}
+
+/// Whether a registered mesh may share its buffers with other meshes of the same content.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MeshSharing {
+	/// Registering a mesh with the content of another shared mesh returns a handle to the existing one.
+	Shared,
+	/// The mesh gets buffers of its own and is never handed out for other registrations, e.g. because it is updated later.
+	Unique,
+}
+
+/// How often the content of a registered mesh changes, which decides how its buffers are updated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MeshUsage {
+	/// The mesh is rarely updated. An update replaces its buffers.
+	Static,
+	/// The mesh is updated often, e.g. every frame. It has a ring of buffers with spare room, so an update
+	/// neither waits for the gpu nor reallocates the buffers unless the mesh outgrows them.
+	Dynamic,
+}
+
+/// Reference to a registered mesh. Clones refer to the same mesh, its buffers are freed
+/// after the last handle was dropped, once the frame which might still draw it is finished.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MeshHandle(Rc<MeshHandleInner>);
+
+#[derive(Debug)]
+pub(crate) struct MeshHandleInner {
+	mesh_id: u64,
+	/// The ids of the meshes without handles, freed by the mesh library before the next frame
+	released: Rc<RefCell<Vec<u64>>>,
+}
+
+impl MeshHandle {
+	pub(crate) fn new(mesh_id: u64, released: Rc<RefCell<Vec<u64>>>) -> Self {
+		MeshHandle(Rc::new(MeshHandleInner { mesh_id, released }))
+	}
+
+	/// A handle to the mesh of the weak reference, None if all of its handles were dropped.
+	pub(crate) fn upgrade(weak: &Weak<MeshHandleInner>) -> Option<Self> {
+		weak.upgrade().map(MeshHandle)
+	}
+
+	pub(crate) fn downgrade(&self) -> Weak<MeshHandleInner> {
+		Rc::downgrade(&self.0)
+	}
+
+	/// The id used by the render views to refer to the mesh, valid as long as the handle exists.
+	pub fn get_id(&self) -> u64 {
+		self.0.mesh_id
 	}
 }
 
+impl PartialEq for MeshHandleInner {
+	fn eq(&self, other: &Self) -> bool {
+		self.mesh_id == other.mesh_id
+	}
+}
 
+impl Eq for MeshHandleInner {}
+
+impl Drop for MeshHandleInner {
+	fn drop(&mut self) {
+		self.released.borrow_mut().push(self.mesh_id);
+	}
+}
+
+
+/// Unit cube with its own vertices for every face, see `shapes::cuboid`.
 pub fn create_primitive_cube() -> Mesh {
-	let vertices = vec![
-		Vertex { position: Vector3::new(-0.5, -0.5, 0.5), uv: Vector2::new(0.0, 0.0) },
-		Vertex { position: Vector3::new(0.5, -0.5, 0.5), uv: Vector2::new(1.0, 0.0) },
-		Vertex { position: Vector3::new(-0.5, 0.5, 0.5), uv: Vector2::new(0.0, 1.0) },
-		Vertex { position: Vector3::new(0.5, 0.5, 0.5), uv: Vector2::new(1.0, 1.0) },
-		Vertex { position: Vector3::new(-0.5, -0.5, -0.5), uv: Vector2::new(0.0, 0.0) },
-		Vertex { position: Vector3::new(0.5, -0.5, -0.5), uv: Vector2::new(1.0, 0.0) },
-		Vertex { position: Vector3::new(-0.5, 0.5, -0.5), uv: Vector2::new(0.0, 1.0) },
-		Vertex { position: Vector3::new(0.5, 0.5, -0.5), uv: Vector2::new(1.0, 1.0) },
-	];
-	let triangle_list = vec![
-		0, 2, 3, 0, 1, 3, //Front
-		4, 6, 7, 4, 5, 7, //Back
-		1, 3, 7, 1, 5, 7, // Right
-		0, 2, 6, 0, 4, 6, // Left
-		2, 6, 7, 2, 3, 7, // Top
-		0, 4, 5, 0, 1, 5, // Bottom
-	];
-	Mesh::new(vertices, triangle_list)
+	shapes::cuboid([1.0, 1.0, 1.0]).build()
 }
 
 pub fn create_primitive_quad() -> Mesh {
@@ -0,0 +1,175 @@
+use crate::mesh::Mesh;
+use crate::vertex::{AttributeData, VertexAttribute};
+
+/// Collects vertices with position, normal and uv and the triangles between them, to build a triangle list mesh.
+/// Triangles are counter clockwise when seen from their front.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshBuilder {
+	positions: Vec<[f32; 3]>,
+	normals: Vec<[f32; 3]>,
+	uvs: Vec<[f32; 2]>,
+	indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+	pub fn new() -> Self {
+		MeshBuilder::default()
+	}
+
+	/// Returns the index of the vertex, to be used by the triangles.
+	pub fn add_vertex(&mut self, position: [f32; 3], normal: [f32; 3], uv: [f32; 2]) -> u32 {
+		self.positions.push(position);
+		self.normals.push(normal);
+		self.uvs.push(uv);
+		self.positions.len() as u32 - 1
+	}
+
+	pub fn add_triangle(&mut self, a: u32, b: u32, c: u32) -> &mut Self {
+		self.indices.extend([a, b, c]);
+		self
+	}
+
+	/// Two triangles between four counter clockwise vertices.
+	pub fn add_quad(&mut self, a: u32, b: u32, c: u32, d: u32) -> &mut Self {
+		self.indices.extend([a, b, c, a, c, d]);
+		self
+	}
+
+	pub fn get_positions(&self) -> &[[f32; 3]] {
+		&self.positions
+	}
+
+	pub fn get_normals(&self) -> &[[f32; 3]] {
+		&self.normals
+	}
+
+	pub fn get_uvs(&self) -> &[[f32; 2]] {
+		&self.uvs
+	}
+
+	pub fn get_indices(&self) -> &[u32] {
+		&self.indices
+	}
+
+	pub fn get_vertex_count(&self) -> usize {
+		self.positions.len()
+	}
+
+	/// Append the vertices and triangles of the other builder.
+	pub fn merge(&mut self, other: &MeshBuilder) -> &mut Self {
+		let offset = self.positions.len() as u32;
+		self.positions.extend_from_slice(&other.positions);
+		self.normals.extend_from_slice(&other.normals);
+		self.uvs.extend_from_slice(&other.uvs);
+		self.indices.extend(other.indices.iter().map(|index| index + offset));
+		self
+	}
+
+	/// Transform the vertices by a column major matrix. The normals are transformed by the inverse transpose,
+	/// and the triangles are flipped if the matrix mirrors them, so they keep facing outwards.
+	pub fn transform(&mut self, matrix: &[[f32; 4]; 4]) -> &mut Self {
+		for position in self.positions.iter_mut() {
+			let [x, y, z] = *position;
+			*position = [0, 1, 2].map(|row| matrix[0][row] * x + matrix[1][row] * y + matrix[2][row] * z + matrix[3][row]);
+		}
+
+		// The columns of the inverse transpose scaled by the determinant
+		let [c0, c1, c2] = [[matrix[0][0], matrix[0][1], matrix[0][2]], [matrix[1][0], matrix[1][1], matrix[1][2]], [matrix[2][0], matrix[2][1], matrix[2][2]]];
+		let cofactors = [cross(c1, c2), cross(c2, c0), cross(c0, c1)];
+		let determinant = dot(c0, cofactors[0]);
+		for normal in self.normals.iter_mut() {
+			let transformed = [0, 1, 2].map(|row| cofactors[0][row] * normal[0] + cofactors[1][row] * normal[1] + cofactors[2][row] * normal[2]);
+			*normal = normalize(transformed.map(|value| value * determinant.signum()));
+		}
+
+		if determinant < 0.0 {
+			self.reverse_winding();
+		}
+		self
+	}
+
+	pub fn translate(&mut self, offset: [f32; 3]) -> &mut Self {
+		for position in self.positions.iter_mut() {
+			*position = [position[0] + offset[0], position[1] + offset[1], position[2] + offset[2]];
+		}
+		self
+	}
+
+	pub fn scale(&mut self, factors: [f32; 3]) -> &mut Self {
+		let [x, y, z] = factors;
+		self.transform(&[[x, 0.0, 0.0, 0.0], [0.0, y, 0.0, 0.0], [0.0, 0.0, z, 0.0], [0.0, 0.0, 0.0, 1.0]])
+	}
+
+	/// Rotate around the origin by a quaternion x, y, z, w.
+	pub fn rotate(&mut self, rotation: [f32; 4]) -> &mut Self {
+		let [x, y, z, w] = rotation;
+		self.transform(&[
+			[1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w), 2.0 * (x * z - y * w), 0.0],
+			[2.0 * (x * y - z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w), 0.0],
+			[2.0 * (x * z + y * w), 2.0 * (y * z - x * w), 1.0 - 2.0 * (x * x + y * y), 0.0],
+			[0.0, 0.0, 0.0, 1.0],
+		])
+	}
+
+	/// Reverse the winding of all triangles and the direction of all normals.
+	pub fn flip_faces(&mut self) -> &mut Self {
+		self.reverse_winding();
+		for normal in self.normals.iter_mut() {
+			*normal = normal.map(|value| -value);
+		}
+		self
+	}
+
+	/// Smooth normals from the triangles, every triangle contributes to the normals of its vertices by its area.
+	/// Vertices which are in no triangle keep their normal.
+	pub fn recalculate_normals(&mut self) -> &mut Self {
+		let mut normals = vec![[0.0f32; 3]; self.positions.len()];
+		for triangle in self.indices.chunks_exact(3) {
+			let face_normal = get_face_normal(&self.positions, triangle);
+			for index in triangle {
+				let normal = &mut normals[*index as usize];
+				*normal = [normal[0] + face_normal[0], normal[1] + face_normal[1], normal[2] + face_normal[2]];
+			}
+		}
+		for (normal, summed) in self.normals.iter_mut().zip(normals) {
+			if dot(summed, summed) > 0.0 {
+				*normal = normalize(summed);
+			}
+		}
+		self
+	}
+
+	fn reverse_winding(&mut self) {
+		for triangle in self.indices.chunks_exact_mut(3) {
+			triangle.swap(1, 2);
+		}
+	}
+
+	/// Triangle list mesh with the normals and uvs as attributes.
+	pub fn build(&self) -> Mesh {
+		let mut mesh = Mesh::from_positions(self.positions.clone(), self.indices.clone());
+		// Both streams always have one value per vertex
+		mesh.set_attribute(VertexAttribute::Normal, AttributeData::Float3(self.normals.clone())).unwrap();
+		mesh.set_attribute(VertexAttribute::Uv0, AttributeData::Float2(self.uvs.clone())).unwrap();
+		mesh
+	}
+}
+
+/// Normal of the triangle with the length of twice its area.
+pub(crate) fn get_face_normal(positions: &[[f32; 3]], triangle: &[u32]) -> [f32; 3] {
+	let [a, b, c] = [positions[triangle[0] as usize], positions[triangle[1] as usize], positions[triangle[2] as usize]];
+	cross([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]])
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+	[a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+	a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn normalize(vector: [f32; 3]) -> [f32; 3] {
+	let length = dot(vector, vector).sqrt();
+	if length > 0.0 { vector.map(|value| value / length) } else { vector }
+}
@@ -0,0 +1,85 @@
+use image::{Rgba, Rgba32FImage, RgbaImage};
+use crate::material::ColorSpace;
+
+/// Number of mip levels of an image down to one pixel, the image itself included.
+pub fn get_mip_level_count(width: u32, height: u32) -> u8 {
+	(u32::BITS - width.max(height).max(1).leading_zeros()) as u8
+}
+
+/// The mip levels below the image, each half as large as the one above, rounded down to at least one pixel.
+/// Used if the gpu can not blit the format of the texture.
+/// Every pixel is the average of the two by two pixels above it. Srgb colors are averaged in linear space,
+/// otherwise the distant surfaces would get darker.
+pub fn generate_mip_chain(image: &RgbaImage, color_space: ColorSpace) -> Vec<RgbaImage> {
+	let to_linear: Vec<f32> = (0..=255).map(|value| match color_space {
+		ColorSpace::Srgb => srgb_to_linear(value as f32 / 255.0),
+		ColorSpace::Linear => value as f32 / 255.0,
+	}).collect();
+	let pixels = image.pixels().map(|pixel| {
+		let [r, g, b, a] = pixel.0;
+		[to_linear[r as usize], to_linear[g as usize], to_linear[b as usize], a as f32 / 255.0]
+	}).collect();
+
+	let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+	let to_color = |value: f32| match color_space {
+		ColorSpace::Srgb => to_byte(linear_to_srgb(value)),
+		ColorSpace::Linear => to_byte(value),
+	};
+	downsample_levels(pixels, image.width(), image.height()).into_iter().map(|(width, height, pixels)| {
+		RgbaImage::from_fn(width, height, |x, y| {
+			let [r, g, b, a] = pixels[(y * width + x) as usize];
+			Rgba([to_color(r), to_color(g), to_color(b), to_byte(a)])
+		})
+	}).collect()
+}
+
+/// The mip levels below a float image, its values are linear.
+pub fn generate_float_mip_chain(image: &Rgba32FImage) -> Vec<Rgba32FImage> {
+	let pixels = image.pixels().map(|pixel| pixel.0).collect();
+	downsample_levels(pixels, image.width(), image.height()).into_iter().map(|(width, height, pixels)| {
+		Rgba32FImage::from_fn(width, height, |x, y| Rgba(pixels[(y * width + x) as usize]))
+	}).collect()
+}
+
+/// Halve the linear pixels until one pixel is left. Every level is averaged from the unrounded level above.
+fn downsample_levels(pixels: Vec<[f32; 4]>, width: u32, height: u32) -> Vec<(u32, u32, Vec<[f32; 4]>)> {
+	let level_count = get_mip_level_count(width, height) as usize;
+	let mut levels: Vec<(u32, u32, Vec<[f32; 4]>)> = Vec::with_capacity(level_count - 1);
+	for _ in 1..level_count {
+		let (above_width, above_height, above_pixels) = levels.last().map_or((width, height, &pixels[..]), |(width, height, pixels)| (*width, *height, &pixels[..]));
+		let level = downsample(above_width, above_height, above_pixels);
+		levels.push(level);
+	}
+	levels
+}
+
+fn downsample(width: u32, height: u32, pixels: &[[f32; 4]]) -> (u32, u32, Vec<[f32; 4]>) {
+	let level_width = (width / 2).max(1);
+	let level_height = (height / 2).max(1);
+	let mut level_pixels = Vec::with_capacity((level_width * level_height) as usize);
+	for y in 0..level_height {
+		for x in 0..level_width {
+			// A side of one pixel is not halved, the pixel is used twice
+			let columns = [(2 * x).min(width - 1), (2 * x + 1).min(width - 1)];
+			let rows = [(2 * y).min(height - 1), (2 * y + 1).min(height - 1)];
+			let mut sum = [0.0f32; 4];
+			for row in rows {
+				for column in columns {
+					for (total, value) in sum.iter_mut().zip(pixels[(row * width + column) as usize]) {
+						*total += value;
+					}
+				}
+			}
+			level_pixels.push(sum.map(|value| value / 4.0));
+		}
+	}
+	(level_width, level_height, level_pixels)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+	if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+	if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+}
@@ -4,21 +4,24 @@ use gfx_hal::Backend;
 use gfx_hal::device::Device;
 use crate::core::CoreDevice;
 use crate::render_stage::RenderStage;
+use crate::vertex::VertexLayout;
 
 pub struct GraphicsPipeline<B: Backend> {
 	device: Rc<RefCell<CoreDevice<B>>>,
 	pub pipeline: Option<B::GraphicsPipeline>,
 	pub layout: Option<B::PipelineLayout>,
+	pub vertex_layout: VertexLayout,
 	pub stage: RenderStage,
 	pub render_pass_id: usize,
 }
 
 impl<B: Backend> GraphicsPipeline<B> {
-	pub(crate) fn new(device: Rc<RefCell<CoreDevice<B>>>, pipeline: B::GraphicsPipeline, layout: B::PipelineLayout, stage: RenderStage, render_pass_id: usize) -> Self {
+	pub(crate) fn new(device: Rc<RefCell<CoreDevice<B>>>, pipeline: B::GraphicsPipeline, layout: B::PipelineLayout, vertex_layout: VertexLayout, stage: RenderStage, render_pass_id: usize) -> Self {
 		GraphicsPipeline{
 			device,
 			pipeline: Some(pipeline),
 			layout: Some(layout),
+			vertex_layout,
 			stage,
 			render_pass_id
 		}
@@ -1,83 +1,72 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use gfx_hal::Backend;
+use crate::material::{Material, PipelineState};
+use crate::mesh::Topology;
 use crate::pipelines::graphics_pipeline::GraphicsPipeline;
 use crate::render_stage::RenderStage;
 
 pub mod graphics_pipeline;
 pub mod pipeline_builder;
 
+/// Everything a pipeline is created from. Materials with the same key share their pipeline.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PipelineKey {
+	pub shader_id: u32,
+	pub stage: RenderStage,
+	pub state: PipelineState,
+	pub topology: Topology,
+}
 
+impl PipelineKey {
+	/// The key of the material for triangle lists.
+	pub fn from_material(material: &Material) -> Self {
+		PipelineKey {
+			shader_id: material.shader_id,
+			stage: material.render_stage,
+			state: material.pipeline_state,
+			topology: Topology::TriangleList,
+		}
+	}
+
+	pub fn with_topology(mut self, topology: Topology) -> Self {
+		self.topology = topology;
+		self
+	}
+}
+
+/// Cache of the pipelines, which are created when a material first needs them.
 pub struct PipelineController<B: Backend> {
-	pub pipelines: HashMap<RenderStage, Vec<GraphicsPipeline<B>>>,
+	pipelines: HashMap<PipelineKey, GraphicsPipeline<B>>,
+	failed_keys: HashSet<PipelineKey>,
 }
 
 impl<B: Backend> PipelineController<B> {
 	pub fn new() -> Self {
 		PipelineController {
 			pipelines: HashMap::new(),
+			failed_keys: HashSet::new(),
 		}
 	}
 
-	pub fn add_pipeline(&mut self, pipeline: GraphicsPipeline<B>) -> u16 {
-		let stage = &pipeline.stage;
-		let render_weight = get_stage_render_weight(stage);
-		if self.pipelines.contains_key(&stage) {
-			let pipelines_in_stage = self.pipelines.get(stage).unwrap().len() as u16;
-			self.pipelines.get_mut(stage).unwrap().push(pipeline);
-			return render_weight + pipelines_in_stage;
-		}
-
-		self.pipelines.insert(stage.clone(), vec![pipeline]);
-		render_weight
+	pub fn add_pipeline(&mut self, key: PipelineKey, pipeline: GraphicsPipeline<B>) {
+		self.pipelines.insert(key, pipeline);
 	}
 
-	pub fn get_render_index_from_pipeline(&self, stage: &RenderStage) -> Option<u16> {
-		if self.pipelines.contains_key(stage) {
-			return Some(get_stage_render_weight(stage));
-		}
-		None
+	pub fn get(&self, key: &PipelineKey) -> Option<&GraphicsPipeline<B>> {
+		self.pipelines.get(key)
 	}
 
-	pub fn get_all_pipelines_sorted(&self) -> Vec<&GraphicsPipeline<B>> {
-		let mut pipelines = vec![];
-		pipelines.append(&mut self.get_all_pipelines_of_stage(&RenderStage::Opaque));
-		pipelines.append(&mut self.get_all_pipelines_of_stage(&RenderStage::Transparent));
-		pipelines.append(&mut self.get_all_pipelines_of_stage(&RenderStage::UI));
-
-		pipelines
+	pub fn contains(&self, key: &PipelineKey) -> bool {
+		self.pipelines.contains_key(key)
 	}
 
-	fn get_all_pipelines_of_stage(&self, stage: &RenderStage) -> Vec<&GraphicsPipeline<B>> {
-		let mut stage_pipelines = vec![];
-		if self.pipelines.contains_key(stage) {
-			let pipelines_of_stage = self.pipelines.get(stage).unwrap();
-			for pipe in pipelines_of_stage {
-				stage_pipelines.push(pipe);
-			}
-		}
-		stage_pipelines
+	/// Remember a key whose pipeline could not be created, so the creation is not retried every frame.
+	/// Returns false if the key was already marked as failed.
+	pub fn mark_failed(&mut self, key: PipelineKey) -> bool {
+		self.failed_keys.insert(key)
 	}
-}
 
-fn get_stage_render_weight(stage: &RenderStage) -> u16 {
-	return match stage {
-		RenderStage::None => 0,
-		RenderStage::Opaque => 1000,
-		RenderStage::Transparent => 2000,
-		RenderStage::UI => 3000,
-	};
-}
-
-fn get_stage_from_weight(weight: u16) -> RenderStage {
-	if weight < 1000 {
-		return RenderStage::None;
-	}
-	if weight < 2000 {
-		return RenderStage::Opaque;
+	pub fn has_failed(&self, key: &PipelineKey) -> bool {
+		self.failed_keys.contains(key)
 	}
-	if weight < 3000 {
-		return RenderStage::Transparent;
-	}
-
-	return RenderStage::UI;
 }
@@ -5,10 +5,12 @@ use gfx_hal::Backend;
 use gfx_hal::device::{Device};
 use gfx_hal::pass::Subpass;
 use gfx_hal::pso::{BlendState, ColorBlendDesc, ColorMask, Comparison, DepthStencilDesc, DepthTest, EntryPoint, GraphicsPipelineDesc, InputAssemblerDesc, Primitive, PrimitiveAssemblerDesc, Rasterizer, ShaderStageFlags};
+use log::error;
 use crate::core::CoreDevice;
+use crate::mesh::Topology;
 use crate::pipelines::graphics_pipeline::GraphicsPipeline;
 use crate::render_stage::RenderStage;
-use crate::vertex::Vertex;
+use crate::vertex::VertexLayout;
 
 pub struct PipelineLayoutDesc<'a, B: Backend> {
 	descriptor_layouts: Vec<&'a <B as Backend>::DescriptorSetLayout>,
@@ -35,6 +37,9 @@ pub struct PipelineBuilder<'a, B: Backend> {
 	fragment_shader: Vec<u32>,
 	color_blend_desc: Option<ColorBlendDesc>,
 	depth_desc: Option<DepthTest>,
+	rasterizer: Rasterizer,
+	vertex_layout: VertexLayout,
+	topology: Topology,
 	stage: RenderStage,
 	render_pass_id: usize,
 }
@@ -50,6 +55,9 @@ impl<'a, B: Backend> PipelineBuilder<'a, B> {
 			fragment_shader: vec![],
 			color_blend_desc: None,
 			depth_desc: None,
+			rasterizer: Rasterizer::FILL,
+			vertex_layout: VertexLayout::default(),
+			topology: Topology::TriangleList,
 			stage: RenderStage::None,
 			render_pass_id: 0
 		}
@@ -90,6 +98,22 @@ impl<'a, B: Backend> PipelineBuilder<'a, B> {
 		self
 	}
 
+	pub fn add_rasterizer(&mut self, rasterizer: Rasterizer) -> &mut Self {
+		self.rasterizer = rasterizer;
+		self
+	}
+
+	/// The vertex attributes the vertex shader consumes.
+	pub fn set_vertex_layout(&mut self, vertex_layout: &VertexLayout) -> &mut Self {
+		self.vertex_layout = vertex_layout.clone();
+		self
+	}
+
+	pub fn set_topology(&mut self, topology: Topology) -> &mut Self {
+		self.topology = topology;
+		self
+	}
+
 	pub fn set_render_stage(&mut self, stage: RenderStage) -> &mut Self{
 		self.stage = stage;
 		self
@@ -110,8 +134,8 @@ impl<'a, B: Backend> PipelineBuilder<'a, B> {
 			specialization: Default::default(),
 		};
 
-		let vertex_buffers = Vertex::get_vertex_buffer_desc();
-		let vertex_attributes = Vertex::get_vertex_attributes();
+		let vertex_buffers = self.vertex_layout.get_vertex_buffer_desc();
+		let vertex_attributes = self.vertex_layout.get_vertex_attributes();
 
 		let pipeline_layout = self.layout_desc.take().unwrap();
 		let pipeline_layout = unsafe {
@@ -122,13 +146,20 @@ impl<'a, B: Backend> PipelineBuilder<'a, B> {
 		}.expect("Cannot create pipeline layout");
 
 		let subpass = self.subpass?;
+		let primitive = match self.topology {
+			Topology::PointList => Primitive::PointList,
+			Topology::LineList => Primitive::LineList,
+			Topology::LineStrip => Primitive::LineStrip,
+			Topology::TriangleList => Primitive::TriangleList,
+			Topology::TriangleStrip => Primitive::TriangleStrip,
+		};
 
 		let mut pipeline_desc = GraphicsPipelineDesc::new(
 			PrimitiveAssemblerDesc::Vertex {
 				buffers: &vertex_buffers,
 				attributes: &vertex_attributes,
 				input_assembler: InputAssemblerDesc {
-					primitive: Primitive::TriangleList,
+					primitive,
 					with_adjacency: false,
 					restart_index: None,
 				},
@@ -136,7 +167,7 @@ impl<'a, B: Backend> PipelineBuilder<'a, B> {
 				tessellation: None,
 				geometry: None,
 			},
-			Rasterizer::FILL,
+			self.rasterizer,
 			Some(fragment_shader),
 			&pipeline_layout,
 			subpass,
@@ -159,14 +190,14 @@ impl<'a, B: Backend> PipelineBuilder<'a, B> {
 			device.destroy_shader_module(fragment_shader_mod);
 		}
 
-		Some(GraphicsPipeline::new(self.device.clone(), pipeline, pipeline_layout, self.stage, self.render_pass_id))
+		Some(GraphicsPipeline::new(self.device.clone(), pipeline, pipeline_layout, self.vertex_layout.clone(), self.stage, self.render_pass_id))
 	}
 
 	fn create_shader_module(&self, device: &B::Device, shader: &[u32]) -> Option<<B as Backend>::ShaderModule> {
 		return match unsafe { device.create_shader_module(shader) } {
 			Ok(module) => Some(module),
 			Err(_) => {
-				println!("Failed to create shader module!");
+				error!("Failed to create shader module!");
 				None
 			}
 		};
@@ -0,0 +1,19 @@
+use std::time::{Duration, Instant};
+
+/// CPU time a section of the last drawn frame took, e.g. recording the commands of a render stage.
+#[derive(Clone, Debug)]
+pub struct RenderTiming {
+	pub label: String,
+	pub start: Instant,
+	pub duration: Duration,
+}
+
+impl RenderTiming {
+	pub(crate) fn since(label: &str, start: Instant) -> Self {
+		RenderTiming {
+			label: label.to_string(),
+			start,
+			duration: start.elapsed(),
+		}
+	}
+}
@@ -0,0 +1,139 @@
+use std::cell::RefCell;
+use std::fs;
+use std::iter;
+use std::path::Path;
+use std::rc::Rc;
+use gfx_hal::Backend;
+use gfx_hal::adapter::MemoryType;
+use gfx_hal::command::{BufferImageCopy, CommandBuffer};
+use gfx_hal::format::{Aspects, Format};
+use gfx_hal::image::{Access, Extent, Layout, Offset, SubresourceLayers, SubresourceRange};
+use gfx_hal::memory::{Barrier, Dependencies};
+use gfx_hal::pso::PipelineStage;
+use crate::buffer::Buffer;
+use crate::core::CoreDevice;
+use crate::error::CaptureError;
+
+/// Pixels of a frame copied back from the gpu. Four bytes per pixel in RGBA order, rows from top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FramePixels {
+	pub width: u32,
+	pub height: u32,
+	pub data: Vec<u8>,
+}
+
+impl FramePixels {
+	pub(crate) fn from_target_format(extent: Extent, format: Format, mut data: Vec<u8>) -> Self {
+		data.truncate((extent.width * extent.height * 4) as usize);
+		if let Format::Bgra8Srgb | Format::Bgra8Unorm = format {
+			for pixel in data.chunks_exact_mut(4) {
+				pixel.swap(0, 2);
+			}
+		}
+
+		FramePixels {
+			width: extent.width,
+			height: extent.height,
+			data,
+		}
+	}
+
+	/// Encode the pixels as png, missing parent directories are created.
+	pub fn save_png(&self, path: &Path) -> Result<(), CaptureError> {
+		if let Some(directory) = path.parent() {
+			fs::create_dir_all(directory).map_err(|source| CaptureError::Io { path: directory.to_path_buf(), source })?;
+		}
+
+		image::save_buffer(path, &self.data, self.width, self.height, image::ColorType::Rgba8)
+			.map_err(|source| CaptureError::Encoding { path: path.to_path_buf(), source })
+	}
+
+	pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+		let index = ((y * self.width + x) * 4) as usize;
+		[self.data[index], self.data[index + 1], self.data[index + 2], self.data[index + 3]]
+	}
+}
+
+/// A frame which is copied into a cpu visible buffer. The data can be read once the commands of the frame completed.
+pub(crate) struct PendingReadback<B: Backend> {
+	pub(crate) buffer: Buffer<B>,
+	pub(crate) extent: Extent,
+	pub(crate) format: Format,
+}
+
+impl<B: Backend> PendingReadback<B> {
+	pub(crate) fn new(device: Rc<RefCell<CoreDevice<B>>>, memory_types: &[MemoryType], extent: Extent, format: Format) -> Self {
+		let size = (extent.width * extent.height * 4) as u64;
+		PendingReadback {
+			buffer: Buffer::new_empty(device, size, gfx_hal::buffer::Usage::TRANSFER_DST, memory_types),
+			extent,
+			format,
+		}
+	}
+
+	pub(crate) unsafe fn record_copy(&self, cmd_buffer: &mut B::CommandBuffer, image: &B::Image) {
+		record_image_copy::<B>(cmd_buffer, image, self.extent, self.buffer.get());
+	}
+
+	/// Read the copied pixels. The commands which copy the frame have to be completed.
+	pub(crate) fn read(mut self) -> FramePixels {
+		let size = (self.extent.width * self.extent.height * 4) as u64;
+		let data = self.buffer.read_data(size);
+		FramePixels::from_target_format(self.extent, self.format, data)
+	}
+}
+
+/// Record the copy of a color image, which has been rendered to, into a buffer.
+/// The image is transitioned back to the color attachment layout afterwards.
+unsafe fn record_image_copy<B: Backend>(cmd_buffer: &mut B::CommandBuffer, image: &B::Image, extent: Extent, buffer: &B::Buffer) {
+	let range = SubresourceRange {
+		aspects: Aspects::COLOR,
+		..Default::default()
+	};
+
+	let to_transfer = Barrier::Image {
+		states: (Access::COLOR_ATTACHMENT_WRITE, Layout::ColorAttachmentOptimal)..(Access::TRANSFER_READ, Layout::TransferSrcOptimal),
+		target: image,
+		families: None,
+		range: range.clone(),
+	};
+	cmd_buffer.pipeline_barrier(
+		PipelineStage::COLOR_ATTACHMENT_OUTPUT..PipelineStage::TRANSFER,
+		Dependencies::empty(),
+		iter::once(to_transfer),
+	);
+
+	cmd_buffer.copy_image_to_buffer(
+		image,
+		Layout::TransferSrcOptimal,
+		buffer,
+		iter::once(BufferImageCopy {
+			buffer_offset: 0,
+			buffer_width: extent.width,
+			buffer_height: extent.height,
+			image_layers: SubresourceLayers {
+				aspects: Aspects::COLOR,
+				level: 0,
+				layers: 0..1,
+			},
+			image_offset: Offset { x: 0, y: 0, z: 0 },
+			image_extent: Extent {
+				width: extent.width,
+				height: extent.height,
+				depth: 1,
+			},
+		}),
+	);
+
+	let to_attachment = Barrier::Image {
+		states: (Access::TRANSFER_READ, Layout::TransferSrcOptimal)..(Access::COLOR_ATTACHMENT_WRITE, Layout::ColorAttachmentOptimal),
+		target: image,
+		families: None,
+		range,
+	};
+	cmd_buffer.pipeline_barrier(
+		PipelineStage::TRANSFER..PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+		Dependencies::empty(),
+		iter::once(to_attachment),
+	);
+}
@@ -36,16 +36,21 @@ pub struct RenderPassController<B: Backend> {
 	device: Rc<RefCell<CoreDevice<B>>>,
 	render_images: u32,
 	image_extent: Extent,
+	color_usage: Usage,
 	entries: Vec<RenderPassEntry<B>>,
+	clear_entry: Option<RenderPassEntry<B>>,
 }
 
 impl<B: Backend> RenderPassController<B> {
-	pub fn new(device: Rc<RefCell<CoreDevice<B>>>, extent: &Extent, image_amount: u32) -> Self {
+	/// The color usage has to match the usage of the images which are attached to the framebuffers.
+	pub fn new(device: Rc<RefCell<CoreDevice<B>>>, extent: &Extent, image_amount: u32, color_usage: Usage) -> Self {
 		Self {
 			device,
 			image_extent: extent.clone(),
+			color_usage,
 			render_images: image_amount,
 			entries: vec![],
+			clear_entry: None,
 		}
 	}
 
@@ -68,6 +73,20 @@ impl<B: Backend> RenderPassController<B> {
 		entry_id
 	}
 
+	/// Recreate the framebuffers of all render passes with a new image extent, e.g. after the render target changed its size.
+	pub fn recreate_framebuffers(&mut self, extent: &Extent, color_usage: Usage) {
+		self.image_extent = extent.clone();
+		self.color_usage = color_usage;
+		for index in 0..self.entries.len() {
+			let framebuffer = self.create_framebuffer(&self.entries[index].descriptor, &self.entries[index].render_pass);
+			self.entries[index].framebuffer = framebuffer;
+		}
+		if let Some(clear_entry) = self.clear_entry.as_ref() {
+			let framebuffer = self.create_framebuffer(&clear_entry.descriptor, &clear_entry.render_pass);
+			self.clear_entry.as_mut().unwrap().framebuffer = framebuffer;
+		}
+	}
+
 	pub fn get_render_pass_ref(&self, id: usize) -> &B::RenderPass {
 		self.entries[id].render_pass.get()
 	}
@@ -80,6 +99,27 @@ impl<B: Backend> RenderPassController<B> {
 		Some((framebuffer, render_pass))
 	}
 
+	/// The pass which clears the whole target at the beginning of a frame. The render passes of the stages
+	/// keep the content of the target, so multiple views can be drawn on top of each other.
+	/// None as long as no render pass of a stage exists, because the formats of the attachments are not known yet.
+	pub fn get_clear_pass_data(&mut self) -> Option<(&B::Framebuffer, &B::RenderPass)> {
+		if self.clear_entry.is_none() {
+			let descriptor = self.entries.first()?.descriptor.clone();
+			let render_pass = self.create_clear_pass(&descriptor);
+			let framebuffer = self.create_framebuffer(&descriptor, &render_pass);
+			self.clear_entry = Some(RenderPassEntry {
+				render_pass,
+				framebuffer,
+				descriptor,
+			});
+		}
+
+		let entry = self.clear_entry.as_mut().unwrap();
+		let framebuffer = entry.framebuffer.get_frame_data();
+		let render_pass = entry.render_pass.get();
+		Some((framebuffer, render_pass))
+	}
+
 
 	fn does_desc_already_exists(&self, desc: &RenderPassDescriptor) -> Option<usize> {
 		for (idx, entry) in self.entries.iter().enumerate() {
@@ -91,36 +131,49 @@ impl<B: Backend> RenderPassController<B> {
 	}
 
 	fn create_color_attachment(&self, image_format: &Format, load_op: AttachmentLoadOp) -> Attachment {
+		// Loaded content has to stay in the layout it was written in
+		let initial_layout = match load_op {
+			AttachmentLoadOp::Load => Layout::ColorAttachmentOptimal,
+			_ => Layout::Undefined,
+		};
 		let color_attachment = Attachment {
 			format: Some(image_format.clone()),
 			samples: 1,
 			ops: AttachmentOps::new(load_op, AttachmentStoreOp::Store),
 			stencil_ops: AttachmentOps::DONT_CARE,
-			layouts: Layout::Undefined..Layout::ColorAttachmentOptimal,
+			layouts: initial_layout..Layout::ColorAttachmentOptimal,
 		};
 		color_attachment
 	}
 
 	fn create_depth_attachment(&self, depth_format: &Format, load_op: AttachmentLoadOp) -> Attachment{
+		let initial_layout = match load_op {
+			AttachmentLoadOp::Load => Layout::DepthStencilAttachmentOptimal,
+			_ => Layout::Undefined,
+		};
 		let depth_attachment = Attachment {
 			format: Some(depth_format.clone()),
 			samples: 1,
 			ops: AttachmentOps::new(load_op, AttachmentStoreOp::Store),
 			stencil_ops: AttachmentOps::DONT_CARE,
-			layouts: Layout::Undefined..Layout::DepthStencilAttachmentOptimal,
+			layouts: initial_layout..Layout::DepthStencilAttachmentOptimal,
 		};
 		depth_attachment
 	}
 
 	fn create_render_pass(&self, desc: &RenderPassDescriptor) -> RenderPass<B> {
-		let attachment_load_op = match desc.render_stage {
-			RenderStage::None => panic!("Render stage none is not allowed at this point anymore!"),
-			RenderStage::Opaque => AttachmentLoadOp::Clear,
-			RenderStage::Transparent => AttachmentLoadOp::Load,
-			RenderStage::UI => AttachmentLoadOp::Clear,
-		};
+		// The target is cleared by the clear pass and the views clear their own viewport
+		if desc.render_stage == RenderStage::None {
+			panic!("Render stage none is not allowed at this point anymore!");
+		}
+		self.build_render_pass(desc, AttachmentLoadOp::Load, &desc.render_stage.to_string())
+	}
 
+	fn create_clear_pass(&self, desc: &RenderPassDescriptor) -> RenderPass<B> {
+		self.build_render_pass(desc, AttachmentLoadOp::Clear, "Clear Pass")
+	}
 
+	fn build_render_pass(&self, desc: &RenderPassDescriptor, attachment_load_op: AttachmentLoadOp, name: &str) -> RenderPass<B> {
 		let color_attachment = Some(self.create_color_attachment(&desc.image_format, attachment_load_op ));
 
 		let mut depth_attachment = None;
@@ -136,10 +189,13 @@ impl<B: Backend> RenderPassController<B> {
 			preserves: &[],
 		};
 
+		// The previous pass of the frame wrote to the same attachments
+		let attachment_stages = PipelineStage::COLOR_ATTACHMENT_OUTPUT | PipelineStage::EARLY_FRAGMENT_TESTS | PipelineStage::LATE_FRAGMENT_TESTS;
+		let attachment_writes = Access::COLOR_ATTACHMENT_WRITE | Access::DEPTH_STENCIL_ATTACHMENT_WRITE;
 		let dependency = SubpassDependency {
 			passes: Range { start: None, end: Some(0) },
-			stages: Range { start: PipelineStage::COLOR_ATTACHMENT_OUTPUT | PipelineStage::EARLY_FRAGMENT_TESTS, end: PipelineStage::COLOR_ATTACHMENT_OUTPUT | PipelineStage::EARLY_FRAGMENT_TESTS },
-			accesses: Range { start: Access::empty(), end: Access::COLOR_ATTACHMENT_WRITE | Access::DEPTH_STENCIL_ATTACHMENT_WRITE },
+			stages: Range { start: attachment_stages, end: attachment_stages },
+			accesses: Range { start: attachment_writes, end: attachment_writes | Access::COLOR_ATTACHMENT_READ | Access::DEPTH_STENCIL_ATTACHMENT_READ },
 			flags: Dependencies::VIEW_LOCAL,
 		};
 
@@ -150,13 +206,13 @@ impl<B: Backend> RenderPassController<B> {
 			.set_render_stage(desc.render_stage.clone())
 			.add_subpass(subpass)
 			.add_dependency(dependency)
-			.add_name(&desc.render_stage.to_string())
+			.add_name(name)
 			.build();
 
 		match render_pass {
 			Ok(rp) => rp,
 			Err(_) => {
-				panic!("Could not create {}", &desc.render_stage.to_string());
+				panic!("Could not create {}", name);
 			}
 		}
 	}
@@ -164,7 +220,7 @@ impl<B: Backend> RenderPassController<B> {
 	fn create_framebuffer_attachments(&self, desc: &RenderPassDescriptor) -> Vec<FramebufferAttachment> {
 		let mut attachments = vec![FramebufferAttachment {
 			format: desc.image_format,
-			usage: Usage::COLOR_ATTACHMENT,
+			usage: self.color_usage,
 			view_caps: ViewCapabilities::empty(),
 		}];
 
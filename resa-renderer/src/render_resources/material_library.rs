@@ -3,10 +3,11 @@ use std::collections::HashMap;
 use std::rc::Rc;
 use gfx_hal::adapter::MemoryType;
 use gfx_hal::{Backend, Limits};
+use log::warn;
 use crate::core::CoreDevice;
 use crate::image_buffer::ImageBuffer;
-use crate::material::{Material, MaterialRef};
-use crate::render_resources::texture_buffer_library::{TBORef, TextureBufferLibrary};
+use crate::material::{Material, MaterialRef, SamplerSettings, Texture, TextureFormat};
+use crate::render_resources::texture_library::{TBORef, TextureLibrary};
 use crate::render_resources::uniform_buffer_library::{UBORef, UniformBufferLibrary};
 use crate::render_stage::RenderStage;
 use crate::uniform::Uniform;
@@ -15,13 +16,14 @@ struct MaterialEntry {
 	material: Material,
 	ubo_ref: UBORef,
 	texture_ref: TBORef,
-	render_stage_index: u16
+	/// Pending textures belong to their material, updating the material replaces their image
+	owns_texture: bool,
 }
 
 pub struct MaterialLibrary<B: Backend> {
 	material_map: HashMap<MaterialRef, MaterialEntry>,
 	ubo_library: UniformBufferLibrary<B>,
-	tbo_library: TextureBufferLibrary<B>,
+	texture_lib: TextureLibrary<B>,
 	last_entry_id: usize,
 }
 
@@ -30,28 +32,25 @@ impl<B: Backend> MaterialLibrary<B> {
 		MaterialLibrary {
 			material_map: HashMap::new(),
 			ubo_library: UniformBufferLibrary::new(device_ptr.clone(), memory_types.clone()),
-			tbo_library: TextureBufferLibrary::new(device_ptr.clone(), memory_types.clone(), adapter_limits),
+			texture_lib: TextureLibrary::new(device_ptr.clone(), memory_types.clone(), adapter_limits),
 			last_entry_id: 0,
 		}
 	}
 
-	pub fn add_materials(&mut self, materials: &[(Material, u16)]) -> Vec<MaterialRef> {
-		let materials_ubo_data = materials.iter().map(|mat| (mat.0.get_ubo_data())).collect();
+	pub fn add_materials(&mut self, materials: &[Material]) -> Vec<MaterialRef> {
+		let materials_ubo_data = materials.iter().map(|mat| (mat.get_ubo_data())).collect();
 		let ubo_refs = self.ubo_library.add_buffers(materials_ubo_data);
 
-		let materials_tbo_data = materials.iter().map(|mat| mat.0.texture.clone()).collect();
-		let tbo_refs = self.tbo_library.add_texture_buffer(materials_tbo_data);
-
 		let mut material_refs = vec![];
-		for (index, (material, render_stage_id)) in materials.iter().enumerate() {
+		for (index, material) in materials.iter().enumerate() {
 			let material_ref = MaterialRef(self.last_entry_id);
 			self.last_entry_id += 1;
 
 			let entry = MaterialEntry {
 				material: material.clone(),
 				ubo_ref: ubo_refs[index],
-				texture_ref: tbo_refs[index],
-				render_stage_index: render_stage_id.clone(),
+				texture_ref: resolve_texture(&mut self.texture_lib, material),
+				owns_texture: matches!(material.texture, Texture::Pending(..)),
 			};
 
 			self.material_map.insert(material_ref, entry);
@@ -64,10 +63,40 @@ impl<B: Backend> MaterialLibrary<B> {
 		let mut entry = self.material_map.get_mut(material_ref).unwrap();
 
 		self.ubo_library.update_buffer(&entry.ubo_ref, new_material.get_ubo_data().clone());
-		// self.tbo_library.update_texture_buffer(&entry.texture_ref, new_material.texture.clone());
+		if new_material.texture != entry.material.texture {
+			match (&new_material.texture, entry.owns_texture) {
+				(Texture::Pending(data, format, sampler), true) => self.texture_lib.replace_texture(&entry.texture_ref, data.clone(), format.clone(), *sampler),
+				(_, owns_texture) => {
+					// The own texture of the material is not used by any other material
+					if owns_texture {
+						self.texture_lib.retire_texture(&entry.texture_ref);
+					}
+					entry.texture_ref = resolve_texture(&mut self.texture_lib, &new_material);
+					entry.owns_texture = matches!(new_material.texture, Texture::Pending(..));
+				}
+			}
+		}
 		entry.material = new_material;
 	}
 
+	/// Register a texture which materials reference by name. Adding a texture with the name of an existing one replaces it.
+	pub fn add_texture(&mut self, name: &str, data: Vec<u8>, format: TextureFormat, sampler: SamplerSettings) {
+		self.texture_lib.add_texture(name, data, format, sampler);
+	}
+
+	/// Replace the image of a named texture for all materials which use it, it keeps its sampler settings.
+	pub fn update_texture(&mut self, name: &str, data: Vec<u8>, format: TextureFormat) {
+		self.texture_lib.update_texture_with_name(name, data, format);
+	}
+
+	pub(crate) fn has_unused_textures(&self) -> bool {
+		self.texture_lib.has_unused_textures()
+	}
+
+	pub(crate) fn free_unused_textures(&mut self) {
+		self.texture_lib.free_unused_textures();
+	}
+
 	#[allow(dead_code, unused)]
 	pub fn remove_material(&mut self, material_ref: MaterialRef) {
 		todo!()
@@ -86,19 +115,38 @@ impl<B: Backend> MaterialLibrary<B> {
 		Some(&self.material_map.get(material_ref)?.material)
 	}
 
+	/// The texture the material is drawn with, the missing texture while its texture is not available.
+	pub fn get_texture_ref(&self, material_ref: &MaterialRef) -> Option<TBORef> {
+		Some(self.texture_lib.get_drawn_ref(&self.material_map.get(material_ref)?.texture_ref))
+	}
+
 	pub fn get_material_mut(&mut self, material_ref: &MaterialRef) -> Option<&mut Material> {
 		Some(&mut self.material_map.get_mut(material_ref)?.material)
 	}
 
 	pub(crate) fn get_render_data(&self, material_ref: &MaterialRef) -> (&Uniform<B>, &ImageBuffer<B>, &RenderStage) {
 		let entry = &self.material_map[material_ref];
-		(self.ubo_library.get_uniform_buffer(&entry.ubo_ref), self.tbo_library.get_texture_buffer(&entry.texture_ref), &entry.material.render_stage)
+		(self.ubo_library.get_uniform_buffer(&entry.ubo_ref), self.texture_lib.get_texture_buffer(&entry.texture_ref), &entry.material.render_stage)
 	}
 
 	pub(crate) fn get_descriptor_layouts(&self) -> Vec<&<B as Backend>::DescriptorSetLayout> {
-		let texture_buffer = self.tbo_library.get_texture_buffer(&TextureBufferLibrary::<B>::get_default_ref());
+		let texture_buffer = self.texture_lib.get_texture_buffer(&TextureLibrary::<B>::get_default_ref());
 		let uniform_buffer = self.ubo_library.get_uniform_buffer(&UniformBufferLibrary::<B>::get_default_uniform_ref());
 
 		vec![texture_buffer.get_layout(), uniform_buffer.get_layout()]
 	}
 }
+
+/// The texture a material is drawn with. Textures which are referenced by name but not registered are drawn as missing texture,
+/// until a texture with the name is added.
+fn resolve_texture<B: Backend>(texture_lib: &mut TextureLibrary<B>, material: &Material) -> TBORef {
+	match &material.texture {
+		Texture::None => TextureLibrary::<B>::get_default_ref(),
+		Texture::Pending(data, format, sampler) => texture_lib.add_unnamed_texture(data.clone(), format.clone(), *sampler),
+		Texture::Named(name) => texture_lib.get_texture_from_name(name).unwrap_or_else(|| {
+			warn!("Texture {} of material {} is not registered", name, material.name);
+			texture_lib.reserve_texture(name)
+		}),
+		Texture::Some(tbo_ref) => *tbo_ref,
+	}
+}
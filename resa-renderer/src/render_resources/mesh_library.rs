@@ -2,25 +2,147 @@ use std::cell::RefCell;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
-use gfx_hal::Backend;
+use std::mem;
+use std::mem::size_of;
+use std::rc::{Rc, Weak};
+use gfx_hal::{Backend, IndexType};
 use gfx_hal::adapter::MemoryType;
 use gfx_hal::buffer::Usage;
+use crate::bounds::Bounds;
 use crate::buffer::Buffer;
 use crate::core::CoreDevice;
-use crate::mesh::Mesh;
-use crate::vertex::Vertex;
+use crate::error::RenderError;
+use crate::mesh::{IndexFormat, Mesh, MeshHandle, MeshHandleInner, MeshSharing, MeshUsage, Topology};
+use crate::vertex::{AttributeData, VertexAttribute, VertexLayout};
+
+/// Number of buffer sets of a dynamic mesh. One frame is in flight, so an update writes the set the frame does not read.
+const DYNAMIC_BUFFER_SETS: usize = 2;
 
 pub(crate) struct MeshEntry<B: Backend> {
 	mesh: Mesh,
 	mesh_hash: u64,
-	pub(crate) instances: u64,
-	pub(crate) vertex_buffer: Buffer<B>,
-	pub(crate) index_buffer: Buffer<B>,
+	sharing: MeshSharing,
+	usage: MeshUsage,
+	/// The handles of the mesh, the entry is freed when the last of them is dropped
+	handle: Weak<MeshHandleInner>,
+	bounds: Bounds,
+	layout: VertexLayout,
+	/// One set for static meshes, a ring of sets for dynamic meshes
+	buffer_sets: Vec<MeshBuffers<B>>,
+	/// The set which the next frame draws
+	current_set: usize,
+	/// The frame the current set was last written in. The set is not read by the gpu until that frame is drawn.
+	written_frame: u64,
+}
+
+/// The vertex and index buffers of a mesh, with room for at least the vertices and indices of the mesh.
+pub(crate) struct MeshBuffers<B: Backend> {
+	layout: VertexLayout,
+	/// One buffer per attribute stream, in the order of the layout.
+	vertex_buffers: Vec<Buffer<B>>,
+	index_buffer: Option<IndexBuffer<B>>,
+	vertex_capacity: usize,
+	index_capacity: usize,
+}
+
+pub(crate) struct IndexBuffer<B: Backend> {
+	pub(crate) buffer: Buffer<B>,
+	pub(crate) index_type: IndexType,
+}
+
+impl<B: Backend> MeshEntry<B> {
+	/// The buffers of the attributes the layout consumes, in the order of the layout.
+	/// None if the mesh does not provide all of them.
+	pub(crate) fn get_vertex_buffers(&self, layout: &VertexLayout) -> Option<Vec<&B::Buffer>> {
+		let buffers = &self.buffer_sets[self.current_set];
+		layout.get_attributes().iter().map(|attribute| {
+			let index = buffers.layout.get_attributes().iter().position(|provided| provided == attribute)?;
+			Some(buffers.vertex_buffers[index].get())
+		}).collect()
+	}
+
+	pub(crate) fn get_index_buffer(&self) -> Option<&IndexBuffer<B>> {
+		self.buffer_sets[self.current_set].index_buffer.as_ref()
+	}
+
+	pub(crate) fn get_layout(&self) -> &VertexLayout {
+		&self.layout
+	}
+
+	/// The amount of indices to draw, or of vertices if the mesh has no index buffer.
+	pub(crate) fn get_draw_count(&self) -> u32 {
+		self.mesh.get_draw_count() as u32
+	}
+
+	pub(crate) fn get_topology(&self) -> Topology {
+		self.mesh.get_topology()
+	}
+}
+
+impl<B: Backend> MeshBuffers<B> {
+	/// Buffers with room for the given number of vertices and indices, filled with the mesh.
+	fn new(device_ptr: &Rc<RefCell<CoreDevice<B>>>, memory_types: &[MemoryType], mesh: &Mesh, vertex_capacity: usize, index_capacity: usize) -> Self {
+		let layout = mesh.get_layout();
+		// Buffers can not be empty, meshes without vertices get room for one
+		let vertex_buffers = layout.get_attributes().iter().map(|attribute| {
+			let size = (vertex_capacity.max(1) * attribute.get_kind().get_size()) as u64;
+			Buffer::new_empty(Rc::clone(device_ptr), size, Usage::VERTEX, memory_types)
+		}).collect();
+
+		let index_buffer = mesh.get_index_format().map(|index_format| {
+			let index_type = get_index_type(index_format);
+			let index_size = if index_type == IndexType::U16 { size_of::<u16>() } else { size_of::<u32>() };
+			IndexBuffer {
+				buffer: Buffer::new_empty(Rc::clone(device_ptr), (index_capacity.max(1) * index_size) as u64, Usage::INDEX, memory_types),
+				index_type,
+			}
+		});
+
+		let mut buffers = MeshBuffers {
+			layout,
+			vertex_buffers,
+			index_buffer,
+			vertex_capacity,
+			index_capacity,
+		};
+		buffers.write(mesh);
+		buffers
+	}
+
+	/// Whether the mesh can be written into the buffers without reallocating them.
+	fn fits(&self, mesh: &Mesh) -> bool {
+		let fits_indices = match (&self.index_buffer, mesh.get_indices()) {
+			(Some(index_buffer), Some(indices)) => indices.len() <= self.index_capacity
+				&& (index_buffer.index_type == IndexType::U32 || mesh.get_index_format() == Some(IndexFormat::U16)),
+			(None, None) => true,
+			_ => false,
+		};
+		self.layout == mesh.get_layout() && mesh.get_vertex_count() <= self.vertex_capacity && fits_indices
+	}
+
+	/// Write the mesh to the start of the buffers, it has to fit into them.
+	fn write(&mut self, mesh: &Mesh) {
+		for (attribute, buffer) in self.layout.get_attributes().iter().zip(self.vertex_buffers.iter_mut()) {
+			write_attribute(buffer, mesh, attribute);
+		}
+		if let (Some(index_buffer), Some(indices)) = (self.index_buffer.as_mut(), mesh.get_indices()) {
+			match index_buffer.index_type {
+				IndexType::U16 => index_buffer.buffer.update_data(0, &indices.iter().map(|index| *index as u16).collect::<Vec<u16>>()),
+				_ => index_buffer.buffer.update_data(0, indices),
+			}
+		}
+	}
 }
 
 pub struct MeshLibrary<B: Backend> {
 	pub(crate) mesh_map: HashMap<u64, MeshEntry<B>>,
+	/// The ids of the shared meshes by the hash of their content
+	shared_meshes: HashMap<u64, Vec<u64>>,
+	released: Rc<RefCell<Vec<u64>>>,
+	/// Buffers which were replaced by updates, the frame in flight might still read them
+	retired_buffers: Vec<MeshBuffers<B>>,
+	/// The number of frames drawn
+	frame: u64,
 	last_entry: u64,
 	device_ptr: Rc<RefCell<CoreDevice<B>>>,
 	memory_types: Vec<MemoryType>,
@@ -30,102 +152,195 @@ impl<B: Backend> MeshLibrary<B> {
 	pub fn new(device_ptr: Rc<RefCell<CoreDevice<B>>>, memory_types: Vec<MemoryType>) -> Self {
 		MeshLibrary {
 			mesh_map: HashMap::new(),
+			shared_meshes: HashMap::new(),
+			released: Rc::new(RefCell::new(vec![])),
+			retired_buffers: vec![],
+			frame: 0,
 			last_entry: 0,
 			device_ptr,
 			memory_types,
 		}
 	}
 
-	pub fn add_mesh(&mut self, mesh: Mesh) -> u64 {
-		let mut hasher = DefaultHasher::new();
-		mesh.hash(&mut hasher);
-		let mesh_hash = hasher.finish();
+	/// Register a mesh. A shared mesh with the content of another shared mesh gets a handle to the existing mesh,
+	/// the content is compared so meshes with colliding hashes are kept apart.
+	pub fn add_mesh(&mut self, mesh: Mesh, sharing: MeshSharing, usage: MeshUsage) -> MeshHandle {
+		let mesh_hash = get_mesh_hash(&mesh);
 
-		let mesh_id = match self.get_mesh_id(&mesh_hash) {
-			Some(id) => {
-				let entry = self.mesh_map.get_mut(&id).unwrap();
-				entry.instances += 1;
-				id
+		if sharing == MeshSharing::Shared {
+			if let Some(mesh_id) = self.find_shared_mesh(mesh_hash, &mesh) {
+				return self.get_handle(mesh_id);
 			}
-			None => {
-				let (vertex, index) = self.create_buffers(&mesh);
-				let entry = MeshEntry {
-					mesh,
-					mesh_hash,
-					instances: 1,
-					vertex_buffer: vertex,
-					index_buffer: index,
-				};
-				let entry_id = self.last_entry;
-				self.last_entry += 1;
-				self.mesh_map.insert(entry_id, entry);
-				entry_id
-			}
-		};
+		}
 
-		mesh_id
+		let buffer_sets = match usage {
+			MeshUsage::Static => vec![self.create_buffers(&mesh, usage)],
+			MeshUsage::Dynamic => (0..DYNAMIC_BUFFER_SETS).map(|_| self.create_buffers(&mesh, usage)).collect(),
+		};
+		let mesh_id = self.last_entry;
+		self.last_entry += 1;
+		let handle = MeshHandle::new(mesh_id, Rc::clone(&self.released));
+		let entry = MeshEntry {
+			bounds: Bounds::from_positions(mesh.get_positions()),
+			layout: mesh.get_layout(),
+			mesh,
+			mesh_hash,
+			sharing,
+			usage,
+			handle: handle.downgrade(),
+			buffer_sets,
+			current_set: 0,
+			written_frame: self.frame,
+		};
+		self.mesh_map.insert(mesh_id, entry);
+		if sharing == MeshSharing::Shared {
+			self.shared_meshes.entry(mesh_hash).or_default().push(mesh_id);
+		}
+		handle
 	}
 
-	pub fn update_mesh(&mut self, mesh_id: &u64, new_mesh: Mesh) {
-		let mut entry = match self.mesh_map.get_mut(mesh_id) {
-			Some(entry) => entry,
-			None => {
-				println!("Try to remove mesh with id {}, but this does not exists!", mesh_id);
-				return;
-			}
-		};
-		let mut hasher = DefaultHasher::new();
-		new_mesh.hash(&mut hasher);
-		let mesh_hash = hasher.finish();
+	/// Replace the data of the mesh. The buffers the frame in flight reads are never written: dynamic meshes write
+	/// the next set of their ring, static meshes get new buffers. Buffers which are too small are replaced by bigger ones,
+	/// the old buffers are destroyed once the gpu is done with them.
+	/// An update of a shared mesh changes it for all of its handles.
+	pub fn update_mesh(&mut self, mesh_id: &u64, new_mesh: Mesh) -> Result<(), RenderError> {
+		let mesh_hash = get_mesh_hash(&new_mesh);
+		let entry = self.mesh_map.get_mut(mesh_id).ok_or(RenderError::MeshNotFound(*mesh_id))?;
 
-		entry.vertex_buffer.update_data(0, &new_mesh.vertices);
-		entry.index_buffer.update_data(0, &new_mesh.indices);
+		// A set written since the last frame was not submitted yet, so it can be written again
+		let current_in_flight = entry.written_frame != self.frame;
+		if current_in_flight {
+			entry.current_set = (entry.current_set + 1) % entry.buffer_sets.len();
+		}
+		let static_in_flight = current_in_flight && entry.buffer_sets.len() == 1;
+
+		let buffers = &mut entry.buffer_sets[entry.current_set];
+		if buffers.fits(&new_mesh) && !static_in_flight {
+			buffers.write(&new_mesh);
+		} else {
+			let (vertex_capacity, index_capacity) = get_capacity(&new_mesh, entry.usage);
+			let new_buffers = MeshBuffers::new(&self.device_ptr, &self.memory_types, &new_mesh, vertex_capacity, index_capacity);
+			self.retired_buffers.push(mem::replace(buffers, new_buffers));
+		}
+		entry.written_frame = self.frame;
+
+		let old_hash = entry.mesh_hash;
+		let shared = entry.sharing == MeshSharing::Shared;
 		entry.mesh_hash = mesh_hash;
+		entry.bounds = Bounds::from_positions(new_mesh.get_positions());
+		entry.layout = new_mesh.get_layout();
+		entry.mesh = new_mesh;
+
+		// Other registrations of the new content get this mesh from now on
+		if shared && old_hash != mesh_hash {
+			self.remove_shared_mesh(old_hash, *mesh_id);
+			self.shared_meshes.entry(mesh_hash).or_default().push(*mesh_id);
+		}
+		Ok(())
 	}
 
-	pub fn remove_mesh(&mut self, mesh_id: &u64) {
-		let mut entry = match self.mesh_map.get_mut(mesh_id) {
-			Some(entry) => entry,
-			None => {
-				println!("Try to remove mesh with id {}, but this does not exists!", mesh_id);
-				return;
-			}
-		};
+	/// Count the frame as drawn, updates from now on must not write the buffers it reads.
+	pub(crate) fn finish_frame(&mut self) {
+		self.frame += 1;
+	}
 
-		entry.instances -= 1;
-		if entry.instances > 0 { return; }
+	/// Whether handles were dropped or buffers replaced since the unused buffers were freed the last time.
+	pub(crate) fn has_unused_buffers(&self) -> bool {
+		!self.released.borrow().is_empty() || !self.retired_buffers.is_empty()
+	}
 
-		self.mesh_map.remove(mesh_id);
+	/// Free the replaced buffers and the meshes whose last handle was dropped. The gpu must not use them anymore.
+	/// Meshes which got a new handle in the meantime, by registering their content again, are kept.
+	pub(crate) fn free_unused_buffers(&mut self) {
+		self.retired_buffers.clear();
+		let released: Vec<u64> = self.released.borrow_mut().drain(..).collect();
+		for mesh_id in released {
+			let in_use = self.mesh_map.get(&mesh_id).map_or(true, |entry| entry.handle.strong_count() > 0);
+			if in_use {
+				continue;
+			}
+			let entry = self.mesh_map.remove(&mesh_id).unwrap();
+			if entry.sharing == MeshSharing::Shared {
+				self.remove_shared_mesh(entry.mesh_hash, mesh_id);
+			}
+		}
 	}
 
 	pub(crate) fn get_mesh_entry(&self, mesh_id: &u64) -> &MeshEntry<B> {
 		self.mesh_map.get(mesh_id).unwrap()
 	}
 
-	pub(crate) fn get_mesh_index_amount(&self, &mesh_id: &u64) -> u32 {
-		self.mesh_map.get(&mesh_id).unwrap().mesh.indices.len() as u32
+	/// The bounds of the mesh in its local space, computed when the mesh was added or updated.
+	pub fn get_mesh_bounds(&self, mesh_id: &u64) -> Option<&Bounds> {
+		self.mesh_map.get(mesh_id).map(|entry| &entry.bounds)
 	}
 
-	fn get_mesh_id(&self, mesh_hash: &u64) -> Option<u64> {
-		let result = self.mesh_map.iter().find(|mesh| &mesh.1.mesh_hash == mesh_hash)?;
-		Some(result.0.clone())
+	pub(crate) fn get_mesh_topology(&self, mesh_id: &u64) -> Option<Topology> {
+		self.mesh_map.get(mesh_id).map(|entry| entry.get_topology())
 	}
 
-	fn create_buffers(&self, mesh: &Mesh) -> (Buffer<B>, Buffer<B>) {
-		let vertex_buffer = Buffer::new::<Vertex>(
-			Rc::clone(&self.device_ptr),
-			&mesh.vertices,
-			Usage::VERTEX,
-			&self.memory_types,
-		);
+	fn find_shared_mesh(&self, mesh_hash: u64, mesh: &Mesh) -> Option<u64> {
+		self.shared_meshes.get(&mesh_hash)?.iter()
+			.find(|mesh_id| self.mesh_map[mesh_id].mesh.has_same_content(mesh))
+			.copied()
+	}
+
+	fn remove_shared_mesh(&mut self, mesh_hash: u64, mesh_id: u64) {
+		if let Some(mesh_ids) = self.shared_meshes.get_mut(&mesh_hash) {
+			mesh_ids.retain(|id| *id != mesh_id);
+			if mesh_ids.is_empty() {
+				self.shared_meshes.remove(&mesh_hash);
+			}
+		}
+	}
 
-		let index_buffer = Buffer::new::<u16>(
-			Rc::clone(&self.device_ptr),
-			&mesh.indices,
-			Usage::INDEX,
-			&self.memory_types,
-		);
+	/// A new handle to an existing mesh. If all of its handles were dropped but the mesh was not freed yet, it is revived.
+	fn get_handle(&mut self, mesh_id: u64) -> MeshHandle {
+		let entry = self.mesh_map.get_mut(&mesh_id).unwrap();
+		MeshHandle::upgrade(&entry.handle).unwrap_or_else(|| {
+			let handle = MeshHandle::new(mesh_id, Rc::clone(&self.released));
+			entry.handle = handle.downgrade();
+			handle
+		})
+	}
+
+	fn create_buffers(&self, mesh: &Mesh, usage: MeshUsage) -> MeshBuffers<B> {
+		let (vertex_capacity, index_capacity) = get_capacity(mesh, usage);
+		MeshBuffers::new(&self.device_ptr, &self.memory_types, mesh, vertex_capacity, index_capacity)
+	}
+}
+
+/// Static meshes get buffers of their size. The buffers of dynamic meshes grow to the next power of two,
+/// so meshes which grow a little every frame are not reallocated every frame.
+fn get_capacity(mesh: &Mesh, usage: MeshUsage) -> (usize, usize) {
+	let index_count = mesh.get_indices().map_or(0, |indices| indices.len());
+	match usage {
+		MeshUsage::Static => (mesh.get_vertex_count(), index_count),
+		MeshUsage::Dynamic => (mesh.get_vertex_count().next_power_of_two(), index_count.next_power_of_two()),
+	}
+}
+
+fn get_index_type(index_format: IndexFormat) -> IndexType {
+	match index_format {
+		IndexFormat::U16 => IndexType::U16,
+		IndexFormat::U32 => IndexType::U32,
+	}
+}
+
+fn get_mesh_hash(mesh: &Mesh) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	mesh.hash(&mut hasher);
+	hasher.finish()
+}
 
-		(vertex_buffer, index_buffer)
+fn write_attribute<B: Backend>(buffer: &mut Buffer<B>, mesh: &Mesh, attribute: &VertexAttribute) {
+	match attribute {
+		VertexAttribute::Position => buffer.update_data(0, mesh.get_positions()),
+		_ => match mesh.get_attribute(*attribute).unwrap() {
+			AttributeData::Float2(values) => buffer.update_data(0, values),
+			AttributeData::Float3(values) => buffer.update_data(0, values),
+			AttributeData::Float4(values) => buffer.update_data(0, values),
+			AttributeData::UShort4(values) => buffer.update_data(0, values),
+		},
 	}
 }
\ No newline at end of file
@@ -8,9 +8,8 @@ use crate::shader::ShaderRef;
 pub mod material_library;
 pub mod mesh_library;
 pub mod shader_library;
-pub mod texture_buffer_library;
 mod uniform_buffer_library;
-mod texture_library;
+pub mod texture_library;
 
 
 pub struct RenderResources<B: Backend> {
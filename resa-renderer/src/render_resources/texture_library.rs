@@ -1,56 +1,78 @@
 use std::cell::RefCell;
-use std::io::Cursor;
-use image::{Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::iter;
+use std::mem;
 use std::rc::Rc;
-use gfx_hal::adapter::MemoryType;
-use gfx_hal::{Backend, Limits};
+use image::{Rgba, RgbaImage};
+use gfx_hal::adapter::{MemoryType, PhysicalDevice};
+use gfx_hal::{Backend, Features, Limits};
 use gfx_hal::buffer::Usage;
 use gfx_hal::device::Device;
+use gfx_hal::format::ImageFeature;
 use gfx_hal::pool::CommandPoolCreateFlags;
-use gfx_hal::pso::{DescriptorPoolCreateFlags, DescriptorRangeDesc, DescriptorSetLayoutBinding, DescriptorType, ImageDescriptorType, ShaderStageFlags};
+use gfx_hal::pso::{DescriptorPool, DescriptorPoolCreateFlags, DescriptorRangeDesc, DescriptorSetLayoutBinding, DescriptorType, ImageDescriptorType, ShaderStageFlags};
+use log::warn;
 use crate::core::CoreDevice;
 use crate::descriptors::{DescSet, DescSetLayout};
 use crate::image_buffer::ImageBuffer;
-use crate::material::TextureFormat;
+use crate::material::{ColorSpace, SamplerSettings, TextureFilter, TextureFormat, TextureWrap};
+use crate::texture_image::TextureImage;
+
+/// Number of textures whose descriptor sets are allocated from one pool.
+const POOL_CAPACITY: usize = 16;
+/// The white texture of materials without texture and the checker of missing textures come first.
+const DEFAULT_TEXTURE: usize = 0;
+const MISSING_TEXTURE: usize = 1;
+/// Size of the squares of the missing texture checker in pixels, and the number of squares per side.
+const CHECKER_SQUARE_SIZE: u32 = 8;
+const CHECKER_SQUARES: u32 = 8;
 
 /**
   Define the state a texture can have.
   - Raw means that the texture is not written to a buffer yet
   - Initialized means that the texture is already loaded into a buffer an can be used in the rendering process
+  - Missing means that the texture is used but was never registered or could not be decoded, the missing texture is drawn instead
   */
 enum TextureState<B: Backend> {
     Raw(TextureRawData),
     Initialized(TextureBufferData<B>),
+    Missing,
 }
 
 /**
-  The texture buffer object (TBO) reference contains the index of the initialized image buffer.
+  A texture and the settings it is sampled with. The settings stay the same when its image is replaced.
   */
-#[derive(Copy, Clone, Hash)]
+struct TextureEntry<B: Backend> {
+    state: TextureState<B>,
+    sampler: SamplerSettings,
+}
+
+/**
+  The texture buffer object (TBO) reference contains the index of the texture.
+  It stays valid when the image of the texture is replaced, so all materials using it show the new image.
+  */
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct TBORef(usize);
 
 /**
-  Contains the raw texture data as byte vector and the name of the texture.
+  Contains the raw texture data as byte vector and its format.
   */
-#[derive(Clone)]
 struct TextureRawData {
-    name: String,
     data: Vec<u8>,
     format: TextureFormat,
 }
 
 /**
-  Contains the image buffer of the texture, as well as its name and the reference to the pool this texture is allocated in.
+  Contains the image buffer of the texture and the index of the pool its descriptor set is allocated from.
   */
 struct TextureBufferData<B: Backend> {
-    name: String,
     buffer_pool_index: usize,
     image: ImageBuffer<B>,
 }
 
 /**
   Contains the pool descriptor and the pool capacity. The current fill field indicates
-  how many textures have been registered to this pool so far.
+  how many descriptor sets of the pool are in use.
   */
 struct TextureBufferPool<B: Backend> {
     image_descriptor: B::DescriptorPool,
@@ -58,12 +80,19 @@ struct TextureBufferPool<B: Backend> {
     current_fill: u32,
 }
 
+/**
+  The textures of the materials. Named textures are uploaded once, when the first material uses them,
+  and shared by all materials which reference them. Replacing the image of a texture changes it for all of them.
+  */
 pub struct TextureLibrary<B: Backend> {
     device_ptr: Rc<RefCell<CoreDevice<B>>>,
     memory_types: Vec<MemoryType>,
     adapter_limits: Limits,
     buffer_pools: Vec<TextureBufferPool<B>>,
-    entries: Vec<TextureState<B>>,
+    entries: Vec<TextureEntry<B>>,
+    names: HashMap<String, usize>,
+    /// Replaced images, the frame in flight might still read them
+    retired: Vec<TextureBufferData<B>>,
 }
 
 impl<B: Backend> TextureLibrary<B> {
@@ -74,63 +103,179 @@ impl<B: Backend> TextureLibrary<B> {
             adapter_limits,
             buffer_pools: vec![],
             entries: vec![],
+            names: HashMap::new(),
+            retired: vec![],
         };
 
+        let default_sampler = SamplerSettings { mipmaps: false, ..SamplerSettings::default() };
+        let default_image = TextureImage::Rgba8(RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255])), ColorSpace::Srgb);
+        let default_texture = instance.create_texture_buffer(&default_image, &default_sampler);
+        instance.entries.push(TextureEntry { state: TextureState::Initialized(default_texture), sampler: default_sampler });
+        // The squares stay sharp up close and blend to dark magenta in the distance
+        let missing_sampler = SamplerSettings { mag_filter: TextureFilter::Nearest, wrap: TextureWrap::Repeat, ..SamplerSettings::default() };
+        let missing_image = TextureImage::Rgba8(create_missing_texture_image(), ColorSpace::Srgb);
+        let missing_texture = instance.create_texture_buffer(&missing_image, &missing_sampler);
+        instance.entries.push(TextureEntry { state: TextureState::Initialized(missing_texture), sampler: missing_sampler });
+
         instance
     }
 
-    pub fn add_texture(&mut self, name: &str, data: Vec<u8>, format: TextureFormat) {
-        let raw_texture = TextureRawData {
-            name: name.to_string(),
-            data,
-            format,
-        };
-        self.entries.push(TextureState::Raw(raw_texture))
+    /// Register a texture by name. It is uploaded when a material uses it the first time.
+    /// Adding a texture with the name of an existing one replaces its image and sampler settings.
+    pub fn add_texture(&mut self, name: &str, data: Vec<u8>, format: TextureFormat, sampler: SamplerSettings) {
+        match self.names.get(name) {
+            Some(index) => self.replace_texture(&TBORef(*index), data, format, sampler),
+            None => {
+                self.entries.push(TextureEntry { state: TextureState::Raw(TextureRawData { data, format }), sampler });
+                self.names.insert(name.to_string(), self.entries.len() - 1);
+            }
+        }
     }
 
-    pub fn get_texture_buffer(&self, ref_obj: &TBORef) -> &ImageBuffer<B> {
-        let texture_state = &self.entries[ref_obj.0];
-        let buffer_data = match texture_state {
-            TextureState::Raw(_) => { panic!("This should not be possible, given the fact that a TBORef exists of an uninitialized texture!") }
-            TextureState::Initialized(buffer_data) => buffer_data,
-        };
-        &buffer_data.image
+    /// Upload a texture without name, e.g. the image embedded in a material.
+    pub fn add_unnamed_texture(&mut self, data: Vec<u8>, format: TextureFormat, sampler: SamplerSettings) -> TBORef {
+        let tbo_ref = TBORef(self.entries.len());
+        self.entries.push(TextureEntry { state: TextureState::Missing, sampler });
+        self.upload(tbo_ref.0, data, format);
+        tbo_ref
     }
 
-    pub fn get_texture_from_name(&mut self, name: &str) -> Option<TBORef> {
+    /// Reference a texture which is not registered yet. The missing texture is drawn until it is added.
+    pub fn reserve_texture(&mut self, name: &str) -> TBORef {
+        if let Some(tbo_ref) = self.get_texture_from_name(name) {
+            return tbo_ref;
+        }
+        self.entries.push(TextureEntry { state: TextureState::Missing, sampler: SamplerSettings::default() });
+        self.names.insert(name.to_string(), self.entries.len() - 1);
+        TBORef(self.entries.len() - 1)
+    }
 
-        let (raw_texture, index) = self.get_raw_texture_data_from_name(name)?;
+    pub fn get_texture_buffer(&self, ref_obj: &TBORef) -> &ImageBuffer<B> {
+        match &self.entries[self.get_drawn_ref(ref_obj).0].state {
+            TextureState::Initialized(buffer_data) => &buffer_data.image,
+            TextureState::Raw(_) | TextureState::Missing => unreachable!("The missing texture is always initialized"),
+        }
+    }
 
-        let pool_index = self.find_or_create_new_buffer_pool();
-        let texture_buffer = self.create_buffer_from_raw_texture(&raw_texture, index)?;
-        self.entries[index] = TextureState::Initialized(texture_buffer);
-        let tbo_ref = TBORef(index);
-        Some(tbo_ref)
+    /// The texture which is drawn for the reference, the missing texture if it is not available.
+    pub fn get_drawn_ref(&self, ref_obj: &TBORef) -> TBORef {
+        match &self.entries[ref_obj.0].state {
+            TextureState::Initialized(_) => *ref_obj,
+            // Textures are uploaded before a reference to them is handed out, the others are missing
+            TextureState::Raw(_) | TextureState::Missing => TextureLibrary::<B>::get_missing_ref(),
+        }
     }
 
+    /// The texture with the name, uploaded on the first request. None if no texture with the name was added or reserved.
+    pub fn get_texture_from_name(&mut self, name: &str) -> Option<TBORef> {
+        let index = *self.names.get(name)?;
+        if let TextureState::Raw(_) = &self.entries[index].state {
+            if let TextureState::Raw(raw_texture) = mem::replace(&mut self.entries[index].state, TextureState::Missing) {
+                self.upload(index, raw_texture.data, raw_texture.format);
+            }
+        }
+        Some(TBORef(index))
+    }
+
+    /// Replace the image of the texture with the name, or add it with the default sampler settings if there is none.
     pub fn update_texture_with_name(&mut self, name: &str, new_data: Vec<u8>, format: TextureFormat) {
-        todo!()
+        match self.names.get(name) {
+            Some(index) => self.update_texture_with_tbo(&TBORef(*index), new_data, format),
+            None => self.add_texture(name, new_data, format, SamplerSettings::default()),
+        }
     }
 
+    /// Replace the image of the texture, it keeps its sampler settings.
     pub fn update_texture_with_tbo(&mut self, tbo_ref: &TBORef, new_data: Vec<u8>, format: TextureFormat) {
-        todo!()
+        let sampler = self.entries[tbo_ref.0].sampler;
+        self.replace_texture(tbo_ref, new_data, format, sampler);
+    }
+
+    /// Replace the image and the sampler settings of the texture. Textures which are not used yet stay on the cpu until they are.
+    pub fn replace_texture(&mut self, tbo_ref: &TBORef, new_data: Vec<u8>, format: TextureFormat, sampler: SamplerSettings) {
+        if tbo_ref.0 == DEFAULT_TEXTURE || tbo_ref.0 == MISSING_TEXTURE {
+            warn!("The default and the missing texture can not be replaced");
+            return;
+        }
+        let entry = &mut self.entries[tbo_ref.0];
+        entry.sampler = sampler;
+        match &mut entry.state {
+            TextureState::Raw(raw_texture) => *raw_texture = TextureRawData { data: new_data, format },
+            _ => self.upload(tbo_ref.0, new_data, format),
+        }
+    }
+
+    /// Free the image of a texture which is not used anymore, e.g. the own texture of a material which now uses another one.
+    /// The reference is drawn as missing texture afterwards.
+    pub fn retire_texture(&mut self, tbo_ref: &TBORef) {
+        if tbo_ref.0 == DEFAULT_TEXTURE || tbo_ref.0 == MISSING_TEXTURE {
+            return;
+        }
+        if let TextureState::Initialized(texture) = mem::replace(&mut self.entries[tbo_ref.0].state, TextureState::Missing) {
+            self.retired.push(texture);
+        }
     }
 
-    fn get_raw_texture_data_from_name(&self, name: &str) -> Option<(TextureRawData, usize)>{
-        for (index, entry) in self.entries.iter().enumerate() {
-            if let TextureState::Raw(raw_data) = entry{
-                if raw_data.name == name.to_string(){
-                    return Some((raw_data.clone(), index));
-                }
+    pub(crate) fn get_default_ref() -> TBORef {
+        TBORef(DEFAULT_TEXTURE)
+    }
+
+    pub(crate) fn get_missing_ref() -> TBORef {
+        TBORef(MISSING_TEXTURE)
+    }
+
+    /// Whether images were replaced since the unused images were freed the last time.
+    pub(crate) fn has_unused_textures(&self) -> bool {
+        !self.retired.is_empty()
+    }
+
+    /// Free the replaced images. The gpu must not use them anymore.
+    pub(crate) fn free_unused_textures(&mut self) {
+        for mut texture in self.retired.drain(..) {
+            let pool = &mut self.buffer_pools[texture.buffer_pool_index];
+            if let Some(set) = texture.image.desc.set.take() {
+                unsafe { pool.image_descriptor.free(iter::once(set)); }
             }
+            pool.current_fill -= 1;
         }
-        None
     }
 
-    fn create_buffer_from_raw_texture(&mut self , raw_data: &TextureRawData, entry_index: usize) -> Option<TextureBufferData<B>>{
+    /// Decode and upload the image. It is drawn as missing texture if it can not be decoded or the gpu does not support its format.
+    fn upload(&mut self, index: usize, data: Vec<u8>, format: TextureFormat) {
+        let sampler = self.entries[index].sampler;
+        let texture_image = TextureImage::decode(data, &format, sampler.color_space).and_then(|texture_image| {
+            let device = self.device_ptr.borrow();
+            // Block compressed formats can only be used if the feature was enabled when the device was opened
+            if matches!(texture_image, TextureImage::Compressed(..)) && !device.features.contains(Features::FORMAT_BC) {
+                return Err("the gpu does not support block compressed textures".to_string());
+            }
+            let image_format = texture_image.get_format();
+            let features = device.physical_device.format_properties(Some(image_format)).optimal_tiling;
+            if features.contains(ImageFeature::SAMPLED) {
+                Ok(texture_image)
+            } else {
+                Err(format!("the gpu can not sample {:?} textures", image_format))
+            }
+        });
+        let new_state = match texture_image {
+            Ok(texture_image) => TextureState::Initialized(self.create_texture_buffer(&texture_image, &sampler)),
+            Err(reason) => {
+                warn!("Texture {} is drawn as missing texture: {}", self.get_name(index), reason);
+                TextureState::Missing
+            }
+        };
+        if let TextureState::Initialized(old_texture) = mem::replace(&mut self.entries[index].state, new_state) {
+            self.retired.push(old_texture);
+        }
+    }
+
+    fn get_name(&self, index: usize) -> &str {
+        self.names.iter().find(|(_, entry)| **entry == index).map_or("without name", |(name, _)| name.as_str())
+    }
 
-        let image_desc = self.create_descriptor(entry_index);
-        let rgba_image = self.build_rgba_image(raw_data.data.clone(), raw_data.format.clone());
+    fn create_texture_buffer(&mut self, texture_image: &TextureImage, sampler: &SamplerSettings) -> TextureBufferData<B> {
+        let pool_index = self.find_or_create_new_buffer_pool();
+        let image_desc = self.create_descriptor(pool_index);
 
         let mut staging_pool = unsafe {
             self.device_ptr.borrow().device.create_command_pool(
@@ -141,7 +286,8 @@ impl<B: Backend> TextureLibrary<B> {
 
         let image_buffer = ImageBuffer::new(
             image_desc,
-            &rgba_image,
+            texture_image,
+            sampler,
             &self.adapter_limits,
             &self.memory_types,
             Usage::TRANSFER_SRC,
@@ -150,64 +296,49 @@ impl<B: Backend> TextureLibrary<B> {
             );
 
         image_buffer.wait_for_transfer_completion();
+        unsafe { self.device_ptr.borrow().device.destroy_command_pool(staging_pool); }
 
-
-        let texture_buffer_data = TextureBufferData{
-            name: raw_data.name.to_string(),
-            buffer_pool_index: entry_index,
+        TextureBufferData {
+            buffer_pool_index: pool_index,
             image: image_buffer,
-        };
-
-       Some(texture_buffer_data)
-    }
-
-
-    fn count_all_raw_texture_references(&mut self) -> usize{
-        self.entries.iter().filter(|texture_state| match texture_state { TextureState::Raw(_) => true, _ => false}).count()
+        }
     }
 
-    fn find_or_create_new_buffer_pool(&mut self) -> usize{
-
-        let  result = self.buffer_pools.iter().enumerate().find(|(idx, pool)| pool.current_fill < pool.capacity);
-        if result.is_some() {
-            return result.unwrap().0;
+    fn find_or_create_new_buffer_pool(&mut self) -> usize {
+        if let Some(pool_index) = self.buffer_pools.iter().position(|pool| pool.current_fill < pool.capacity) {
+            return pool_index;
         }
 
-        let capacity = self.count_all_raw_texture_references();
-
+        // The sets of replaced images are freed, so their place can be reused
         let image_desc_pool = unsafe {
             self.device_ptr.borrow().device.create_descriptor_pool(
-                capacity,
+                POOL_CAPACITY,
                 vec![DescriptorRangeDesc {
                     ty: DescriptorType::Image {
                         ty: ImageDescriptorType::Sampled {
                             with_sampler: false,
                         },
                     },
-                    count: capacity,
+                    count: POOL_CAPACITY,
                 },
                 DescriptorRangeDesc {
                     ty: DescriptorType::Sampler,
-                    count: capacity,
+                    count: POOL_CAPACITY,
                 },
                 ].into_iter(),
-                DescriptorPoolCreateFlags::empty(),
+                DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET,
                 )
-        }.ok();
+        }.expect("Cannot create texture descriptor pool");
 
-        let buffer_pool = TextureBufferPool{
-            image_descriptor: image_desc_pool.unwrap(),
-            capacity: capacity as u32,
+        self.buffer_pools.push(TextureBufferPool {
+            image_descriptor: image_desc_pool,
+            capacity: POOL_CAPACITY as u32,
             current_fill: 0,
-        };
-
-
-        let pool_index = self.buffer_pools.len();
-        self.buffer_pools.push(buffer_pool);
-        pool_index
+        });
+        self.buffer_pools.len() - 1
     }
 
-    fn create_descriptor(&mut self, entry_index: usize) -> DescSet<B> {
+    fn create_descriptor(&mut self, pool_index: usize) -> DescSet<B> {
         let image_desc = DescSetLayout::new(
             Rc::clone(&self.device_ptr),
             vec![
@@ -231,18 +362,24 @@ impl<B: Backend> TextureLibrary<B> {
             },
             ],
             );
-            let image_desc = image_desc.create_desc_set(
-                &mut self.buffer_pools[entry_index].image_descriptor,
-                "image",
-                Rc::clone(&self.device_ptr),
-                );
-            image_desc
-    }
-
-    fn build_rgba_image(&self, image_data: Vec<u8>, format: TextureFormat) -> image::ImageBuffer<Rgba<u8>, Vec<u8>> {
-        return match format {
-            TextureFormat::Custom((width, height)) => RgbaImage::from_raw(width, height, image_data).unwrap(),
-            TextureFormat::Png => image::load(Cursor::new(&image_data[..]), image::ImageFormat::Png).unwrap().to_rgba8(),
-        }
+        let pool = &mut self.buffer_pools[pool_index];
+        pool.current_fill += 1;
+        image_desc.create_desc_set(
+            &mut pool.image_descriptor,
+            "image",
+            Rc::clone(&self.device_ptr),
+            )
     }
 }
+
+/// Magenta and black checker which stands out, drawn instead of textures which are missing.
+fn create_missing_texture_image() -> RgbaImage {
+    let size = CHECKER_SQUARE_SIZE * CHECKER_SQUARES;
+    RgbaImage::from_fn(size, size, |x, y| {
+        if (x / CHECKER_SQUARE_SIZE + y / CHECKER_SQUARE_SIZE) % 2 == 0 {
+            Rgba([255, 0, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    })
+}
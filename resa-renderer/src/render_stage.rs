@@ -1,5 +1,6 @@
 use std::fmt;
 use std::fmt::Formatter;
+use crate::error::RenderError;
 
 #[derive(Copy, Clone, Hash, Debug, Eq, PartialEq)]
 pub enum RenderStage {
@@ -21,13 +22,13 @@ impl fmt::Display for RenderStage {
 }
 
 impl RenderStage {
-	pub fn get_stage_form_index(index: usize) -> Self {
+	pub fn get_stage_form_index(index: usize) -> Result<Self, RenderError> {
 		match index {
-			0 => RenderStage::None,
-			1 => RenderStage::Opaque,
-			2 => RenderStage::Transparent,
-			3 => RenderStage::UI,
-			_ => panic!("Invalid index!"),
+			0 => Ok(RenderStage::None),
+			1 => Ok(RenderStage::Opaque),
+			2 => Ok(RenderStage::Transparent),
+			3 => Ok(RenderStage::UI),
+			_ => Err(RenderError::InvalidRenderStageIndex(index)),
 		}
 	}
 	pub fn get_stages_in_order_of_priority() -> Vec<Self> {
@@ -0,0 +1,86 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use gfx_hal::Backend;
+use gfx_hal::adapter::MemoryType;
+use gfx_hal::format::{Aspects, Format};
+use gfx_hal::image::{Extent, Tiling, Usage};
+use gfx_hal::memory::Properties;
+use gfx_hal::pso::{Rect, Viewport};
+use gfx_hal::window::Extent2D;
+use crate::core::CoreDevice;
+use crate::image_buffer::Image;
+use crate::swapchain::Swapchain;
+
+/// The image the renderer draws into. Either the swapchain of the window,
+/// or an offscreen color image which can be used without a window, e.g. for tests.
+pub(crate) enum RenderTarget<B: Backend> {
+	Swapchain(Swapchain),
+	Offscreen(OffscreenTarget<B>),
+}
+
+pub(crate) struct OffscreenTarget<B: Backend> {
+	pub(crate) color_image: Image<B>,
+	pub(crate) extent: Extent,
+}
+
+impl<B: Backend> OffscreenTarget<B> {
+	pub(crate) const FORMAT: Format = Format::Rgba8Srgb;
+
+	pub(crate) fn new(device: Rc<RefCell<CoreDevice<B>>>, memory_types: &[MemoryType], dimensions: Extent2D) -> Self {
+		let extent = dimensions.to_extent();
+		let color_image = Image::new(device, memory_types, extent, Self::FORMAT, Tiling::Optimal, Self::get_usage(), Properties::DEVICE_LOCAL, Aspects::COLOR, Usage::COLOR_ATTACHMENT);
+
+		OffscreenTarget {
+			color_image,
+			extent,
+		}
+	}
+
+	pub(crate) fn get_usage() -> Usage {
+		Usage::COLOR_ATTACHMENT | Usage::TRANSFER_SRC
+	}
+}
+
+impl<B: Backend> RenderTarget<B> {
+	pub(crate) fn get_extent(&self) -> Extent {
+		match self {
+			RenderTarget::Swapchain(swapchain) => swapchain.extent,
+			RenderTarget::Offscreen(offscreen) => offscreen.extent,
+		}
+	}
+
+	pub(crate) fn get_format(&self) -> Format {
+		match self {
+			RenderTarget::Swapchain(swapchain) => swapchain.format,
+			RenderTarget::Offscreen(_) => OffscreenTarget::<B>::FORMAT,
+		}
+	}
+
+	/// The usage of the color images, the framebuffer attachments have to be created with the same usage.
+	pub(crate) fn get_color_usage(&self) -> Usage {
+		match self {
+			RenderTarget::Swapchain(swapchain) => swapchain.framebuffer_attachment.usage,
+			RenderTarget::Offscreen(_) => OffscreenTarget::<B>::get_usage(),
+		}
+	}
+
+	pub(crate) fn get_frame_queue_size(&self) -> u32 {
+		match self {
+			RenderTarget::Swapchain(swapchain) => swapchain.frame_queue_size,
+			RenderTarget::Offscreen(_) => 1,
+		}
+	}
+
+	pub(crate) fn make_viewport(&self) -> Viewport {
+		let extent = self.get_extent();
+		Viewport {
+			rect: Rect {
+				x: 0,
+				y: 0,
+				h: extent.height as i16,
+				w: extent.width as i16,
+			},
+			depth: 0.0..1.0,
+		}
+	}
+}
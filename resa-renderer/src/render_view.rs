@@ -0,0 +1,60 @@
+use gfx_hal::pso::{Rect, Viewport};
+use crate::material::MaterialRef;
+
+/// Part of the render target a view is drawn to, in coordinates relative to the target size from 0 to 1.
+/// The origin is the top left corner.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ViewportRect {
+	pub x: f32,
+	pub y: f32,
+	pub width: f32,
+	pub height: f32,
+}
+
+impl ViewportRect {
+	pub const FULL: ViewportRect = ViewportRect { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+	pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+		ViewportRect { x, y, width, height }
+	}
+
+	/// Ratio of width to height of the rect on a target with the given size.
+	pub fn get_aspect_ratio(&self, target_width: u32, target_height: u32) -> f32 {
+		let width = self.width * target_width as f32;
+		let height = self.height * target_height as f32;
+		if height > 0.0 { width / height } else { 1.0 }
+	}
+
+	/// The rect in pixels on the target, clamped to it. None if nothing of it is on the target.
+	pub(crate) fn to_viewport(&self, target: &Rect) -> Option<Viewport> {
+		let to_pixels = |relative: f32, offset: i16, size: i16| offset + (relative.clamp(0.0, 1.0) * size as f32).round() as i16;
+		let x0 = to_pixels(self.x, target.x, target.w);
+		let y0 = to_pixels(self.y, target.y, target.h);
+		let x1 = to_pixels(self.x + self.width, target.x, target.w);
+		let y1 = to_pixels(self.y + self.height, target.y, target.h);
+		if x1 <= x0 || y1 <= y0 {
+			return None;
+		}
+
+		Some(Viewport {
+			rect: Rect { x: x0, y: y0, w: x1 - x0, h: y1 - y0 },
+			depth: 0.0..1.0,
+		})
+	}
+}
+
+impl Default for ViewportRect {
+	fn default() -> Self {
+		ViewportRect::FULL
+	}
+}
+
+/// Everything one camera sees. Views are drawn in the given order, each one on top of the previous ones.
+pub struct RenderView {
+	pub view_mat: [[f32; 4]; 4],
+	pub projection_mat: [[f32; 4]; 4],
+	pub viewport: ViewportRect,
+	/// Color the viewport is cleared with before drawing. Without one the view is drawn over the previous views.
+	pub clear_color: Option<[f32; 4]>,
+	pub render_objects: Vec<(u64, MaterialRef, [[f32; 4]; 4])>,
+}
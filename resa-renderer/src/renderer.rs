@@ -1,44 +1,57 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::{iter};
 use std::mem::size_of;
 use std::rc::Rc;
 use std::time::Instant;
 
-use gfx_hal::{Backend, IndexType, Limits};
+use gfx_hal::{Backend, Features, Limits};
 use gfx_hal::adapter::MemoryType;
 use gfx_hal::buffer::SubRange;
-use gfx_hal::command::{ClearColor, ClearDepthStencil, ClearValue, CommandBuffer, CommandBufferFlags, Level, RenderAttachmentInfo, SubpassContents};
+use gfx_hal::command::{AttachmentClear, ClearColor, ClearDepthStencil, ClearValue, CommandBuffer, CommandBufferFlags, Level, RenderAttachmentInfo, SubpassContents};
 use gfx_hal::device::Device;
 use gfx_hal::format::{Aspects, Format, ImageFeature};
-use gfx_hal::image::{Extent, Tiling};
+use gfx_hal::image::{Extent, Tiling, Usage};
 use gfx_hal::memory::{Properties};
 use gfx_hal::pool::{CommandPool, CommandPoolCreateFlags};
 use gfx_hal::prelude::PresentationSurface;
-use gfx_hal::pso::{BlendState, ColorMask, ColorValue, Comparison, ShaderStageFlags, Viewport};
+use gfx_hal::pso::{BlendState, ClearRect, ColorMask, ColorValue, Comparison, DepthBias, Face, Rasterizer, ShaderStageFlags, State, Viewport};
 use gfx_hal::queue::Queue;
 use gfx_hal::window::Extent2D;
 use winit::window::Window;
+use log::{error, warn};
 
 use crate::core::{Core, CoreAdapter, CoreDevice};
-use crate::helper::MVP;
+use crate::helper::ViewProjection;
 use crate::image_buffer::{Image};
-use crate::material::{Material, MaterialRef};
+use crate::instancing::InstanceBuffer;
+use crate::draw_list::DrawList;
+use crate::material::{BlendMode, CullMode, PipelineState, PolygonMode};
 use crate::render_passes::{RenderPassController, RenderPassDescriptor};
-use crate::pipelines::{PipelineController};
+use crate::pipelines::{PipelineController, PipelineKey};
 use crate::pipelines::graphics_pipeline::GraphicsPipeline;
 use crate::pipelines::pipeline_builder::{PipelineBuilder, PipelineLayoutDesc};
 use crate::swapchain::Swapchain;
+use crate::render_target::{OffscreenTarget, RenderTarget};
+use crate::readback::{FramePixels, PendingReadback};
 use crate::render_resources::RenderResources;
 use crate::render_stage::RenderStage;
+use crate::render_view::RenderView;
+use crate::PresentMode;
+use crate::profiling::RenderTiming;
+use crate::error::RenderError;
 
 pub struct Renderer<B: Backend> {
 	core: Core<B>,
 	device: Rc<RefCell<CoreDevice<B>>>,
-	swapchain: Swapchain,
+	target: RenderTarget<B>,
+	extent: Extent2D,
+	present_mode: PresentMode,
 	pipeline_controller: PipelineController<B>,
 	render_pass_controller: RenderPassController<B>,
 	viewport: Viewport,
 	depth_image: Image<B>,
+	instance_buffer: InstanceBuffer<B>,
 	draw_fence: B::Fence,
 	command_pool: B::CommandPool,
 	command_buffers: Vec<B::CommandBuffer>,
@@ -46,50 +59,63 @@ pub struct Renderer<B: Backend> {
 	bg_color: ColorValue,
 	frames_drawn: usize,
 	start_time: Instant,
-	pub recreate_swapchain: bool,
+	frame_timings: Vec<RenderTiming>,
+	readback_requested: bool,
+	pending_readback: Option<PendingReadback<B>>,
+	/// Meshes and shaders which were reported because the mesh does not provide the attributes the shader reads.
+	reported_layout_mismatches: HashSet<(u64, u32)>,
+	pub recreate_render_target: bool,
 }
 
 impl<B: Backend> Renderer<B> {
-	pub(crate) fn new(window: &Window, extent: Extent2D) -> Self {
+	/// Create a renderer which draws to the window, or to an offscreen image if no window is given.
+	pub(crate) fn new(window: Option<&Window>, extent: Extent2D, present_mode: PresentMode) -> Result<Self, RenderError> {
 		// Create the connection between code and gpu.
-		let mut core = Core::<B>::create(&window).unwrap();
-		let device = Rc::new(RefCell::new(CoreDevice::<B>::new(core.adapter.adapter.take().unwrap(), &core.surface)));
+		let mut core = Core::<B>::create(window)?;
+		let device = Rc::new(RefCell::new(CoreDevice::<B>::new(core.adapter.adapter.take().unwrap(), core.surface.as_ref())));
 
-		// Create swapchain and render pass and pipelines
-		let swapchain = Swapchain::new(&mut *core.surface, &*device.borrow(), extent);
-		let depth_image = Renderer::<B>::create_depth_image(device.clone(), &core.adapter, swapchain.extent);
+		// Create render target and render pass and pipelines
+		let target = Renderer::create_render_target(&mut core, &device, extent, present_mode);
+		let depth_image = Renderer::<B>::create_depth_image(device.clone(), &core.adapter, target.get_extent());
 
-		let render_pass_controller = RenderPassController::new(device.clone(), &swapchain.extent, swapchain.frame_queue_size);
+		let render_pass_controller = RenderPassController::new(device.clone(), &target.get_extent(), target.get_frame_queue_size(), target.get_color_usage());
 		let mut command_pool = unsafe { device.borrow().device.create_command_pool(device.borrow().queues.family, CommandPoolCreateFlags::empty()) }.unwrap();
-		let swapchain_images = swapchain.frame_queue_size as usize;
+		let target_images = target.get_frame_queue_size() as usize;
 		let mut command_buffers = vec![];
 		let mut present_semaphores = vec![];
-		for _ in 0..swapchain_images {
+		for _ in 0..target_images {
 			command_buffers.push(unsafe { command_pool.allocate_one(Level::Primary) });
 			present_semaphores.push(device.borrow().device.create_semaphore().unwrap());
         }
 
 		let draw_fence =  device.borrow().device.create_fence(true).unwrap();
 
-		let viewport = swapchain.make_viewport();
+		let viewport = target.make_viewport();
 
-		Renderer {
+		Ok(Renderer {
 			core,
 			device,
-			swapchain,
+			target,
+			extent,
+			present_mode,
 			pipeline_controller: PipelineController::new(),
 			render_pass_controller,
 			viewport,
 			depth_image,
+			instance_buffer: InstanceBuffer::new(),
 			command_pool,
 			command_buffers,
 			present_complete_semaphores: present_semaphores,
 			draw_fence,
-			recreate_swapchain: true,
+			recreate_render_target: true,
 			bg_color: [0.1, 0.1, 0.1, 1.0],
 			frames_drawn: 0,
 			start_time: Instant::now(),
-		}
+			frame_timings: vec![],
+			readback_requested: false,
+			pending_readback: None,
+			reported_layout_mismatches: HashSet::new(),
+		})
 	}
 
 	pub fn get_device(&self) -> Rc<RefCell<CoreDevice<B>>> {
@@ -104,26 +130,56 @@ impl<B: Backend> Renderer<B> {
 		self.core.adapter.limits.clone()
 	}
 
-	pub fn get_material_render_stage_index(&mut self, material: &Material, resources: &RenderResources<B>) -> u16 {
-		let pipeline_stage_index = self.pipeline_controller.get_render_index_from_pipeline(&material.render_stage);
-		if pipeline_stage_index.is_some() {
-			return pipeline_stage_index.unwrap();
+	/// Create the pipeline of the key and the render pass of its stage, if they do not exist yet.
+	/// Returns false if the pipeline can not be created, the error is only logged the first time.
+	pub fn prepare_pipeline(&mut self, key: &PipelineKey, resources: &RenderResources<B>) -> bool {
+		if self.pipeline_controller.contains(key) {
+			return true;
+		}
+		if self.pipeline_controller.has_failed(key) {
+			return false;
 		}
 
-		let render_pass = self.create_render_pass(&material.render_stage);
-		let pipeline = self.create_graphics_pipeline(material, render_pass, resources);
-		self.pipeline_controller.add_pipeline(pipeline)
+		let render_pass = self.create_render_pass(&key.stage);
+		match self.create_graphics_pipeline(key, render_pass, resources) {
+			Ok(pipeline) => {
+				self.pipeline_controller.add_pipeline(*key, pipeline);
+				true
+			}
+			Err(e) => {
+				if self.pipeline_controller.mark_failed(*key) {
+					error!("Could not create the pipeline for {:?}: {}", key, e);
+				}
+				false
+			}
+		}
 	}
 
-	pub fn recreate_swapchain(&mut self, dimensions: Extent2D) {
-		let device = &self.device.borrow().device;
-		device.wait_idle().unwrap();
+	pub fn set_extent(&mut self, extent: Extent2D) {
+		self.extent = extent;
+		self.recreate_render_target = true;
+	}
+
+	pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+		self.present_mode = present_mode;
+		self.recreate_render_target = true;
+	}
+
+	pub fn recreate_render_target(&mut self, dimensions: Extent2D) {
+		self.device.borrow().device.wait_idle().unwrap();
+
+		self.target = Renderer::create_render_target(&mut self.core, &self.device, dimensions, self.present_mode);
+		self.depth_image = Renderer::<B>::create_depth_image(self.device.clone(), &self.core.adapter, self.target.get_extent());
 
-		self.swapchain = Swapchain::new(&mut *self.core.surface, &*self.device.borrow(), dimensions);
-		self.depth_image = Renderer::<B>::create_depth_image(self.device.clone(), &self.core.adapter, self.swapchain.extent);
+		self.render_pass_controller.recreate_framebuffers(&self.target.get_extent(), self.target.get_color_usage());
+		self.viewport = self.target.make_viewport();
+	}
 
-		// TODO: Implement framebuffer update here!
-		self.viewport = self.swapchain.make_viewport();
+	fn create_render_target(core: &mut Core<B>, device: &Rc<RefCell<CoreDevice<B>>>, dimensions: Extent2D, present_mode: PresentMode) -> RenderTarget<B> {
+		match core.surface.as_mut() {
+			Some(surface) => RenderTarget::Swapchain(Swapchain::new(surface, &*device.borrow(), dimensions, present_mode)),
+			None => RenderTarget::Offscreen(OffscreenTarget::new(device.clone(), &core.adapter.memory_types, dimensions)),
+		}
 	}
 
 	fn create_depth_image(device: Rc<RefCell<CoreDevice<B>>>, adapter: &CoreAdapter<B>, dimensions: Extent) -> Image<B> {
@@ -136,44 +192,89 @@ impl<B: Backend> Renderer<B> {
 	fn create_render_pass(&mut self, render_stage: &RenderStage) -> usize {
 		let render_pass_desc = RenderPassDescriptor {
 			render_stage: render_stage.clone(),
-			image_format: self.swapchain.format,
+			image_format: self.target.get_format(),
 			depth_format: Some(self.depth_image.format),
 		};
 
 		self.render_pass_controller.create_new_render_pass_and_framebuffer(&render_pass_desc)
 	}
 
-	fn create_graphics_pipeline(&self, material: &Material, render_pass_id: usize, resources: &RenderResources<B>) -> GraphicsPipeline<B> {
+	fn create_graphics_pipeline(&self, key: &PipelineKey, render_pass_id: usize, resources: &RenderResources<B>) -> Result<GraphicsPipeline<B>, RenderError> {
 		let layout_desc = PipelineLayoutDesc::new(
 			resources.material_lib.get_descriptor_layouts(),
-			size_of::<MVP>() as u32,
+			size_of::<ViewProjection>() as u32,
 		);
 
-		let shader_ref = resources.shader_lib.get_by_id(&material.shader_id).unwrap();
+		let shader_ref = resources.shader_lib.get_by_id(&key.shader_id).ok_or(RenderError::ShaderNotFound(key.shader_id))?;
 
 		let render_pass = self.render_pass_controller.get_render_pass_ref(render_pass_id);
 
-		let pipeline = PipelineBuilder::new(self.device.clone(), layout_desc)
+		let state = &key.state;
+		let blend_state = match state.blend {
+			BlendMode::Opaque => BlendState::REPLACE,
+			BlendMode::Alpha => BlendState::ALPHA,
+			BlendMode::Additive => BlendState::ADD,
+			BlendMode::Premultiplied => BlendState::PREMULTIPLIED_ALPHA,
+			BlendMode::Multiply => BlendState::MULTIPLY,
+		};
+
+		let mut builder = PipelineBuilder::new(self.device.clone(), layout_desc);
+		builder
 			.add_render_pass(render_pass, render_pass_id)
-			.set_render_stage(material.render_stage.clone())
+			.set_render_stage(key.stage)
 			.add_vertex_shader(&shader_ref.vertex)
 			.add_fragment_shader(&shader_ref.fragment)
-			.add_color_blend_state(ColorMask::ALL, BlendState::ALPHA)
-			.add_depth_desc(Comparison::LessEqual, true)
-			.build();
+			.set_vertex_layout(&shader_ref.vertex_layout)
+			.set_topology(key.topology)
+			.add_color_blend_state(ColorMask::ALL, blend_state)
+			.add_rasterizer(self.create_rasterizer(state));
+		// Writing the depth without testing it is done with a test that always passes
+		if state.depth_test || state.depth_write {
+			let comparison = if state.depth_test { Comparison::LessEqual } else { Comparison::Always };
+			builder.add_depth_desc(comparison, state.depth_write);
+		}
 
-		match pipeline {
-			Some(pipeline) => pipeline,
-			None => panic!("Could not create pipeline for {}", &material.render_stage.to_string()),
+		builder.build().ok_or(RenderError::PipelineCreationFailed(key.stage))
+	}
+
+	fn create_rasterizer(&self, state: &PipelineState) -> Rasterizer {
+		let polygon_mode = match state.polygon {
+			PolygonMode::Fill => gfx_hal::pso::PolygonMode::Fill,
+			PolygonMode::Wireframe if self.device.borrow().features.contains(Features::NON_FILL_POLYGON_MODE) => gfx_hal::pso::PolygonMode::Line,
+			PolygonMode::Wireframe => {
+				warn!("Wireframe is not supported by the gpu, falling back to fill");
+				gfx_hal::pso::PolygonMode::Fill
+			}
+		};
+		let cull_face = match state.cull {
+			CullMode::None => Face::NONE,
+			CullMode::Front => Face::FRONT,
+			CullMode::Back => Face::BACK,
+		};
+		let depth_bias = state.depth_bias.map(|bias| State::Static(DepthBias {
+			const_factor: bias.constant_factor,
+			clamp: bias.clamp,
+			slope_factor: bias.slope_factor,
+		}));
+
+		Rasterizer {
+			polygon_mode,
+			cull_face,
+			depth_bias,
+			..Rasterizer::FILL
 		}
 	}
 
-	pub fn draw(&mut self, render_objects: &[(u64, MaterialRef, [[f32; 4]; 4])], view_mat: [[f32; 4]; 4], projection_mat: [[f32; 4]; 4], resource_binding: &RenderResources<B>) {
-		if self.recreate_swapchain {
-			self.recreate_swapchain(Extent2D { width: self.swapchain.extent.width, height: self.swapchain.extent.height });
-			self.recreate_swapchain = false;
+	/// Draw the views in the given order. The target is cleared with the background color first,
+	/// so parts which are not covered by any view show the background.
+	pub fn draw(&mut self, views: &[RenderView], resource_binding: &RenderResources<B>) {
+		if self.recreate_render_target {
+			self.recreate_render_target(self.extent);
+			self.recreate_render_target = false;
 		}
+		self.frame_timings.clear();
 
+		let acquire_start = Instant::now();
 		unsafe {
 			let logical_device = &self.device.borrow().device;
 			logical_device.wait_for_fence(&mut self.draw_fence, !0).unwrap();
@@ -181,18 +282,62 @@ impl<B: Backend> Renderer<B> {
 			self.command_pool.reset(false);
 		}
 
-		let surface_image = match unsafe { self.core.surface.acquire_image(!0) } {
-			Ok((image, _)) => image,
-			Err(_) => {
-				self.recreate_swapchain = true;
-				return;
+		// The draw fence was waited for, so the instance data of the previous frame is not in use anymore.
+		// Views which are completely outside of the target are skipped.
+		let mut instances = vec![];
+		let mut view_draw_lists = vec![];
+		for view in views.iter() {
+			if let Some(viewport) = view.viewport.to_viewport(&self.viewport.rect) {
+				let draw_list = DrawList::new(&view.render_objects, &view.view_mat, &resource_binding.material_lib, &resource_binding.mesh_lib, &mut instances);
+				view_draw_lists.push((view, viewport, draw_list));
+			}
+		}
+		self.instance_buffer.upload(self.device.clone(), &self.core.adapter.memory_types, &instances);
+		for (_, _, draw_list) in view_draw_lists.iter() {
+			for key in draw_list.get_pipeline_keys() {
+				self.prepare_pipeline(key, resource_binding);
 			}
+		}
+
+		// Offscreen targets always draw into the same image, only the swapchain has to hand out the next one.
+		let surface_image = match (&self.target, self.core.surface.as_mut()) {
+			(RenderTarget::Swapchain(_), Some(surface)) => match unsafe { surface.acquire_image(!0) } {
+				Ok((image, _)) => Some(image),
+				Err(_) => {
+					// The requested readback belongs to this frame, the next frame must not be captured instead
+					self.readback_requested = false;
+					self.pending_readback = None;
+					self.recreate_render_target = true;
+					return;
+				}
+			},
+			_ => None,
 		};
+		self.frame_timings.push(RenderTiming::since("Acquire", acquire_start));
 
-		let frame_index = (self.swapchain.frame_index % self.swapchain.frame_queue_size) as usize;
-		self.swapchain.frame_index += 1;
+		let frame_index = self.frames_drawn % self.target.get_frame_queue_size() as usize;
 		self.frames_drawn += 1;
 
+		self.pending_readback = None;
+		if self.readback_requested {
+			self.readback_requested = false;
+			if self.target.get_color_usage().contains(Usage::TRANSFER_SRC) {
+				let memory_types = &self.core.adapter.memory_types;
+				self.pending_readback = Some(PendingReadback::new(self.device.clone(), memory_types, self.target.get_extent(), self.target.get_format()));
+			} else {
+				warn!("The render target does not support copying its images, the frame can not be read back");
+			}
+		}
+
+		let (color_view, color_image): (&B::ImageView, &B::Image) = match (&surface_image, &self.target) {
+			(Some(surface_image), _) => (std::borrow::Borrow::borrow(surface_image), std::borrow::Borrow::borrow(surface_image)),
+			(None, RenderTarget::Offscreen(offscreen)) => (
+				offscreen.color_image.image_view.as_ref().unwrap(),
+				offscreen.color_image.image.as_ref().unwrap(),
+			),
+			(None, RenderTarget::Swapchain(_)) => unreachable!("A swapchain image is acquired for every frame"),
+		};
+
 		unsafe {
 			let cmd_buffer = &mut self.command_buffers[frame_index];
 			let present_semaphore = &self.present_complete_semaphores[frame_index];
@@ -201,95 +346,172 @@ impl<B: Backend> Renderer<B> {
 			cmd_buffer.set_viewports(0, iter::once(self.viewport.clone()));
 			cmd_buffer.set_scissors(0, iter::once(self.viewport.rect));
 
-			let render_stages = RenderStage::get_stages_in_order_of_priority();
-			for render_stage in render_stages.iter() {
-				let framebuffer_data = self.render_pass_controller.get_framebuffer_data(&render_stage);
-				if framebuffer_data.is_none() {
-					continue;
-				}
-				let (framebuffer, render_pass) = framebuffer_data.unwrap();
-				let attachments = vec![RenderAttachmentInfo {
-					image_view: std::borrow::Borrow::borrow(&surface_image),
-					clear_value: ClearValue {
-						color: ClearColor {
-							float32: self.bg_color,
-						},
+			// The clear values are only used by the clear pass, the passes of the stages load the attachments
+			let attachment_infos = || vec![RenderAttachmentInfo {
+				image_view: color_view,
+				clear_value: ClearValue {
+					color: ClearColor {
+						float32: self.bg_color,
 					},
 				},
-					RenderAttachmentInfo {
-						image_view: self.depth_image.image_view.as_ref().unwrap(),
-						clear_value: ClearValue {
-							depth_stencil: ClearDepthStencil { depth: 1.0, stencil: 0 }
-						},
+			},
+				RenderAttachmentInfo {
+					image_view: self.depth_image.image_view.as_ref().unwrap(),
+					clear_value: ClearValue {
+						depth_stencil: ClearDepthStencil { depth: 1.0, stencil: 0 }
 					},
-				];
+				},
+			];
+
+			if let Some((framebuffer, render_pass)) = self.render_pass_controller.get_clear_pass_data() {
 				cmd_buffer.begin_render_pass(
 					render_pass,
 					framebuffer,
 					self.viewport.rect,
-					attachments.into_iter(),
+					attachment_infos().into_iter(),
 					SubpassContents::Inline,
 				);
+				cmd_buffer.end_render_pass();
+			}
 
-				let pipelines = self.pipeline_controller.pipelines.get(&render_stage).unwrap();
-				let pipeline = pipelines[0].pipeline.as_ref().unwrap();
-				cmd_buffer.bind_graphics_pipeline(&pipeline);
-
-				for (mesh_id, material_id, transform) in render_objects.iter() {
-					let mesh_data = resource_binding.mesh_lib.get_mesh_entry(mesh_id);
-					let mesh_index_amount = resource_binding.mesh_lib.get_mesh_index_amount(mesh_id);
-					let (material_ubo, material_tbo, material_render_stage) = resource_binding.material_lib.get_render_data(material_id);
-
-					if material_render_stage != render_stage{
+			let render_stages = RenderStage::get_stages_in_order_of_priority();
+			for (view_index, (view, viewport, draw_list)) in view_draw_lists.iter().enumerate() {
+				cmd_buffer.set_viewports(0, iter::once(viewport.clone()));
+				cmd_buffer.set_scissors(0, iter::once(viewport.rect));
+				let view_projection = ViewProjection {
+					view: view.view_mat,
+					proj: view.projection_mat,
+				};
+
+				let mut viewport_cleared = false;
+				for render_stage in render_stages.iter() {
+					let framebuffer_data = self.render_pass_controller.get_framebuffer_data(&render_stage);
+					if framebuffer_data.is_none() {
 						continue;
 					}
+					let (framebuffer, render_pass) = framebuffer_data.unwrap();
+					let stage_start = Instant::now();
+					cmd_buffer.begin_render_pass(
+						render_pass,
+						framebuffer,
+						viewport.rect,
+						attachment_infos().into_iter(),
+						SubpassContents::Inline,
+					);
 
-					let mvp = MVP {
-						model: *transform,
-						view: view_mat,
-						proj: projection_mat,
-					};
-
-					let mvp_bytes = mvp.as_bytes();
-					let pipeline_layout = pipelines[0].layout.as_ref().unwrap();
-					cmd_buffer.bind_vertex_buffers(0, iter::once((mesh_data.vertex_buffer.get(), SubRange::WHOLE)));
-					cmd_buffer.bind_index_buffer(mesh_data.index_buffer.get(), SubRange::WHOLE, IndexType::U16);
-					cmd_buffer.push_graphics_constants(&pipeline_layout, ShaderStageFlags::VERTEX, 0, mvp_bytes);
+					// Every view starts with an empty depth buffer, the color is only cleared if the view has a clear color
+					if !viewport_cleared {
+						let mut clears = vec![AttachmentClear::DepthStencil { depth: Some(1.0), stencil: None }];
+						if let Some(clear_color) = view.clear_color {
+							clears.push(AttachmentClear::Color { index: 0, value: ClearColor { float32: clear_color } });
+						}
+						cmd_buffer.clear_attachments(clears.into_iter(), iter::once(ClearRect { rect: viewport.rect, layers: 0..1 }));
+						viewport_cleared = true;
+					}
 
-					let sets = vec![
-						material_tbo.desc.set.as_ref().unwrap(),
-						material_ubo.desc.as_ref().unwrap().set.as_ref().unwrap()];
+					let batches = draw_list.get_stage_batches(render_stage);
+					if let Some(instance_buffer) = self.instance_buffer.get() {
+						cmd_buffer.bind_vertex_buffers(0, iter::once((instance_buffer, SubRange::WHOLE)));
+					}
 
+					// The draw list is sorted by pipeline, material and mesh, so consecutive batches often share them
+					let mut bound_pipeline = None;
+					let mut bound_mesh = None;
+					let mut bound_material = None;
+					for batch in batches.iter() {
+						let pipeline = match self.pipeline_controller.get(&batch.pipeline) {
+							Some(pipeline) => pipeline,
+							None => continue,
+						};
+						let pipeline_layout = pipeline.layout.as_ref().unwrap();
+						if bound_pipeline != Some(batch.pipeline) {
+							cmd_buffer.bind_graphics_pipeline(pipeline.pipeline.as_ref().unwrap());
+							cmd_buffer.push_graphics_constants(pipeline_layout, ShaderStageFlags::VERTEX, 0, view_projection.as_bytes());
+							bound_pipeline = Some(batch.pipeline);
+							bound_material = None;
+							// The attribute streams depend on the vertex layout of the pipeline
+							bound_mesh = None;
+						}
+
+						let mesh_data = resource_binding.mesh_lib.get_mesh_entry(&batch.mesh_id);
+						if bound_mesh != Some(batch.mesh_id) {
+							let vertex_buffers = match mesh_data.get_vertex_buffers(&pipeline.vertex_layout) {
+								Some(vertex_buffers) => vertex_buffers,
+								None => {
+									if self.reported_layout_mismatches.insert((batch.mesh_id, batch.pipeline.shader_id)) {
+										error!("Mesh {} is not drawn, it lacks the attributes {:?} of shader {}",
+											batch.mesh_id, pipeline.vertex_layout.get_missing(mesh_data.get_layout()), batch.pipeline.shader_id);
+									}
+									continue;
+								}
+							};
+							cmd_buffer.bind_vertex_buffers(1, vertex_buffers.into_iter().map(|buffer| (buffer, SubRange::WHOLE)));
+							if let Some(index_buffer) = mesh_data.get_index_buffer() {
+								cmd_buffer.bind_index_buffer(index_buffer.buffer.get(), SubRange::WHOLE, index_buffer.index_type);
+							}
+							bound_mesh = Some(batch.mesh_id);
+						}
+
+						if bound_material != Some(batch.material) {
+							let (material_ubo, material_tbo, _) = resource_binding.material_lib.get_render_data(&batch.material);
+							let sets = vec![
+								material_tbo.desc.set.as_ref().unwrap(),
+								material_ubo.desc.as_ref().unwrap().set.as_ref().unwrap()];
+
+
+							cmd_buffer.bind_graphics_descriptor_sets(pipeline_layout,
+								0,
+								sets.into_iter(),
+								iter::empty(),
+							);
+							bound_material = Some(batch.material);
+						}
+
+						// The instance data is read from the instances first_instance to first_instance + instance_count
+						let instances = batch.first_instance..batch.first_instance + batch.instance_count;
+						match mesh_data.get_index_buffer() {
+							Some(_) => cmd_buffer.draw_indexed(0..mesh_data.get_draw_count(), 0, instances),
+							None => cmd_buffer.draw(0..mesh_data.get_draw_count(), instances),
+						}
+					}
 
-					cmd_buffer.bind_graphics_descriptor_sets(pipelines[0].layout.as_ref().unwrap(),
-						0,
-						sets.into_iter(),
-						iter::empty(),
-					);
 
-					cmd_buffer.draw_indexed(0..mesh_index_amount, 0, 0..1);
+					cmd_buffer.end_render_pass();
+					let label = if view_draw_lists.len() > 1 {
+						format!("{} (View {})", render_stage, view_index)
+					} else {
+						render_stage.to_string()
+					};
+					self.frame_timings.push(RenderTiming::since(&label, stage_start));
 				}
-
-
-				cmd_buffer.end_render_pass();
+			}
+			if let Some(readback) = &self.pending_readback {
+				readback.record_copy(cmd_buffer, color_image);
 			}
 			cmd_buffer.finish();
 
+			// Only a presented frame has to wait for the rendering to complete
+			let signal_semaphore = surface_image.as_ref().map(|_| present_semaphore);
+			let submit_start = Instant::now();
 			self.device.borrow_mut().queues.queues[0].submit(
 				iter::once(&self.command_buffers[frame_index]),
 				iter::empty(),
-				iter::once(present_semaphore),
+				signal_semaphore.into_iter(),
 				Some(&mut self.draw_fence),
 			);
+			self.frame_timings.push(RenderTiming::since("Submit", submit_start));
 
 			// present frame
-
-			if let Err(_) = self.device.borrow_mut().queues.queues[0].present(
-				&mut *self.core.surface,
-				surface_image,
-				Some(&mut self.present_complete_semaphores[frame_index]),
-			) {
-				self.recreate_swapchain = true;
+			if let (Some(surface_image), Some(surface)) = (surface_image, self.core.surface.as_mut()) {
+				let present_start = Instant::now();
+				if let Err(_) = self.device.borrow_mut().queues.queues[0].present(
+					surface,
+					surface_image,
+					Some(&mut self.present_complete_semaphores[frame_index]),
+				) {
+					self.recreate_render_target = true;
+				}
+				self.frame_timings.push(RenderTiming::since("Present", present_start));
 			}
 		}
 		// let pipelines = self.pipeline_controller.get_all_pipelines_sorted();
@@ -395,10 +617,76 @@ impl<B: Backend> Renderer<B> {
 		// }
 	}
 
+	/// Copy the next drawn frame to the cpu, so it can be read with read_pixels.
+	pub fn request_readback(&mut self) {
+		self.readback_requested = true;
+	}
+
+	/// Wait until the gpu finished the last submitted frame, so the resources it used can be freed.
+	pub(crate) fn wait_for_last_frame(&mut self) {
+		unsafe {
+			self.device.borrow().device.wait_for_fence(&mut self.draw_fence, !0).unwrap();
+		}
+	}
+
+	/// Read the pixels of the last drawn frame. Swapchain frames are only available if the readback
+	/// was requested before they were drawn, the offscreen image can be copied at any time.
+	pub fn read_pixels(&mut self) -> Result<FramePixels, RenderError> {
+		if self.frames_drawn == 0 {
+			return Err(RenderError::NoFrameToRead);
+		}
+		if self.pending_readback.is_none() {
+			self.copy_offscreen_image()?;
+		}
+
+		unsafe {
+			self.device.borrow().device.wait_for_fence(&mut self.draw_fence, !0).unwrap();
+		}
+		Ok(self.pending_readback.take().unwrap().read())
+	}
+
+	/// Copy the offscreen image into a new readback buffer, outside of the draw commands.
+	fn copy_offscreen_image(&mut self) -> Result<(), RenderError> {
+		let offscreen = match &self.target {
+			RenderTarget::Offscreen(offscreen) => offscreen,
+			RenderTarget::Swapchain(_) => return Err(RenderError::NoFrameToRead),
+		};
+
+		let readback = PendingReadback::new(self.device.clone(), &self.core.adapter.memory_types, offscreen.extent, OffscreenTarget::<B>::FORMAT);
+		unsafe {
+			{
+				let logical_device = &self.device.borrow().device;
+				logical_device.wait_for_fence(&mut self.draw_fence, !0).unwrap();
+				logical_device.reset_fence(&mut self.draw_fence).unwrap();
+			}
+
+			let mut cmd_buffer = self.command_pool.allocate_one(Level::Primary);
+			cmd_buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+			readback.record_copy(&mut cmd_buffer, offscreen.color_image.image.as_ref().unwrap());
+			cmd_buffer.finish();
+
+			self.device.borrow_mut().queues.queues[0].submit(
+				iter::once(&cmd_buffer),
+				iter::empty(),
+				iter::empty(),
+				Some(&mut self.draw_fence),
+			);
+			self.device.borrow().device.wait_for_fence(&mut self.draw_fence, !0).unwrap();
+			self.command_pool.free(iter::once(cmd_buffer));
+		}
+
+		self.pending_readback = Some(readback);
+		Ok(())
+	}
+
 	pub fn get_fps(&self) -> f32 {
 		let elapsed_time = self.start_time.elapsed();
 		let fps = self.frames_drawn as f32 / elapsed_time.as_secs_f32();
 		fps
 	}
+
+	pub fn get_frame_timings(&self) -> &[RenderTiming] {
+		&self.frame_timings
+	}
 }
 
@@ -1,47 +1,171 @@
+use std::collections::{HashMap, HashSet};
 use glsl_to_spirv::ShaderType;
+use crate::error::ShaderError;
+use crate::vertex::VertexLayout;
+
+/// Words of the spir-v header before the first instruction.
+const SPIRV_HEADER_WORDS: usize = 5;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const DECORATION_LOCATION: u32 = 30;
+const STORAGE_CLASS_INPUT: u32 = 1;
 
 #[derive(Clone)]
 pub struct ShaderRef{
 	pub name: String,
 	pub(crate) vertex: Vec<u32>,
 	pub(crate) fragment: Vec<u32>,
+	/// The vertex attributes the vertex shader reads, pipelines of the shader are built against it.
+	pub(crate) vertex_layout: VertexLayout,
 }
 
 impl ShaderRef {
-	pub fn new(name: String, shaders_to_compile: &[(String, String)]) -> Option<Self>{
+	pub fn new(name: String, shaders_to_compile: &[(String, String)]) -> Result<Self, ShaderError>{
 
 		let mut vertex: Option<Vec<u32>> = None;
 		let mut fragment: Option<Vec<u32>> = None;
 
 		for (shader_type, shader_to_compile) in shaders_to_compile {
 			match shader_type.as_str() {
-				"vert" => {
-					vertex = ShaderRef::compile_shader(shader_to_compile, ShaderType::Vertex)
-				},
-				"frag" => {
-					fragment = ShaderRef::compile_shader(shader_to_compile, ShaderType::Fragment);
-				}
+				"vert" => vertex = Some(ShaderRef::compile_shader(&name, shader_type, shader_to_compile, ShaderType::Vertex)?),
+				"frag" => fragment = Some(ShaderRef::compile_shader(&name, shader_type, shader_to_compile, ShaderType::Fragment)?),
 				_ => {}
 			}
 		}
 
+		let missing_stage = |stage: &str| ShaderError::MissingStage { shader: name.clone(), stage: stage.to_string() };
+		let vertex = vertex.ok_or_else(|| missing_stage("vert"))?;
+		let fragment = fragment.ok_or_else(|| missing_stage("frag"))?;
+		// The compiled module has the declarations without comments and with all qualifiers resolved
+		let vertex_layout = VertexLayout::from_shader_locations(&get_input_locations(&vertex))
+			.map_err(|locations| ShaderError::UnknownVertexLocations { shader: name.clone(), locations })?;
+
 		let instance = ShaderRef{
 			name,
-			vertex: vertex?,
-			fragment: fragment?,
+			vertex,
+			fragment,
+			vertex_layout,
 		};
-		Some(instance)
+		Ok(instance)
 	}
 	
-	fn compile_shader(shader_file: &str, kind: ShaderType) -> Option<Vec<u32>>{
-		let file = match glsl_to_spirv::compile(&shader_file, kind) {
-			Ok(spirv_file) => spirv_file,
-			Err(_) => return None,
-		};
+	fn compile_shader(name: &str, stage: &str, shader_file: &str, kind: ShaderType) -> Result<Vec<u32>, ShaderError>{
+		let compilation_failed = |reason: String| ShaderError::CompilationFailed { shader: name.to_string(), stage: stage.to_string(), reason };
+		let file = glsl_to_spirv::compile(shader_file, kind).map_err(compilation_failed)?;
+		gfx_auxil::read_spirv(file).map_err(|e| compilation_failed(e.to_string()))
+	}
+}
+
+/// The locations of the input variables of a spir-v module, from their Location decorations.
+/// Built-in inputs like gl_VertexIndex have no location and are left out.
+fn get_input_locations(spirv: &[u32]) -> Vec<u32> {
+	let mut locations = HashMap::new();
+	let mut inputs = HashSet::new();
 
-		match gfx_auxil::read_spirv(file) {
-			Ok(spirv) => Some(spirv),
-			Err(_) => None,
+	let mut index = SPIRV_HEADER_WORDS;
+	while index < spirv.len() {
+		// Every instruction starts with its length in words and its opcode
+		let word_count = (spirv[index] >> 16) as usize;
+		let opcode = spirv[index] & 0xffff;
+		if word_count == 0 || index + word_count > spirv.len() {
+			break;
+		}
+		let operands = &spirv[index + 1..index + word_count];
+		match opcode {
+			// Target, decoration, location
+			OP_DECORATE if operands.len() >= 3 && operands[1] == DECORATION_LOCATION => {
+				locations.insert(operands[0], operands[2]);
+			}
+			// Result type, result id, storage class
+			OP_VARIABLE if operands.len() >= 3 && operands[2] == STORAGE_CLASS_INPUT => {
+				inputs.insert(operands[1]);
+			}
+			_ => {}
 		}
+		index += word_count;
 	}
-}
\ No newline at end of file
+
+	let mut input_locations: Vec<u32> = locations.into_iter()
+		.filter(|(id, _)| inputs.contains(id))
+		.map(|(_, location)| location)
+		.collect();
+	input_locations.sort_unstable();
+	input_locations
+}
+
+#[cfg(test)]
+mod tests {
+	use glsl_to_spirv::ShaderType;
+	use crate::error::ShaderError;
+	use crate::shader::{get_input_locations, ShaderRef, DECORATION_LOCATION, OP_DECORATE, OP_VARIABLE, STORAGE_CLASS_INPUT};
+
+	const STORAGE_CLASS_OUTPUT: u32 = 3;
+	const DECORATION_BUILT_IN: u32 = 11;
+
+	fn instruction(opcode: u32, operands: &[u32]) -> Vec<u32> {
+		let mut words = vec![((operands.len() as u32 + 1) << 16) | opcode];
+		words.extend_from_slice(operands);
+		words
+	}
+
+	fn compile(source: &str) -> Vec<u32> {
+		ShaderRef::compile_shader("test", "vert", source, ShaderType::Vertex).unwrap()
+	}
+
+	#[test]
+	fn only_input_variables_with_a_location_are_read() {
+		// Magic number, version, generator, bound and schema
+		let mut spirv = vec![0x0723_0203, 0x0001_0000, 0, 20, 0];
+		spirv.extend(instruction(OP_DECORATE, &[10, DECORATION_LOCATION, 7]));
+		spirv.extend(instruction(OP_DECORATE, &[11, DECORATION_LOCATION, 0]));
+		spirv.extend(instruction(OP_DECORATE, &[12, DECORATION_LOCATION, 1]));
+		spirv.extend(instruction(OP_DECORATE, &[13, DECORATION_BUILT_IN, 42]));
+		spirv.extend(instruction(OP_VARIABLE, &[1, 10, STORAGE_CLASS_INPUT]));
+		spirv.extend(instruction(OP_VARIABLE, &[1, 11, STORAGE_CLASS_INPUT]));
+		spirv.extend(instruction(OP_VARIABLE, &[1, 12, STORAGE_CLASS_OUTPUT]));
+		spirv.extend(instruction(OP_VARIABLE, &[1, 13, STORAGE_CLASS_INPUT]));
+		assert_eq!(get_input_locations(&spirv), vec![0, 7]);
+
+		// A truncated instruction ends the module
+		spirv.push((4 << 16) | OP_DECORATE);
+		assert_eq!(get_input_locations(&spirv), vec![0, 7]);
+	}
+
+	#[test]
+	fn declarations_are_read_however_they_are_written() {
+		let source = "#version 450
+			layout(location = 0) in vec3 position;
+			/* layout(location = 1) in vec2 uv;
+			   layout(location = 8) in vec4 color; */
+			// layout(location = 9) in vec2 uv1;
+			layout(
+				location = 6
+			) in vec3 normal;
+			layout(location = 7) highp in vec4 tangent; layout(location = 8) in vec4 color;
+			layout(location = 0) out vec4 v_color;
+			void main() {
+				v_color = color * tangent;
+				gl_Position = vec4(position + normal, 1.0);
+			}";
+		assert_eq!(get_input_locations(&compile(source)), vec![0, 6, 7, 8]);
+	}
+
+	#[test]
+	fn unknown_locations_are_named() {
+		let vertex = "#version 450
+			layout(location = 0) in vec3 position;
+			layout(location = 15) in vec4 unknown;
+			void main() { gl_Position = vec4(position, 1.0) + unknown; }";
+		let fragment = "#version 450
+			layout(location = 0) out vec4 color;
+			void main() { color = vec4(1.0); }";
+		let shaders = [("vert".to_string(), vertex.to_string()), ("frag".to_string(), fragment.to_string())];
+		match ShaderRef::new("unknown".to_string(), &shaders) {
+			Err(ShaderError::UnknownVertexLocations { shader, locations }) => assert_eq!((shader.as_str(), locations), ("unknown", vec![15])),
+			_ => panic!("the shader with an unknown location was created"),
+		}
+
+		let broken = [("vert".to_string(), "#version 450\nvoid main() { gl_Position = ; }".to_string()), ("frag".to_string(), fragment.to_string())];
+		assert!(matches!(ShaderRef::new("broken".to_string(), &broken), Err(ShaderError::CompilationFailed { .. })));
+	}
+}
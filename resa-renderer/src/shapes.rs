@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+use crate::error::RenderError;
+use crate::mesh_builder::{dot, get_face_normal, normalize, MeshBuilder};
+
+/// Fewer segments around a shape can not enclose a volume.
+const MIN_SEGMENTS: u32 = 3;
+/// Every subdivision of an icosphere quadruples its triangles, more than this would not fit into memory.
+const MAX_ICOSPHERE_SUBDIVISIONS: u32 = 8;
+
+// All shapes are centered around the origin with the y axis up. The uv origin is the top left of the texture,
+// seams are at the +z side of the shapes, where the vertices are duplicated to wrap the uvs around.
+
+/// Box with four vertices per face, so every face has its own normal and the full texture.
+pub fn cuboid(size: [f32; 3]) -> MeshBuilder {
+	let [x, y, z] = size.map(|extent| extent / 2.0);
+	let mut builder = MeshBuilder::new();
+	// Center, right and up direction of every face, seen from the front
+	let faces = [
+		([0.0, 0.0, z], [x, 0.0, 0.0], [0.0, y, 0.0]),
+		([0.0, 0.0, -z], [-x, 0.0, 0.0], [0.0, y, 0.0]),
+		([x, 0.0, 0.0], [0.0, 0.0, -z], [0.0, y, 0.0]),
+		([-x, 0.0, 0.0], [0.0, 0.0, z], [0.0, y, 0.0]),
+		([0.0, y, 0.0], [x, 0.0, 0.0], [0.0, 0.0, -z]),
+		([0.0, -y, 0.0], [x, 0.0, 0.0], [0.0, 0.0, z]),
+	];
+	for (center, right, up) in faces {
+		let normal = normalize(center);
+		let corner = |right_factor: f32, up_factor: f32| [0, 1, 2].map(|axis| center[axis] + right[axis] * right_factor + up[axis] * up_factor);
+		let top_left = builder.add_vertex(corner(-1.0, 1.0), normal, [0.0, 0.0]);
+		let bottom_left = builder.add_vertex(corner(-1.0, -1.0), normal, [0.0, 1.0]);
+		let bottom_right = builder.add_vertex(corner(1.0, -1.0), normal, [1.0, 1.0]);
+		let top_right = builder.add_vertex(corner(1.0, 1.0), normal, [1.0, 0.0]);
+		builder.add_quad(top_left, bottom_left, bottom_right, top_right);
+	}
+	builder
+}
+
+/// Sphere of rings from pole to pole, with the texture wrapped around it like a map of the earth.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshBuilder {
+	let rings = rings.max(2);
+	let profile: Vec<([f32; 2], [f32; 2])> = (0..=rings).map(|ring| {
+		let angle = PI * ring as f32 / rings as f32;
+		([radius * angle.sin(), radius * angle.cos()], [angle.sin(), angle.cos()])
+	}).collect();
+
+	let mut builder = MeshBuilder::new();
+	add_lathe(&mut builder, segments, &profile);
+	builder
+}
+
+/// Sphere from a subdivided icosahedron, its triangles are of nearly the same size everywhere.
+/// The uvs are the same as the ones of the uv sphere.
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshBuilder {
+	let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+	let mut points: Vec<[f32; 3]> = [
+		[-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+		[0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+		[t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+	].iter().map(|point| normalize(*point)).collect();
+	let mut triangles: Vec<[u32; 3]> = vec![
+		[0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+		[1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+		[3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+		[4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+	];
+
+	for _ in 0..subdivisions.min(MAX_ICOSPHERE_SUBDIVISIONS) {
+		let mut midpoints = HashMap::<(u32, u32), u32>::new();
+		let mut get_midpoint = |a: u32, b: u32| *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+			let [pa, pb] = [points[a as usize], points[b as usize]];
+			points.push(normalize([pa[0] + pb[0], pa[1] + pb[1], pa[2] + pb[2]]));
+			points.len() as u32 - 1
+		});
+		triangles = triangles.iter().flat_map(|[a, b, c]| {
+			let [ab, bc, ca] = [get_midpoint(*a, *b), get_midpoint(*b, *c), get_midpoint(*c, *a)];
+			[[*a, ab, ca], [*b, bc, ab], [*c, ca, bc], [ab, bc, ca]]
+		}).collect();
+	}
+
+	// Triangles across the seam get their own vertices with u above 1, the poles get the u of the triangle
+	let mut builder = MeshBuilder::new();
+	let mut vertices = HashMap::<(u32, u32), u32>::new();
+	for triangle in triangles {
+		let corners = triangle.map(|index| points[index as usize]);
+		let mut uvs = corners.map(|point| [(point[0].atan2(point[2]) / TAU).rem_euclid(1.0), point[1].clamp(-1.0, 1.0).acos() / PI]);
+		let is_pole = corners.map(|point| point[1].abs() > 1.0 - 1e-6);
+		let (min_u, max_u) = (0..3).filter(|corner| !is_pole[*corner]).fold((f32::MAX, f32::MIN), |(min, max), corner| (min.min(uvs[corner][0]), max.max(uvs[corner][0])));
+		if max_u - min_u > 0.5 {
+			for uv in uvs.iter_mut().filter(|uv| uv[0] < 0.5) {
+				uv[0] += 1.0;
+			}
+		}
+		for corner in (0..3).filter(|corner| is_pole[*corner]) {
+			let others: Vec<f32> = (0..3).filter(|other| !is_pole[*other]).map(|other| uvs[other][0]).collect();
+			uvs[corner][0] = others.iter().sum::<f32>() / others.len().max(1) as f32;
+		}
+
+		let mut indices = [0, 1, 2].map(|corner| *vertices.entry((triangle[corner], uvs[corner][0].to_bits())).or_insert_with(|| {
+			builder.add_vertex(corners[corner].map(|value| value * radius), corners[corner], uvs[corner])
+		}));
+		// The triangles of the icosahedron are not ordered, so they are turned to face outwards
+		let centroid = [0, 1, 2].map(|axis| corners[0][axis] + corners[1][axis] + corners[2][axis]);
+		if dot(get_face_normal(&[corners[0], corners[1], corners[2]], &[0, 1, 2]), centroid) < 0.0 {
+			indices.swap(1, 2);
+		}
+		builder.add_triangle(indices[0], indices[1], indices[2]);
+	}
+	builder
+}
+
+/// Cylinder along the y axis with closed ends.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshBuilder {
+	let half_height = height / 2.0;
+	let mut builder = MeshBuilder::new();
+	add_lathe(&mut builder, segments, &[([radius, half_height], [1.0, 0.0]), ([radius, -half_height], [1.0, 0.0])]);
+	add_disk(&mut builder, radius, half_height, true, segments);
+	add_disk(&mut builder, radius, -half_height, false, segments);
+	builder
+}
+
+/// Cone along the y axis with its tip at the top and a closed base.
+pub fn cone(radius: f32, height: f32, segments: u32) -> MeshBuilder {
+	let half_height = height / 2.0;
+	// The normal of the side is perpendicular to the line from the tip to the base
+	let slope_length = (height * height + radius * radius).sqrt().max(f32::EPSILON);
+	let normal = [height / slope_length, radius / slope_length];
+	let mut builder = MeshBuilder::new();
+	add_lathe(&mut builder, segments, &[([0.0, half_height], normal), ([radius, -half_height], normal)]);
+	add_disk(&mut builder, radius, -half_height, false, segments);
+	builder
+}
+
+/// Cylinder with half spheres as ends, `height` is the total height including the ends.
+/// `rings` is the number of rings of each half sphere.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshBuilder {
+	let rings = rings.max(1);
+	let half_cylinder = (height / 2.0 - radius).max(0.0);
+	let mut profile = vec![];
+	for (center, first_angle) in [(half_cylinder, 0.0), (-half_cylinder, PI / 2.0)] {
+		for ring in 0..=rings {
+			let angle = first_angle + PI / 2.0 * ring as f32 / rings as f32;
+			profile.push(([radius * angle.sin(), center + radius * angle.cos()], [angle.sin(), angle.cos()]));
+		}
+	}
+
+	let mut builder = MeshBuilder::new();
+	add_lathe(&mut builder, segments, &profile);
+	builder
+}
+
+/// Torus around the y axis. `major_radius` is the distance of the tube center to the origin, `minor_radius` the radius of the tube.
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> MeshBuilder {
+	let minor_segments = minor_segments.max(MIN_SEGMENTS);
+	// The tube is walked from its outer side downwards, so the triangles face outwards
+	let profile: Vec<([f32; 2], [f32; 2])> = (0..=minor_segments).map(|segment| {
+		let angle = -TAU * segment as f32 / minor_segments as f32;
+		([major_radius + minor_radius * angle.cos(), minor_radius * angle.sin()], [angle.cos(), angle.sin()])
+	}).collect();
+
+	let mut builder = MeshBuilder::new();
+	add_lathe(&mut builder, major_segments, &profile);
+	builder
+}
+
+/// Flat grid in the xz plane facing up, with `columns` times `rows` quads.
+pub fn plane(width: f32, depth: f32, columns: u32, rows: u32) -> MeshBuilder {
+	let (columns, rows) = (columns.max(1), rows.max(1));
+	let mut builder = MeshBuilder::new();
+	let first = builder.get_vertex_count() as u32;
+	for row in 0..=rows {
+		for column in 0..=columns {
+			let uv = [column as f32 / columns as f32, row as f32 / rows as f32];
+			builder.add_vertex([width * (uv[0] - 0.5), 0.0, depth * (uv[1] - 0.5)], [0.0, 1.0, 0.0], uv);
+		}
+	}
+	add_grid_triangles(&mut builder, first, columns, rows);
+	builder
+}
+
+/// Terrain in the xz plane from a heightmap of `columns` times `rows` samples, row by row from -z to +z.
+/// `size` is the extent in x and z and the factor of the heights in y.
+pub fn terrain(heights: &[f32], columns: u32, rows: u32, size: [f32; 3]) -> Result<MeshBuilder, RenderError> {
+	// The sample count is checked without overflow, a heightmap larger than u32 could not be indexed anyway
+	if columns < 2 || rows < 2 || columns.checked_mul(rows).map(|samples| samples as usize) != Some(heights.len()) {
+		return Err(RenderError::InvalidHeightmap { columns, rows, samples: heights.len() });
+	}
+
+	let [width, height_scale, depth] = size;
+	let (step_x, step_z) = (width / (columns - 1) as f32, depth / (rows - 1) as f32);
+	let get_height = |column: u32, row: u32| heights[(row * columns + column) as usize] * height_scale;
+
+	let mut builder = MeshBuilder::new();
+	for row in 0..rows {
+		for column in 0..columns {
+			// Slope from the neighbour samples, one sided at the borders
+			let (left, right) = (column.saturating_sub(1), (column + 1).min(columns - 1));
+			let (front, back) = (row.saturating_sub(1), (row + 1).min(rows - 1));
+			let slope_x = (get_height(right, row) - get_height(left, row)) / ((right - left) as f32 * step_x);
+			let slope_z = (get_height(column, back) - get_height(column, front)) / ((back - front) as f32 * step_z);
+
+			let uv = [column as f32 / (columns - 1) as f32, row as f32 / (rows - 1) as f32];
+			let position = [width * (uv[0] - 0.5), get_height(column, row), depth * (uv[1] - 0.5)];
+			builder.add_vertex(position, normalize([-slope_x, 1.0, -slope_z]), uv);
+		}
+	}
+	add_grid_triangles(&mut builder, 0, columns - 1, rows - 1);
+	Ok(builder)
+}
+
+/// Revolve a profile of radius and height around the y axis. The profile has to go from top to bottom
+/// for the triangles to face outwards, its normals are given as radial and vertical part.
+/// The v coordinate follows the length of the profile, so the texture is not stretched.
+fn add_lathe(builder: &mut MeshBuilder, segments: u32, profile: &[([f32; 2], [f32; 2])]) {
+	let segments = segments.max(MIN_SEGMENTS);
+	let mut lengths = vec![0.0f32];
+	for pair in profile.windows(2) {
+		let ([radius_a, y_a], [radius_b, y_b]) = (pair[0].0, pair[1].0);
+		lengths.push(lengths.last().unwrap() + ((radius_b - radius_a).powi(2) + (y_b - y_a).powi(2)).sqrt());
+	}
+	let total_length = lengths.last().copied().unwrap_or(0.0);
+
+	let first = builder.get_vertex_count() as u32;
+	for (index, ([radius, y], [normal_radial, normal_y])) in profile.iter().enumerate() {
+		let v = if total_length > 0.0 { lengths[index] / total_length } else { index as f32 / (profile.len() - 1).max(1) as f32 };
+		for segment in 0..=segments {
+			let u = segment as f32 / segments as f32;
+			let (sin, cos) = (TAU * u).sin_cos();
+			builder.add_vertex([radius * sin, *y, radius * cos], normalize([normal_radial * sin, *normal_y, normal_radial * cos]), [u, v]);
+		}
+	}
+	add_grid_triangles(builder, first, segments, profile.len() as u32 - 1);
+}
+
+/// Closed circle at the height y, facing up or down.
+fn add_disk(builder: &mut MeshBuilder, radius: f32, y: f32, facing_up: bool, segments: u32) {
+	let segments = segments.max(MIN_SEGMENTS);
+	let (normal, mirror) = if facing_up { ([0.0, 1.0, 0.0], 1.0) } else { ([0.0, -1.0, 0.0], -1.0) };
+	let center = builder.add_vertex([0.0, y, 0.0], normal, [0.5, 0.5]);
+	for segment in 0..=segments {
+		let (sin, cos) = (TAU * segment as f32 / segments as f32).sin_cos();
+		builder.add_vertex([radius * sin, y, radius * cos], normal, [0.5 + 0.5 * mirror * sin, 0.5 + 0.5 * cos]);
+	}
+	for segment in 0..segments {
+		let (a, b) = (center + 1 + segment, center + 2 + segment);
+		if facing_up {
+			builder.add_triangle(center, a, b);
+		} else {
+			builder.add_triangle(center, b, a);
+		}
+	}
+}
+
+/// Triangles between rows of `columns` + 1 vertices, starting at the vertex `first`.
+/// Triangles without area, like the ones at the poles of a sphere, are left out.
+fn add_grid_triangles(builder: &mut MeshBuilder, first: u32, columns: u32, rows: u32) {
+	for row in 0..rows {
+		for column in 0..columns {
+			let top_left = first + row * (columns + 1) + column;
+			let bottom_left = top_left + columns + 1;
+			for triangle in [[top_left, bottom_left, bottom_left + 1], [top_left, bottom_left + 1, top_left + 1]] {
+				let normal = get_face_normal(builder.get_positions(), &triangle);
+				let [a, b, c] = triangle.map(|index| builder.get_positions()[index as usize]);
+				let edges = [0, 1, 2].map(|axis| b[axis] - a[axis]);
+				let other_edges = [0, 1, 2].map(|axis| c[axis] - a[axis]);
+				if dot(normal, normal) > 1e-10 * dot(edges, edges) * dot(other_edges, other_edges) {
+					builder.add_triangle(triangle[0], triangle[1], triangle[2]);
+				}
+			}
+		}
+	}
+}
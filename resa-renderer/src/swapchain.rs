@@ -1,21 +1,21 @@
 use gfx_hal::Backend;
 use gfx_hal::format::{ChannelType, Format};
-use gfx_hal::image::{Extent, FramebufferAttachment};
+use gfx_hal::image::{Extent, FramebufferAttachment, Usage};
 use gfx_hal::prelude::{PresentationSurface, Surface};
-use gfx_hal::pso::{Rect, Viewport};
 use gfx_hal::window::{Extent2D, SwapchainConfig};
+use log::warn;
 use crate::core::CoreDevice;
+use crate::PresentMode;
 
 pub struct Swapchain {
     pub extent: Extent,
     pub format: Format,
-    pub frame_index: u32,
     pub frame_queue_size: u32,
     pub framebuffer_attachment: FramebufferAttachment,
 }
 
 impl Swapchain {
-    pub fn new<B: Backend>(surface: &mut B::Surface, core_device: &CoreDevice<B>, dimensions: Extent2D) -> Self {
+    pub fn new<B: Backend>(surface: &mut B::Surface, core_device: &CoreDevice<B>, dimensions: Extent2D, present_mode: PresentMode) -> Self {
         let capabilities = surface.capabilities(&core_device.physical_device);
         let formats = surface.supported_formats(&core_device.physical_device);
 
@@ -27,7 +27,23 @@ impl Swapchain {
                 .unwrap_or(formats[0])
         });
 
-        let swap_config = SwapchainConfig::from_caps(&capabilities, format, dimensions);
+        let requested_mode = match present_mode {
+            PresentMode::Fifo => gfx_hal::window::PresentMode::FIFO,
+            PresentMode::Mailbox => gfx_hal::window::PresentMode::MAILBOX,
+            PresentMode::Immediate => gfx_hal::window::PresentMode::IMMEDIATE,
+        };
+        let present_mode = if capabilities.present_modes.contains(requested_mode) {
+            requested_mode
+        } else {
+            warn!("Present mode {:?} is not supported, falling back to fifo", present_mode);
+            gfx_hal::window::PresentMode::FIFO
+        };
+
+        let mut swap_config = SwapchainConfig::from_caps(&capabilities, format, dimensions).with_present_mode(present_mode);
+        // Allow copying the swapchain images, so frames can be read back to the cpu.
+        if capabilities.usage.contains(Usage::TRANSFER_SRC) {
+            swap_config.image_usage |= Usage::TRANSFER_SRC;
+        }
         let framebuffer_attachment = swap_config.framebuffer_attachment();
         let extent = swap_config.extent.to_extent();
         let frame_queue_size = swap_config.image_count;
@@ -39,21 +55,8 @@ impl Swapchain {
         Swapchain {
             extent,
             format,
-            frame_index: 0,
             frame_queue_size,
             framebuffer_attachment,
         }
     }
-
-    pub fn make_viewport(&self) -> Viewport{
-        Viewport{
-            rect: Rect{
-                x: 0,
-                y: 0,
-                h: self.extent.height as i16,
-                w: self.extent.width as i16,
-            },
-            depth: 0.0..1.0,
-        }
-    }
 }
\ No newline at end of file
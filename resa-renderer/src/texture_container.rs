@@ -0,0 +1,213 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+/// Block compressed formats, every block holds four by four pixels.
+/// Whether the colors are srgb is set per texture, independent of the format the container names.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum BlockFormat {
+	Bc1Rgb,
+	/// Bc1 with one bit alpha
+	Bc1Rgba,
+	Bc2,
+	Bc3,
+	/// One channel
+	Bc4,
+	Bc4Signed,
+	/// Two channels, e.g. the x and y of a normal map
+	Bc5,
+	Bc5Signed,
+	/// Unsigned half floats
+	Bc6h,
+	Bc6hSigned,
+	Bc7,
+}
+
+impl BlockFormat {
+	pub fn get_block_size(&self) -> u32 {
+		match self {
+			BlockFormat::Bc1Rgb | BlockFormat::Bc1Rgba | BlockFormat::Bc4 | BlockFormat::Bc4Signed => 8,
+			_ => 16,
+		}
+	}
+
+	/// Size in bytes of a level with the pixel size.
+	pub fn get_level_size(&self, width: u32, height: u32) -> usize {
+		let blocks = ((width.max(1) + 3) / 4) * ((height.max(1) + 3) / 4);
+		blocks as usize * self.get_block_size() as usize
+	}
+}
+
+impl fmt::Display for BlockFormat {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			BlockFormat::Bc1Rgb => write!(f, "bc1 rgb"),
+			BlockFormat::Bc1Rgba => write!(f, "bc1 rgba"),
+			BlockFormat::Bc2 => write!(f, "bc2"),
+			BlockFormat::Bc3 => write!(f, "bc3"),
+			BlockFormat::Bc4 => write!(f, "bc4"),
+			BlockFormat::Bc4Signed => write!(f, "bc4 signed"),
+			BlockFormat::Bc5 => write!(f, "bc5"),
+			BlockFormat::Bc5Signed => write!(f, "bc5 signed"),
+			BlockFormat::Bc6h => write!(f, "bc6h"),
+			BlockFormat::Bc6hSigned => write!(f, "bc6h signed"),
+			BlockFormat::Bc7 => write!(f, "bc7"),
+		}
+	}
+}
+
+/// The blocks of a compressed 2d texture, uploaded as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressedTexture {
+	pub format: BlockFormat,
+	pub width: u32,
+	pub height: u32,
+	/// The blocks of every mip level the container has, from the full size down
+	pub levels: Vec<Vec<u8>>,
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+/// The identifier, the header of nine values and the index of the data format, key value and supercompression data
+const KTX2_LEVEL_INDEX_OFFSET: usize = 12 + 9 * 4 + 4 * 4 + 2 * 8;
+
+/// Read a KTX2 file with a 2d texture of block compressed data, without supercompression.
+pub fn parse_ktx2(data: &[u8]) -> Result<CompressedTexture, String> {
+	if data.get(..12) != Some(&KTX2_IDENTIFIER[..]) {
+		return Err("the data is not a ktx2 file".to_string());
+	}
+	let vk_format = read_u32(data, 12)?;
+	let width = read_u32(data, 20)?;
+	let height = read_u32(data, 24)?;
+	let depth = read_u32(data, 28)?;
+	let layer_count = read_u32(data, 32)?;
+	let face_count = read_u32(data, 36)?;
+	// No levels means that they should be generated, which is not possible for compressed data
+	let level_count = read_u32(data, 40)?.max(1);
+	let supercompression = read_u32(data, 44)?;
+
+	let format = match vk_format {
+		131 | 132 => BlockFormat::Bc1Rgb,
+		133 | 134 => BlockFormat::Bc1Rgba,
+		135 | 136 => BlockFormat::Bc2,
+		137 | 138 => BlockFormat::Bc3,
+		139 => BlockFormat::Bc4,
+		140 => BlockFormat::Bc4Signed,
+		141 => BlockFormat::Bc5,
+		142 => BlockFormat::Bc5Signed,
+		143 => BlockFormat::Bc6h,
+		144 => BlockFormat::Bc6hSigned,
+		145 | 146 => BlockFormat::Bc7,
+		_ => return Err(format!("vulkan format {} is not a block compressed format", vk_format)),
+	};
+	if depth > 1 || layer_count > 1 || face_count != 1 {
+		return Err("only 2d textures without layers and faces are supported".to_string());
+	}
+	if supercompression != 0 {
+		return Err(format!("supercompression scheme {} is not supported", supercompression));
+	}
+	check_dimensions(width, height, level_count)?;
+
+	let levels = (0..level_count).map(|level| {
+		let index_offset = KTX2_LEVEL_INDEX_OFFSET + level as usize * 3 * 8;
+		let offset = read_u64(data, index_offset)? as usize;
+		let length = read_u64(data, index_offset + 8)? as usize;
+		let expected_length = format.get_level_size(width >> level, height >> level);
+		if length != expected_length {
+			return Err(format!("level {} has {} bytes instead of {}", level, length, expected_length));
+		}
+		read_bytes(data, offset, length).map(<[u8]>::to_vec)
+	}).collect::<Result<Vec<Vec<u8>>, String>>()?;
+
+	Ok(CompressedTexture { format, width, height, levels })
+}
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: usize = 4 + 124;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_FOURCC: u32 = 0x4;
+
+/// Read a DDS file with a 2d texture of block compressed data. The format is either a four character code
+/// like DXT1 or a DXGI format of the DX10 header.
+pub fn parse_dds(data: &[u8]) -> Result<CompressedTexture, String> {
+	if data.get(..4) != Some(&DDS_MAGIC[..]) {
+		return Err("the data is not a dds file".to_string());
+	}
+	let flags = read_u32(data, 8)?;
+	let height = read_u32(data, 12)?;
+	let width = read_u32(data, 16)?;
+	let level_count = if flags & DDSD_MIPMAPCOUNT != 0 { read_u32(data, 28)?.max(1) } else { 1 };
+	let pixel_flags = read_u32(data, 80)?;
+	let four_cc = read_bytes(data, 84, 4)?;
+
+	if pixel_flags & DDPF_FOURCC == 0 {
+		return Err("the data is not block compressed".to_string());
+	}
+	let (format, data_offset) = match four_cc {
+		b"DXT1" => (BlockFormat::Bc1Rgba, DDS_HEADER_SIZE),
+		b"DXT2" | b"DXT3" => (BlockFormat::Bc2, DDS_HEADER_SIZE),
+		b"DXT4" | b"DXT5" => (BlockFormat::Bc3, DDS_HEADER_SIZE),
+		b"ATI1" | b"BC4U" => (BlockFormat::Bc4, DDS_HEADER_SIZE),
+		b"BC4S" => (BlockFormat::Bc4Signed, DDS_HEADER_SIZE),
+		b"ATI2" | b"BC5U" => (BlockFormat::Bc5, DDS_HEADER_SIZE),
+		b"BC5S" => (BlockFormat::Bc5Signed, DDS_HEADER_SIZE),
+		b"DX10" => {
+			let dxgi_format = read_u32(data, DDS_HEADER_SIZE)?;
+			let array_size = read_u32(data, DDS_HEADER_SIZE + 12)?;
+			if array_size > 1 {
+				return Err("only 2d textures without layers are supported".to_string());
+			}
+			let format = match dxgi_format {
+				70..=72 => BlockFormat::Bc1Rgba,
+				73..=75 => BlockFormat::Bc2,
+				76..=78 => BlockFormat::Bc3,
+				79 | 80 => BlockFormat::Bc4,
+				81 => BlockFormat::Bc4Signed,
+				82 | 83 => BlockFormat::Bc5,
+				84 => BlockFormat::Bc5Signed,
+				94 | 95 => BlockFormat::Bc6h,
+				96 => BlockFormat::Bc6hSigned,
+				97..=99 => BlockFormat::Bc7,
+				_ => return Err(format!("dxgi format {} is not a block compressed format", dxgi_format)),
+			};
+			(format, DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE)
+		}
+		_ => return Err(format!("{} is not a block compressed format", String::from_utf8_lossy(four_cc))),
+	};
+	check_dimensions(width, height, level_count)?;
+
+	// The levels follow each other from the full size down
+	let mut offset = data_offset;
+	let levels = (0..level_count).map(|level| {
+		let length = format.get_level_size(width >> level, height >> level);
+		let level_data = read_bytes(data, offset, length)?.to_vec();
+		offset += length;
+		Ok(level_data)
+	}).collect::<Result<Vec<Vec<u8>>, String>>()?;
+
+	Ok(CompressedTexture { format, width, height, levels })
+}
+
+fn check_dimensions(width: u32, height: u32, level_count: u32) -> Result<(), String> {
+	if width == 0 || height == 0 {
+		return Err("the texture has no pixels".to_string());
+	}
+	let max_levels = u32::BITS - width.max(height).leading_zeros();
+	if level_count > max_levels {
+		return Err(format!("a texture of {}x{} pixels can not have {} mip levels", width, height, level_count));
+	}
+	Ok(())
+}
+
+fn read_bytes(data: &[u8], offset: usize, length: usize) -> Result<&[u8], String> {
+	offset.checked_add(length)
+		.and_then(|end| data.get(offset..end))
+		.ok_or_else(|| format!("the data ends before byte {}", offset.saturating_add(length)))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+	Ok(u32::from_le_bytes(read_bytes(data, offset, 4)?.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, String> {
+	Ok(u64::from_le_bytes(read_bytes(data, offset, 8)?.try_into().unwrap()))
+}
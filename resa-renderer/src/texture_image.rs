@@ -0,0 +1,243 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use gfx_hal::format::Format;
+use image::{DynamicImage, ImageFormat, Rgba32FImage, RgbaImage};
+use crate::material::{ColorSpace, TextureFormat};
+use crate::mipmap::{generate_float_mip_chain, generate_mip_chain};
+use crate::texture_container::{parse_dds, parse_ktx2, BlockFormat, CompressedTexture};
+
+/// A decoded texture, ready to be uploaded.
+pub(crate) enum TextureImage {
+	/// Eight bits per channel
+	Rgba8(RgbaImage, ColorSpace),
+	/// Linear floats, uploaded as half floats
+	RgbaFloat(Rgba32FImage),
+	/// Blocks of compressed pixels with the mip levels of their container
+	Compressed(CompressedTexture, ColorSpace),
+}
+
+/// The smallest part of a texture which can be copied, a pixel or a block of compressed pixels.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TexelBlock {
+	pub width: u32,
+	pub height: u32,
+	/// Size in bytes
+	pub size: u32,
+}
+
+impl TextureImage {
+	/// The color space applies to eight bit and compressed images, float images are always linear.
+	pub(crate) fn decode(data: Vec<u8>, format: &TextureFormat, color_space: ColorSpace) -> Result<Self, String> {
+		let texture = match format {
+			TextureFormat::Custom((width, height)) => {
+				let image = RgbaImage::from_raw(*width, *height, data)
+					.ok_or_else(|| format!("the data does not have four bytes for each of the {}x{} pixels", width, height))?;
+				TextureImage::Rgba8(image, color_space)
+			}
+			TextureFormat::Png => TextureImage::Rgba8(load_image(&data, ImageFormat::Png)?.into_rgba8(), color_space),
+			TextureFormat::Jpeg => TextureImage::Rgba8(load_image(&data, ImageFormat::Jpeg)?.into_rgba8(), color_space),
+			TextureFormat::Tga => TextureImage::Rgba8(load_image(&data, ImageFormat::Tga)?.into_rgba8(), color_space),
+			TextureFormat::Bmp => TextureImage::Rgba8(load_image(&data, ImageFormat::Bmp)?.into_rgba8(), color_space),
+			TextureFormat::Hdr => TextureImage::RgbaFloat(load_image(&data, ImageFormat::Hdr)?.into_rgba32f()),
+			TextureFormat::OpenExr => TextureImage::RgbaFloat(load_image(&data, ImageFormat::OpenExr)?.into_rgba32f()),
+			TextureFormat::Ktx2 => TextureImage::Compressed(parse_ktx2(&data)?, color_space),
+			TextureFormat::Dds => TextureImage::Compressed(parse_dds(&data)?, color_space),
+		};
+
+		let (width, height) = texture.get_dimensions();
+		if width == 0 || height == 0 {
+			return Err("the image has no pixels".to_string());
+		}
+		Ok(texture)
+	}
+
+	pub(crate) fn get_dimensions(&self) -> (u32, u32) {
+		match self {
+			TextureImage::Rgba8(image, _) => image.dimensions(),
+			TextureImage::RgbaFloat(image) => image.dimensions(),
+			TextureImage::Compressed(texture, _) => (texture.width, texture.height),
+		}
+	}
+
+	pub(crate) fn get_format(&self) -> Format {
+		let srgb = |color_space: &ColorSpace, srgb_format: Format, linear_format: Format| match color_space {
+			ColorSpace::Srgb => srgb_format,
+			ColorSpace::Linear => linear_format,
+		};
+		match self {
+			TextureImage::Rgba8(_, color_space) => srgb(color_space, Format::Rgba8Srgb, Format::Rgba8Unorm),
+			TextureImage::RgbaFloat(_) => Format::Rgba16Sfloat,
+			TextureImage::Compressed(texture, color_space) => match texture.format {
+				BlockFormat::Bc1Rgb => srgb(color_space, Format::Bc1RgbSrgb, Format::Bc1RgbUnorm),
+				BlockFormat::Bc1Rgba => srgb(color_space, Format::Bc1RgbaSrgb, Format::Bc1RgbaUnorm),
+				BlockFormat::Bc2 => srgb(color_space, Format::Bc2Srgb, Format::Bc2Unorm),
+				BlockFormat::Bc3 => srgb(color_space, Format::Bc3Srgb, Format::Bc3Unorm),
+				// The formats with one or two channels and the float formats have no srgb variant
+				BlockFormat::Bc4 => Format::Bc4Unorm,
+				BlockFormat::Bc4Signed => Format::Bc4Snorm,
+				BlockFormat::Bc5 => Format::Bc5Unorm,
+				BlockFormat::Bc5Signed => Format::Bc5Snorm,
+				BlockFormat::Bc6h => Format::Bc6hUfloat,
+				BlockFormat::Bc6hSigned => Format::Bc6hSfloat,
+				BlockFormat::Bc7 => srgb(color_space, Format::Bc7Srgb, Format::Bc7Unorm),
+			},
+		}
+	}
+
+	pub(crate) fn get_texel_block(&self) -> TexelBlock {
+		match self {
+			TextureImage::Rgba8(..) => TexelBlock { width: 1, height: 1, size: 4 },
+			TextureImage::RgbaFloat(_) => TexelBlock { width: 1, height: 1, size: 8 },
+			TextureImage::Compressed(texture, _) => TexelBlock { width: 4, height: 4, size: texture.format.get_block_size() },
+		}
+	}
+
+	/// Compressed textures can not be filtered into new mip levels, they have the ones of their container.
+	pub(crate) fn get_level_count(&self) -> Option<u8> {
+		match self {
+			TextureImage::Compressed(texture, _) => Some(texture.levels.len() as u8),
+			_ => None,
+		}
+	}
+
+	/// The data of the levels of the texture. The levels below the first one are generated on the cpu if it is asked to.
+	pub(crate) fn get_levels(&self, generate_mip_levels: bool) -> Vec<Cow<[u8]>> {
+		match self {
+			TextureImage::Rgba8(image, color_space) => {
+				let mut levels = vec![Cow::Borrowed(image.as_raw().as_slice())];
+				if generate_mip_levels {
+					levels.extend(generate_mip_chain(image, *color_space).into_iter().map(|level| Cow::Owned(level.into_raw())));
+				}
+				levels
+			}
+			TextureImage::RgbaFloat(image) => {
+				let mut levels = vec![Cow::Owned(to_half_floats(image))];
+				if generate_mip_levels {
+					levels.extend(generate_float_mip_chain(image).iter().map(|level| Cow::Owned(to_half_floats(level))));
+				}
+				levels
+			}
+			TextureImage::Compressed(texture, _) => texture.levels.iter().map(|level| Cow::Borrowed(level.as_slice())).collect(),
+		}
+	}
+}
+
+fn load_image(data: &[u8], image_format: ImageFormat) -> Result<DynamicImage, String> {
+	image::load(Cursor::new(data), image_format).map_err(|e| e.to_string())
+}
+
+fn to_half_floats(image: &Rgba32FImage) -> Vec<u8> {
+	image.as_raw().iter().flat_map(|value| to_half_float(*value).to_le_bytes()).collect()
+}
+
+/// The nearest half float, values beyond its range become infinite.
+fn to_half_float(value: f32) -> u16 {
+	let bits = value.to_bits();
+	let sign = ((bits >> 16) & 0x8000) as u16;
+	if value.is_nan() {
+		return sign | 0x7e00;
+	}
+	let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+	let mantissa = bits & 0x7f_ffff;
+	if exponent >= 0x1f {
+		return sign | 0x7c00;
+	}
+	if exponent <= 0 {
+		// Too small for a half float
+		if exponent < -10 {
+			return sign;
+		}
+		// Subnormal, the implicit leading one becomes part of the mantissa
+		let mantissa = mantissa | 0x80_0000;
+		let shift = (14 - exponent) as u32;
+		let rounding = (mantissa >> (shift - 1)) & 1;
+		return sign | ((mantissa >> shift) + rounding) as u16;
+	}
+	// A rounding carry into the exponent is still the nearest half float
+	let rounding = (mantissa >> 12) & 1;
+	sign | ((((exponent as u32) << 10) | (mantissa >> 13)) + rounding) as u16
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+	use gfx_hal::format::Format;
+	use image::codecs::hdr::HdrEncoder;
+	use image::{DynamicImage, ImageOutputFormat, Rgb, Rgba, Rgba32FImage};
+	use crate::material::{ColorSpace, TextureFormat};
+	use crate::texture_container::{BlockFormat, CompressedTexture};
+	use crate::texture_image::{to_half_float, TextureImage};
+
+	#[test]
+	fn floats_round_to_the_nearest_half_float() {
+		assert_eq!(to_half_float(0.0), 0x0000);
+		assert_eq!(to_half_float(-0.0), 0x8000);
+		assert_eq!(to_half_float(1.0), 0x3c00);
+		assert_eq!(to_half_float(-2.0), 0xc000);
+		assert_eq!(to_half_float(0.1), 0x2e66);
+		// 1 + 2^-11 lies between two half floats and rounds up, 1 + 2^-12 rounds down
+		assert_eq!(to_half_float(1.0 + 2f32.powi(-11)), 0x3c01);
+		assert_eq!(to_half_float(1.0 + 2f32.powi(-12)), 0x3c00);
+		assert_eq!(to_half_float(65504.0), 0x7bff);
+	}
+
+	#[test]
+	fn out_of_range_floats_become_infinite_or_zero() {
+		assert_eq!(to_half_float(65520.0), 0x7c00);
+		assert_eq!(to_half_float(1e6), 0x7c00);
+		assert_eq!(to_half_float(-1e6), 0xfc00);
+		assert_eq!(to_half_float(f32::INFINITY), 0x7c00);
+		assert_eq!(to_half_float(f32::NAN) & 0x7fff, 0x7e00);
+		// The smallest normal and subnormal half floats
+		assert_eq!(to_half_float(2f32.powi(-14)), 0x0400);
+		assert_eq!(to_half_float(2f32.powi(-24)), 0x0001);
+		assert_eq!(to_half_float(3.0 * 2f32.powi(-16)), 0x0300);
+		assert_eq!(to_half_float(1e-10), 0x0000);
+		assert_eq!(to_half_float(-1e-10), 0x8000);
+	}
+
+	#[test]
+	fn eight_bit_images_follow_the_color_space() {
+		let pixels = vec![255u8; 2 * 2 * 4];
+		let srgb = TextureImage::decode(pixels.clone(), &TextureFormat::Custom((2, 2)), ColorSpace::Srgb).unwrap();
+		assert_eq!(srgb.get_format(), Format::Rgba8Srgb);
+		let linear = TextureImage::decode(pixels, &TextureFormat::Custom((2, 2)), ColorSpace::Linear).unwrap();
+		assert_eq!(linear.get_format(), Format::Rgba8Unorm);
+		assert_eq!(linear.get_dimensions(), (2, 2));
+
+		assert!(TextureImage::decode(vec![255u8; 15], &TextureFormat::Custom((2, 2)), ColorSpace::Srgb).is_err());
+		assert!(TextureImage::decode(vec![], &TextureFormat::Custom((0, 0)), ColorSpace::Srgb).is_err());
+	}
+
+	#[test]
+	fn compressed_images_follow_the_color_space_if_their_format_has_srgb() {
+		let get_format = |format: BlockFormat, color_space: ColorSpace| {
+			let texture = CompressedTexture { format, width: 4, height: 4, levels: vec![vec![0; format.get_block_size() as usize]] };
+			TextureImage::Compressed(texture, color_space).get_format()
+		};
+		assert_eq!(get_format(BlockFormat::Bc1Rgba, ColorSpace::Srgb), Format::Bc1RgbaSrgb);
+		assert_eq!(get_format(BlockFormat::Bc1Rgba, ColorSpace::Linear), Format::Bc1RgbaUnorm);
+		assert_eq!(get_format(BlockFormat::Bc7, ColorSpace::Srgb), Format::Bc7Srgb);
+		assert_eq!(get_format(BlockFormat::Bc7, ColorSpace::Linear), Format::Bc7Unorm);
+		assert_eq!(get_format(BlockFormat::Bc5, ColorSpace::Srgb), Format::Bc5Unorm);
+		assert_eq!(get_format(BlockFormat::Bc6h, ColorSpace::Srgb), Format::Bc6hUfloat);
+	}
+
+	#[test]
+	fn float_images_are_uploaded_as_half_floats() {
+		let mut hdr = vec![];
+		HdrEncoder::new(&mut hdr).encode(&[Rgb([1.0, 2.0, 0.5]); 6], 3, 2).unwrap();
+		let image = Rgba32FImage::from_pixel(2, 1, Rgba([0.25, 1.0, 4.0, 1.0]));
+		let mut exr = Cursor::new(vec![]);
+		DynamicImage::ImageRgba32F(image).write_to(&mut exr, ImageOutputFormat::OpenExr).unwrap();
+
+		for (data, format, size) in [(hdr, TextureFormat::Hdr, (3, 2)), (exr.into_inner(), TextureFormat::OpenExr, (2, 1))] {
+			// Float images are linear, whatever color space they are loaded with
+			let texture = TextureImage::decode(data, &format, ColorSpace::Srgb).unwrap();
+			assert_eq!(texture.get_format(), Format::Rgba16Sfloat);
+			assert_eq!(texture.get_dimensions(), size);
+			assert_eq!(texture.get_texel_block().size, 8);
+			assert_eq!(texture.get_levels(false)[0].len(), (size.0 * size.1 * 8) as usize);
+		}
+	}
+}
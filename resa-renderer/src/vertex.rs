@@ -1,8 +1,11 @@
+use std::fmt;
+use std::fmt::Formatter;
 use std::mem::size_of;
 use gfx_hal::format::Format;
 use gfx_hal::pso::{AttributeDesc, Element, VertexBufferDesc, VertexInputRate};
 use glm::{Vec2, Vec3};
 
+/// Vertex with the attributes of the simple meshes, see `Mesh::new`.
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct Vertex {
@@ -10,35 +13,253 @@ pub struct Vertex {
 	pub uv: Vec2,
 }
 
-impl Vertex {
-	pub fn get_vertex_buffer_desc() -> Vec<VertexBufferDesc>{
-		let vertex_buffers = vec![VertexBufferDesc {
-			binding: 0,
-			stride: size_of::<Vertex>() as u32,
-			rate: VertexInputRate::Vertex,
+/// Per instance vertex data, read by the vertex shader from the locations 2 to 5.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct InstanceData {
+	pub model: [[f32; 4]; 4],
+}
+
+/// Binding of the instance data, the attribute streams of a mesh follow from binding 1 on.
+const INSTANCE_BINDING: u32 = 0;
+const INSTANCE_LOCATIONS: std::ops::Range<u32> = 2..6;
+
+/// The attributes a mesh can provide and a vertex shader can consume. Every attribute is read from a fixed location:
+///
+/// | attribute     | location | glsl type |
+/// |---------------|----------|-----------|
+/// | Position      | 0        | vec3      |
+/// | Uv0           | 1        | vec2      |
+/// | Normal        | 6        | vec3      |
+/// | Tangent       | 7        | vec4      |
+/// | Color         | 8        | vec4      |
+/// | Uv1           | 9        | vec2      |
+/// | JointIndices  | 10       | uvec4     |
+/// | JointWeights  | 11       | vec4      |
+///
+/// The locations 2 to 5 hold the model matrix of the instance.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum VertexAttribute {
+	Position,
+	Uv0,
+	Normal,
+	/// The w component is the handedness of the bitangent.
+	Tangent,
+	Color,
+	Uv1,
+	JointIndices,
+	JointWeights,
+}
+
+impl fmt::Display for VertexAttribute {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			VertexAttribute::Position => write!(f, "position"),
+			VertexAttribute::Uv0 => write!(f, "uv0"),
+			VertexAttribute::Normal => write!(f, "normal"),
+			VertexAttribute::Tangent => write!(f, "tangent"),
+			VertexAttribute::Color => write!(f, "color"),
+			VertexAttribute::Uv1 => write!(f, "uv1"),
+			VertexAttribute::JointIndices => write!(f, "joint indices"),
+			VertexAttribute::JointWeights => write!(f, "joint weights"),
+		}
+	}
+}
+
+impl VertexAttribute {
+	pub const ALL: [VertexAttribute; 8] = [
+		VertexAttribute::Position,
+		VertexAttribute::Uv0,
+		VertexAttribute::Normal,
+		VertexAttribute::Tangent,
+		VertexAttribute::Color,
+		VertexAttribute::Uv1,
+		VertexAttribute::JointIndices,
+		VertexAttribute::JointWeights,
+	];
+
+	pub fn get_location(&self) -> u32 {
+		match self {
+			VertexAttribute::Position => 0,
+			VertexAttribute::Uv0 => 1,
+			VertexAttribute::Normal => 6,
+			VertexAttribute::Tangent => 7,
+			VertexAttribute::Color => 8,
+			VertexAttribute::Uv1 => 9,
+			VertexAttribute::JointIndices => 10,
+			VertexAttribute::JointWeights => 11,
+		}
+	}
+
+	pub fn from_location(location: u32) -> Option<Self> {
+		VertexAttribute::ALL.iter().copied().find(|attribute| attribute.get_location() == location)
+	}
+
+	pub fn get_kind(&self) -> AttributeKind {
+		match self {
+			VertexAttribute::Uv0 | VertexAttribute::Uv1 => AttributeKind::Float2,
+			VertexAttribute::Position | VertexAttribute::Normal => AttributeKind::Float3,
+			VertexAttribute::Tangent | VertexAttribute::Color | VertexAttribute::JointWeights => AttributeKind::Float4,
+			VertexAttribute::JointIndices => AttributeKind::UShort4,
+		}
+	}
+}
+
+/// Type of the values of an attribute stream.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AttributeKind {
+	Float2,
+	Float3,
+	Float4,
+	UShort4,
+}
+
+impl AttributeKind {
+	pub(crate) fn get_format(&self) -> Format {
+		match self {
+			AttributeKind::Float2 => Format::Rg32Sfloat,
+			AttributeKind::Float3 => Format::Rgb32Sfloat,
+			AttributeKind::Float4 => Format::Rgba32Sfloat,
+			AttributeKind::UShort4 => Format::Rgba16Uint,
+		}
+	}
+
+	/// Size of one value in bytes.
+	pub(crate) fn get_size(&self) -> usize {
+		match self {
+			AttributeKind::Float2 => size_of::<[f32; 2]>(),
+			AttributeKind::Float3 => size_of::<[f32; 3]>(),
+			AttributeKind::Float4 => size_of::<[f32; 4]>(),
+			AttributeKind::UShort4 => size_of::<[u16; 4]>(),
+		}
+	}
+}
+
+/// The values of one attribute for every vertex of a mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeData {
+	Float2(Vec<[f32; 2]>),
+	Float3(Vec<[f32; 3]>),
+	Float4(Vec<[f32; 4]>),
+	UShort4(Vec<[u16; 4]>),
+}
+
+impl AttributeData {
+	pub fn get_kind(&self) -> AttributeKind {
+		match self {
+			AttributeData::Float2(_) => AttributeKind::Float2,
+			AttributeData::Float3(_) => AttributeKind::Float3,
+			AttributeData::Float4(_) => AttributeKind::Float4,
+			AttributeData::UShort4(_) => AttributeKind::UShort4,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		match self {
+			AttributeData::Float2(values) => values.len(),
+			AttributeData::Float3(values) => values.len(),
+			AttributeData::Float4(values) => values.len(),
+			AttributeData::UShort4(values) => values.len(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// The values as bits, so the float values can be hashed and compared exactly.
+	pub(crate) fn to_bits(&self) -> Vec<u32> {
+		match self {
+			AttributeData::Float2(values) => values.iter().flatten().map(|value| value.to_bits()).collect(),
+			AttributeData::Float3(values) => values.iter().flatten().map(|value| value.to_bits()).collect(),
+			AttributeData::Float4(values) => values.iter().flatten().map(|value| value.to_bits()).collect(),
+			AttributeData::UShort4(values) => values.iter().flatten().map(|value| *value as u32).collect(),
+		}
+	}
+}
+
+/// The attributes of a vertex, either provided by a mesh or consumed by a shader.
+/// Every attribute is stored in its own vertex buffer, so a mesh can be drawn by every shader
+/// which consumes a subset of its attributes.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct VertexLayout {
+	attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+	pub fn new(attributes: &[VertexAttribute]) -> Self {
+		let mut attributes = attributes.to_vec();
+		attributes.sort();
+		attributes.dedup();
+		VertexLayout { attributes }
+	}
+
+	/// The attributes the vertex shader reads, based on the locations of its inputs.
+	/// Returns the locations which do not belong to any attribute as error.
+	pub fn from_shader_locations(locations: &[u32]) -> Result<Self, Vec<u32>> {
+		let mut attributes = vec![];
+		let mut unknown_locations = vec![];
+		for location in locations.iter().filter(|location| !INSTANCE_LOCATIONS.contains(*location)) {
+			match VertexAttribute::from_location(*location) {
+				Some(attribute) => attributes.push(attribute),
+				None => unknown_locations.push(*location),
+			}
+		}
+
+		if unknown_locations.is_empty() {
+			Ok(VertexLayout::new(&attributes))
+		} else {
+			Err(unknown_locations)
+		}
+	}
+
+	pub fn get_attributes(&self) -> &[VertexAttribute] {
+		&self.attributes
+	}
+
+	pub fn contains(&self, attribute: &VertexAttribute) -> bool {
+		self.attributes.contains(attribute)
+	}
+
+	/// The attributes of this layout which the other layout does not have.
+	pub fn get_missing(&self, provided: &VertexLayout) -> Vec<VertexAttribute> {
+		self.attributes.iter().filter(|attribute| !provided.contains(attribute)).copied().collect()
+	}
+
+	/// The instance data is bound to binding 0, the attribute streams in the order of the layout from binding 1 on.
+	pub(crate) fn get_vertex_buffer_desc(&self) -> Vec<VertexBufferDesc> {
+		let mut vertex_buffers = vec![VertexBufferDesc {
+			binding: INSTANCE_BINDING,
+			stride: size_of::<InstanceData>() as u32,
+			rate: VertexInputRate::Instance(1),
 		}];
+		vertex_buffers.extend(self.attributes.iter().enumerate().map(|(index, attribute)| VertexBufferDesc {
+			binding: index as u32 + 1,
+			stride: attribute.get_kind().get_size() as u32,
+			rate: VertexInputRate::Vertex,
+		}));
 		vertex_buffers
 	}
 
-	pub fn get_vertex_attributes() -> Vec<AttributeDesc>{
-		let attributes = vec![
-			AttributeDesc {
-				location: 0,
-				binding: 0,
-				element: Element {
-					format: Format::Rgb32Sfloat,
-					offset: 0, // Zero bytes form the beginning of the vertex struct to the position value
-				},
+	pub(crate) fn get_vertex_attributes(&self) -> Vec<AttributeDesc> {
+		// A mat4 attribute takes one location per column
+		let mut attributes: Vec<AttributeDesc> = INSTANCE_LOCATIONS.map(|location| AttributeDesc {
+			location,
+			binding: INSTANCE_BINDING,
+			element: Element {
+				format: Format::Rgba32Sfloat,
+				offset: (location - INSTANCE_LOCATIONS.start) * size_of::<[f32; 4]>() as u32,
 			},
-			AttributeDesc{
-				location: 1,
-				binding: 0,
-				element: Element{
-					format: Format::Rg32Sfloat,
-					offset: size_of::<Vec3>() as u32,
-				}
+		}).collect();
+
+		attributes.extend(self.attributes.iter().enumerate().map(|(index, attribute)| AttributeDesc {
+			location: attribute.get_location(),
+			binding: index as u32 + 1,
+			element: Element {
+				format: attribute.get_kind().get_format(),
+				offset: 0,
 			},
-		];
+		}));
 		attributes
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,50 @@
+mod support;
+
+use resa_renderer::bounds::{Bounds, Frustum};
+use support::to_matrix_array;
+
+/// The camera of the example scene, five units in front of the origin with a square viewport.
+fn create_frustum() -> Frustum {
+	let view = glm::ext::look_at(glm::vec3(0.0, 0.0, 5.0), glm::vec3(0.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0));
+	let projection = glm::ext::perspective(glm::radians(45.0), 1.0, 0.1, 100.0);
+	Frustum::from_view_projection(&to_matrix_array(view), &to_matrix_array(projection))
+}
+
+#[test]
+fn boxes_inside_the_view_are_kept() {
+	let frustum = create_frustum();
+	assert!(frustum.intersects(&Bounds { min: [-0.5; 3], max: [0.5; 3] }));
+	assert!(frustum.intersects(&Bounds { min: [-0.1, -0.1, -50.0], max: [0.1, 0.1, -49.0] }));
+}
+
+#[test]
+fn boxes_outside_the_view_are_culled() {
+	let frustum = create_frustum();
+	// Right of the view, behind the camera and beyond the far plane
+	assert!(!frustum.intersects(&Bounds { min: [10.0, -0.5, -0.5], max: [11.0, 0.5, 0.5] }));
+	assert!(!frustum.intersects(&Bounds { min: [-0.5, -0.5, 6.0], max: [0.5, 0.5, 7.0] }));
+	assert!(!frustum.intersects(&Bounds { min: [-0.5, -0.5, -200.0], max: [0.5, 0.5, -199.0] }));
+}
+
+#[test]
+fn boxes_crossing_a_plane_are_kept() {
+	let frustum = create_frustum();
+	// At the origin the view reaches about 2.07 units to each side
+	assert!(frustum.intersects(&Bounds { min: [1.5, -0.5, -0.5], max: [3.0, 0.5, 0.5] }));
+	assert!(frustum.intersects(&Bounds { min: [-0.5, -3.0, -0.5], max: [0.5, -1.5, 0.5] }));
+	// Through the near plane, partly behind the camera
+	assert!(frustum.intersects(&Bounds { min: [-0.5, -0.5, 4.0], max: [0.5, 0.5, 6.0] }));
+}
+
+#[test]
+fn transformed_bounds_contain_the_rotated_box() {
+	// Quarter turn around the y axis and a shift along x
+	let matrix = [
+		[0.0, 0.0, -1.0, 0.0],
+		[0.0, 1.0, 0.0, 0.0],
+		[1.0, 0.0, 0.0, 0.0],
+		[2.0, 0.0, 0.0, 1.0],
+	];
+	let bounds = Bounds { min: [-1.0, -0.5, -0.25], max: [1.0, 0.5, 0.25] }.transform(&matrix);
+	assert_eq!(bounds, Bounds { min: [1.75, -0.5, -1.0], max: [2.25, 0.5, 1.0] });
+}
@@ -0,0 +1,261 @@
+//! Harness for the golden image tests. Scenes are rendered offscreen and compared against the
+//! reference images in tests/golden/images. Set RESA_UPDATE_GOLDEN=1 to write the rendered frames
+//! as new references, e.g. after an intended change of the output or on a new reference renderer.
+//! The tests which render are ignored by default, run them with `cargo test -- --ignored` on a machine with a gpu.
+//!
+//! The references are rendered with the vulkan backend of gfx-hal 0.9 on Linux. They have not been
+//! recorded yet, so until then every rendering test fails with the missing reference and writes its
+//! frame to the target directory. Record them from the resa-renderer directory with
+//! `RESA_UPDATE_GOLDEN=1 cargo test --test golden_images -- --ignored`, check the
+//! images by eye and name the adapter and driver which rendered them in the commit and in this comment.
+//!
+//! Every test crate which includes the harness only uses a part of it and has to include tests/support as well.
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use image::{Rgba, RgbaImage};
+use winit::dpi::PhysicalSize;
+use resa_renderer::{PresentMode, RendererConfig, ResaRenderer};
+use resa_renderer::material::{Color, Material, PipelineState, Texture};
+use resa_renderer::mesh::{Mesh, MeshSharing};
+use resa_renderer::readback::FramePixels;
+use resa_renderer::render_stage::RenderStage;
+use resa_renderer::render_view::{RenderView, ViewportRect};
+use resa_renderer::shader::ShaderRef;
+use crate::support::to_matrix_array;
+
+pub const FRAME_WIDTH: u32 = 160;
+pub const FRAME_HEIGHT: u32 = 90;
+
+/// The yiq distance of two pixels up to which they are perceived as equal, relative to the largest possible distance.
+const PIXEL_THRESHOLD: f32 = 0.1;
+/// Share of the pixels which may differ, e.g. due to rasterization differences at the edges between drivers.
+const MAX_DIFFERING_PIXELS: f32 = 0.002;
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+pub struct SceneObject {
+	pub mesh: Mesh,
+	pub material: &'static str,
+	pub position: [f32; 3],
+	pub angle: f32,
+}
+
+/// Create an offscreen renderer with the shaders of the resources directory and the materials material01 to 03.
+/// The tests which render need a gpu (or software vulkan), so they are ignored by default and fail without one.
+pub fn create_renderer() -> ResaRenderer {
+	let config = RendererConfig {
+		extent: PhysicalSize::new(FRAME_WIDTH, FRAME_HEIGHT),
+		shaders: load_shaders(),
+		present_mode: PresentMode::Fifo,
+	};
+
+	let mut renderer = ResaRenderer::new_offscreen(config).unwrap_or_else(|e| panic!("Could not create the offscreen renderer: {}", e));
+	renderer.register_materials(&create_materials());
+	renderer
+}
+
+/// Render the objects with the camera of the example scene and read the frame back.
+pub fn render_scene(renderer: &mut ResaRenderer, objects: Vec<SceneObject>) -> FramePixels {
+	render_scene_in_viewports(renderer, objects, &[(ViewportRect::FULL, None)])
+}
+
+/// Render the objects once per viewport with the camera of the example scene, each with the aspect ratio of
+/// its viewport and the given clear color, and read the frame back.
+pub fn render_scene_in_viewports(renderer: &mut ResaRenderer, objects: Vec<SceneObject>, viewports: &[(ViewportRect, Option<[f32; 4]>)]) -> FramePixels {
+	// The meshes are freed with their handles, after the frame was drawn
+	let mut mesh_handles = vec![];
+	let mut registered_objects = vec![];
+	for object in objects {
+		let mesh_handle = renderer.register_mesh(object.mesh, MeshSharing::Shared);
+		registered_objects.push(RegisteredObject { mesh_id: mesh_handle.get_id(), material: object.material, position: object.position, angle: object.angle });
+		mesh_handles.push(mesh_handle);
+	}
+	render_registered_in_viewports(renderer, &registered_objects, viewports)
+}
+
+/// A mesh which was registered by the test, e.g. to update it between frames.
+pub struct RegisteredObject {
+	pub mesh_id: u64,
+	pub material: &'static str,
+	pub position: [f32; 3],
+	pub angle: f32,
+}
+
+/// Render registered meshes with the camera of the example scene and read the frame back.
+pub fn render_registered(renderer: &mut ResaRenderer, objects: &[RegisteredObject]) -> FramePixels {
+	render_registered_in_viewports(renderer, objects, &[(ViewportRect::FULL, None)])
+}
+
+fn render_registered_in_viewports(renderer: &mut ResaRenderer, objects: &[RegisteredObject], viewports: &[(ViewportRect, Option<[f32; 4]>)]) -> FramePixels {
+	let render_objects: Vec<_> = objects.iter().map(|object| {
+		let material = renderer.get_material_ref_from_name(object.material).unwrap();
+		(object.mesh_id, material, make_transform_matrix(object.position, object.angle))
+	}).collect();
+
+	let view = glm::ext::look_at(glm::vec3(0.0, 0.0, 5.0), glm::vec3(0.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0));
+	let views: Vec<RenderView> = viewports.iter().map(|(viewport, clear_color)| {
+		let aspect_ratio = viewport.get_aspect_ratio(FRAME_WIDTH, FRAME_HEIGHT);
+		let projection = glm::ext::perspective(glm::radians(45.0), aspect_ratio, 0.1, 100.0);
+		RenderView {
+			view_mat: to_matrix_array(view),
+			projection_mat: to_matrix_array(projection),
+			viewport: *viewport,
+			clear_color: *clear_color,
+			render_objects: render_objects.clone(),
+		}
+	}).collect();
+	renderer.render(&views);
+	renderer.read_pixels().unwrap()
+}
+
+/// Compare the frame with the reference image of the given name. On a mismatch the frame and
+/// an image highlighting the differing pixels are written to the target directory.
+pub fn assert_matches_golden(name: &str, frame: &FramePixels) {
+	let actual = RgbaImage::from_raw(frame.width, frame.height, frame.data.clone()).unwrap();
+	let reference_path = get_golden_dir().join(format!("{}.png", name));
+
+	if env::var("RESA_UPDATE_GOLDEN").map_or(false, |value| value == "1") {
+		fs::create_dir_all(get_golden_dir()).unwrap();
+		actual.save(&reference_path).unwrap();
+		return;
+	}
+
+	let output_dir = get_output_dir();
+	fs::create_dir_all(&output_dir).unwrap();
+	let actual_path = output_dir.join(format!("{}.png", name));
+
+	let expected = match image::open(&reference_path) {
+		Ok(image) => image.to_rgba8(),
+		Err(e) => {
+			actual.save(&actual_path).unwrap();
+			panic!(
+				"Could not read reference image {}: {}. The rendered frame was written to {}, run with RESA_UPDATE_GOLDEN=1 to accept it.",
+				reference_path.display(), e, actual_path.display()
+			);
+		}
+	};
+
+	let comparison = compare_images(&expected, &actual);
+	let allowed_pixels = (MAX_DIFFERING_PIXELS * (frame.width * frame.height) as f32) as u32;
+	if comparison.differing_pixels > allowed_pixels {
+		let diff_path = output_dir.join(format!("{}.diff.png", name));
+		actual.save(&actual_path).unwrap();
+		comparison.diff_image.save(&diff_path).unwrap();
+		panic!(
+			"{} of {} pixels differ from {}, only {} are allowed. See {} and {}",
+			comparison.differing_pixels, frame.width * frame.height, reference_path.display(), allowed_pixels,
+			actual_path.display(), diff_path.display()
+		);
+	}
+}
+
+pub struct Comparison {
+	pub differing_pixels: u32,
+	/// The expected image faded to grey with the differing pixels in red.
+	pub diff_image: RgbaImage,
+}
+
+/// Compare two images pixel by pixel with the perceptual yiq color distance.
+/// Images of different sizes count all pixels as differing.
+pub fn compare_images(expected: &RgbaImage, actual: &RgbaImage) -> Comparison {
+	if expected.dimensions() != actual.dimensions() {
+		let (width, height) = actual.dimensions();
+		return Comparison {
+			differing_pixels: width * height,
+			diff_image: RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255])),
+		};
+	}
+
+	let max_delta = PIXEL_THRESHOLD * PIXEL_THRESHOLD * MAX_YIQ_DELTA;
+	let mut differing_pixels = 0;
+	let diff_image = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+		let expected_pixel = expected.get_pixel(x, y);
+		if get_yiq_delta(expected_pixel, actual.get_pixel(x, y)) > max_delta {
+			differing_pixels += 1;
+			Rgba([255, 0, 0, 255])
+		} else {
+			let grey = (255.0 - (255.0 - get_brightness(expected_pixel)) * 0.1) as u8;
+			Rgba([grey, grey, grey, 255])
+		}
+	});
+
+	Comparison {
+		differing_pixels,
+		diff_image,
+	}
+}
+
+/// Squared distance of two colors in the yiq color space, which weights the brightness the most,
+/// as described in "Measuring perceived color difference using YIQ NTSC transmission color space" by Kotsarenko and Ramos.
+fn get_yiq_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+	let [ay, ai, aq] = to_yiq(&blend_on_white(a));
+	let [by, bi, bq] = to_yiq(&blend_on_white(b));
+	let (y, i, q) = (ay - by, ai - bi, aq - bq);
+	0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+fn blend_on_white(pixel: &Rgba<u8>) -> [f32; 3] {
+	let alpha = pixel[3] as f32 / 255.0;
+	[0, 1, 2].map(|channel| 255.0 + (pixel[channel] as f32 - 255.0) * alpha)
+}
+
+fn to_yiq([r, g, b]: &[f32; 3]) -> [f32; 3] {
+	[
+		r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+		r * 0.59597799 - g * 0.27417610 - b * 0.32180189,
+		r * 0.21147017 - g * 0.52261711 + b * 0.31114694,
+	]
+}
+
+fn get_brightness(pixel: &Rgba<u8>) -> f32 {
+	to_yiq(&blend_on_white(pixel))[0]
+}
+
+fn get_golden_dir() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join("images")
+}
+
+fn get_output_dir() -> PathBuf {
+	Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+fn load_shaders() -> Vec<ShaderRef> {
+	let shader_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("resources").join("shaders");
+	let vertex = fs::read_to_string(shader_dir.join("base.vert")).unwrap();
+	let fragment = fs::read_to_string(shader_dir.join("base.frag")).unwrap();
+	let shader = ShaderRef::new("base".to_string(), &[("vert".to_string(), vertex), ("frag".to_string(), fragment)]);
+	vec![shader.expect("Could not compile the base shader")]
+}
+
+/// The materials of resources/materials, the tests must not change when the example materials are edited.
+fn create_materials() -> Vec<Material> {
+	vec![
+		create_material("material01", RenderStage::Transparent, Color::new(125, 125, 125, 150)),
+		create_material("material02", RenderStage::Opaque, Color::new(0, 0, 0, 255)),
+		create_material("material03", RenderStage::Opaque, Color::new(255, 255, 255, 255)),
+	]
+}
+
+fn create_material(name: &str, render_stage: RenderStage, color: Color) -> Material {
+	Material {
+		name: name.to_string(),
+		shader_id: 0,
+		render_stage,
+		pipeline_state: PipelineState::default(),
+		color,
+		texture: Texture::None,
+	}
+}
+
+fn make_transform_matrix(position: [f32; 3], angle: f32) -> [[f32; 4]; 4] {
+	let (s, c) = angle.sin_cos();
+	let [dx, dy, dz] = position;
+	[
+		[c, 0.0, s, 0.0],
+		[0.0, 1.0, 0.0, 0.0],
+		[-s, 0.0, c, 0.0],
+		[dx, dy, dz, 1.0],
+	]
+}
@@ -0,0 +1,107 @@
+mod golden;
+mod support;
+
+use image::{Rgba, RgbaImage};
+use resa_renderer::mesh::{create_primitive_cube, create_primitive_quad, create_primitive_triangle, Mesh, Topology};
+use resa_renderer::render_view::ViewportRect;
+use resa_renderer::vertex::{AttributeData, VertexAttribute};
+use golden::{assert_matches_golden, compare_images, create_renderer, render_scene, render_scene_in_viewports, SceneObject};
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn opaque_triangle() {
+	let mut renderer = create_renderer();
+
+	let objects = vec![SceneObject { mesh: create_primitive_triangle(), material: "material02", position: [0.0, 0.0, 0.0], angle: 0.0 }];
+	let frame = render_scene(&mut renderer, objects);
+	assert_matches_golden("opaque_triangle", &frame);
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn transparent_triangle_over_quad() {
+	let mut renderer = create_renderer();
+
+	let objects = vec![
+		SceneObject { mesh: create_primitive_quad(), material: "material03", position: [0.0, 0.0, -0.5], angle: 0.0 },
+		SceneObject { mesh: create_primitive_triangle(), material: "material01", position: [0.2, 0.0, 0.0], angle: 0.0 },
+	];
+	let frame = render_scene(&mut renderer, objects);
+	assert_matches_golden("transparent_triangle_over_quad", &frame);
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn rotated_cube() {
+	let mut renderer = create_renderer();
+
+	let objects = vec![SceneObject { mesh: create_primitive_cube(), material: "material03", position: [0.0, 0.0, 0.0], angle: 0.6 }];
+	let frame = render_scene(&mut renderer, objects);
+	assert_matches_golden("rotated_cube", &frame);
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn example_scene() {
+	let mut renderer = create_renderer();
+
+	let objects = vec![
+		SceneObject { mesh: create_primitive_triangle(), material: "material01", position: [0.0, 0.0, 0.0], angle: 0.0 },
+		SceneObject { mesh: create_primitive_triangle(), material: "material02", position: [-0.2, 0.0, -1.0], angle: 0.0 },
+		SceneObject { mesh: create_primitive_quad(), material: "material03", position: [0.8, 0.2, 0.0], angle: 0.0 },
+		SceneObject { mesh: create_primitive_cube(), material: "material03", position: [-1.1, 1.0, 0.2], angle: 0.3 },
+	];
+	let frame = render_scene(&mut renderer, objects);
+	assert_matches_golden("example_scene", &frame);
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn split_screen_with_picture_in_picture() {
+	let mut renderer = create_renderer();
+
+	let objects = vec![
+		SceneObject { mesh: create_primitive_quad(), material: "material03", position: [0.0, 0.0, -0.5], angle: 0.0 },
+		SceneObject { mesh: create_primitive_triangle(), material: "material02", position: [0.2, 0.0, 0.0], angle: 0.0 },
+	];
+	// The left half keeps the background, the inset is drawn over the right half
+	let viewports = [
+		(ViewportRect::new(0.0, 0.0, 0.5, 1.0), None),
+		(ViewportRect::new(0.5, 0.0, 0.5, 1.0), Some([0.2, 0.3, 0.5, 1.0])),
+		(ViewportRect::new(0.75, 0.0, 0.25, 0.4), Some([0.0, 0.0, 0.0, 1.0])),
+	];
+	let frame = render_scene_in_viewports(&mut renderer, objects, &viewports);
+	assert_matches_golden("split_screen_with_picture_in_picture", &frame);
+}
+
+#[test]
+fn comparison_tolerates_small_color_differences() {
+	let expected = RgbaImage::from_pixel(4, 4, Rgba([120, 120, 120, 255]));
+	let actual = RgbaImage::from_pixel(4, 4, Rgba([122, 121, 120, 255]));
+	assert_eq!(compare_images(&expected, &actual).differing_pixels, 0);
+}
+
+#[test]
+fn comparison_detects_changed_pixels() {
+	let expected = RgbaImage::from_pixel(4, 4, Rgba([255, 255, 255, 255]));
+	let mut actual = expected.clone();
+	actual.put_pixel(1, 2, Rgba([0, 0, 0, 255]));
+
+	let comparison = compare_images(&expected, &actual);
+	assert_eq!(comparison.differing_pixels, 1);
+	assert_eq!(comparison.diff_image.get_pixel(1, 2), &Rgba([255, 0, 0, 255]));
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn non_indexed_line_strip() {
+	let mut renderer = create_renderer();
+
+	let mut zigzag = Mesh::non_indexed(vec![[-1.5, -0.5, 0.0], [-0.5, 0.5, 0.0], [0.5, -0.5, 0.0], [1.5, 0.5, 0.0]]);
+	zigzag.set_topology(Topology::LineStrip);
+	// The base shader reads the uvs
+	zigzag.set_attribute(VertexAttribute::Uv0, AttributeData::Float2(vec![[0.0, 0.0]; 4])).unwrap();
+	let objects = vec![SceneObject { mesh: zigzag, material: "material02", position: [0.0, 0.0, 0.0], angle: 0.0 }];
+	let frame = render_scene(&mut renderer, objects);
+	assert_matches_golden("non_indexed_line_strip", &frame);
+}
@@ -0,0 +1,72 @@
+mod golden;
+mod support;
+
+use resa_renderer::material::{Color, Material, PipelineState, SamplerSettings, Texture, TextureFormat};
+use resa_renderer::render_stage::RenderStage;
+use golden::create_renderer;
+
+fn create_textured_material(name: &str, texture: Texture) -> Material {
+	Material {
+		name: name.to_string(),
+		shader_id: 0,
+		render_stage: RenderStage::Opaque,
+		pipeline_state: PipelineState::default(),
+		color: Color::new(255, 255, 255, 255),
+		texture,
+	}
+}
+
+fn create_pixel(color: [u8; 4]) -> (Vec<u8>, TextureFormat) {
+	(color.to_vec(), TextureFormat::Custom((1, 1)))
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn unregistered_textures_are_drawn_as_missing_texture() {
+	let mut renderer = create_renderer();
+
+	let material_refs = renderer.register_materials(&[create_textured_material("unregistered", Texture::Named("later".to_string()))]);
+	assert_eq!(renderer.get_texture_ref(&material_refs[0]), Some(renderer.get_missing_texture_ref()));
+
+	// Registering the texture replaces the missing texture for the material which waits for it
+	let (data, format) = create_pixel([255, 0, 0, 255]);
+	renderer.register_texture("later", data, format, SamplerSettings::default());
+	assert_ne!(renderer.get_texture_ref(&material_refs[0]), Some(renderer.get_missing_texture_ref()));
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn materials_share_textures_by_name() {
+	let mut renderer = create_renderer();
+
+	let (data, format) = create_pixel([255, 0, 0, 255]);
+	renderer.register_texture("shared", data, format, SamplerSettings::default());
+	let material_refs = renderer.register_materials(&[
+		create_textured_material("first", Texture::Named("shared".to_string())),
+		create_textured_material("second", Texture::Named("shared".to_string())),
+	]);
+	let shared = renderer.get_texture_ref(&material_refs[0]);
+	assert!(shared.is_some());
+	assert_eq!(renderer.get_texture_ref(&material_refs[1]), shared);
+	assert_ne!(shared, Some(renderer.get_missing_texture_ref()));
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn own_textures_are_released_when_the_material_uses_another_one() {
+	let mut renderer = create_renderer();
+
+	let (data, format) = create_pixel([0, 255, 0, 255]);
+	let material_refs = renderer.register_materials(&[create_textured_material("own", Texture::Pending(data, format, SamplerSettings::default()))]);
+	let own = renderer.get_texture_ref(&material_refs[0]).unwrap();
+	assert_ne!(own, renderer.get_missing_texture_ref());
+
+	let (data, format) = create_pixel([255, 0, 0, 255]);
+	renderer.register_texture("shared", data, format, SamplerSettings::default());
+	renderer.update_material(&material_refs[0], create_textured_material("own", Texture::Named("shared".to_string())));
+	assert_ne!(renderer.get_texture_ref(&material_refs[0]), Some(own));
+
+	// The image of the released texture is freed, a reference to it is drawn as missing texture
+	renderer.update_material(&material_refs[0], create_textured_material("own", Texture::Some(own)));
+	assert_eq!(renderer.get_texture_ref(&material_refs[0]), Some(renderer.get_missing_texture_ref()));
+}
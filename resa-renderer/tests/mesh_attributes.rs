@@ -0,0 +1,95 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use resa_renderer::error::RenderError;
+use resa_renderer::mesh::{create_primitive_triangle, IndexFormat, Mesh, Topology};
+use resa_renderer::vertex::{AttributeData, AttributeKind, VertexAttribute, VertexLayout};
+
+#[test]
+fn layout_follows_attribute_streams() {
+	let mut mesh = create_primitive_triangle();
+	assert_eq!(mesh.get_layout(), VertexLayout::new(&[VertexAttribute::Position, VertexAttribute::Uv0]));
+
+	mesh.set_attribute(VertexAttribute::Normal, AttributeData::Float3(vec![[0.0, 0.0, 1.0]; 3])).unwrap();
+	mesh.set_attribute(VertexAttribute::Color, AttributeData::Float4(vec![[1.0; 4]; 3])).unwrap();
+	assert_eq!(mesh.get_layout().get_attributes(), &[VertexAttribute::Position, VertexAttribute::Uv0, VertexAttribute::Normal, VertexAttribute::Color]);
+
+	mesh.remove_attribute(VertexAttribute::Uv0);
+	assert!(!mesh.get_layout().contains(&VertexAttribute::Uv0));
+}
+
+#[test]
+fn attributes_need_one_value_of_their_kind_per_vertex() {
+	let mut mesh = Mesh::from_positions(vec![[0.0; 3]; 4], vec![0, 1, 2, 0, 2, 3]);
+
+	let result = mesh.set_attribute(VertexAttribute::Normal, AttributeData::Float4(vec![[0.0; 4]; 4]));
+	assert_eq!(result, Err(RenderError::AttributeKindMismatch { attribute: VertexAttribute::Normal, expected: AttributeKind::Float3, actual: AttributeKind::Float4 }));
+
+	let result = mesh.set_attribute(VertexAttribute::JointIndices, AttributeData::UShort4(vec![[0; 4]; 3]));
+	assert_eq!(result, Err(RenderError::AttributeLengthMismatch { attribute: VertexAttribute::JointIndices, expected: 4, actual: 3 }));
+	assert_eq!(mesh.get_attribute(VertexAttribute::JointIndices), None);
+}
+
+#[test]
+fn shader_layout_skips_instance_locations() {
+	let layout = VertexLayout::from_shader_locations(&[0, 1, 2, 6]).unwrap();
+	assert_eq!(layout.get_attributes(), &[VertexAttribute::Position, VertexAttribute::Uv0, VertexAttribute::Normal]);
+
+	let mesh_layout = VertexLayout::new(&[VertexAttribute::Position, VertexAttribute::Uv0]);
+	assert_eq!(layout.get_missing(&mesh_layout), vec![VertexAttribute::Normal]);
+
+	assert_eq!(VertexLayout::from_shader_locations(&[0, 12]), Err(vec![12]));
+}
+
+#[test]
+fn non_indexed_meshes_draw_every_vertex() {
+	let mut mesh = Mesh::non_indexed(vec![[0.0; 3]; 5]);
+	mesh.set_topology(Topology::LineStrip);
+	assert_eq!(mesh.get_indices(), None);
+	assert_eq!(mesh.get_draw_count(), 5);
+
+	mesh.set_indices(Some(vec![0, 1, 70_000]));
+	assert_eq!(mesh.get_draw_count(), 3);
+	assert_eq!(mesh.get_topology(), Topology::LineStrip);
+}
+
+#[test]
+fn indices_above_u16_pick_u32_indices() {
+	let mut mesh = Mesh::non_indexed(vec![[0.0; 3]; 70_000]);
+	assert_eq!(mesh.get_index_format(), None);
+
+	mesh.set_indices(Some(vec![0, 1, u16::MAX as u32]));
+	assert_eq!(mesh.get_index_format(), Some(IndexFormat::U16));
+
+	mesh.set_indices(Some(vec![0, 1, u16::MAX as u32 + 1]));
+	assert_eq!(mesh.get_index_format(), Some(IndexFormat::U32));
+}
+
+fn get_hash(mesh: &Mesh) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	mesh.hash(&mut hasher);
+	hasher.finish()
+}
+
+#[test]
+fn meshes_are_compared_by_content() {
+	let triangle = create_primitive_triangle();
+	let mut moved = create_primitive_triangle();
+	moved.set_attribute(VertexAttribute::Position, AttributeData::Float3(vec![[0.0; 3], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]])).unwrap();
+	assert!(!triangle.has_same_content(&moved));
+	assert_ne!(get_hash(&triangle), get_hash(&moved));
+
+	// The order the attributes were set in does not change the content
+	let normals = AttributeData::Float3(vec![[0.0, 0.0, 1.0]; 3]);
+	let colors = AttributeData::Float4(vec![[1.0; 4]; 3]);
+	let mut a = Mesh::from_positions(vec![[0.0; 3]; 3], vec![0, 1, 2]);
+	a.set_attribute(VertexAttribute::Normal, normals.clone()).unwrap();
+	a.set_attribute(VertexAttribute::Color, colors.clone()).unwrap();
+	let mut b = Mesh::from_positions(vec![[0.0; 3]; 3], vec![0, 1, 2]);
+	b.set_attribute(VertexAttribute::Color, colors).unwrap();
+	b.set_attribute(VertexAttribute::Normal, normals).unwrap();
+	assert!(a.has_same_content(&b));
+	assert_eq!(get_hash(&a), get_hash(&b));
+
+	b.set_topology(Topology::PointList);
+	assert!(!a.has_same_content(&b));
+}
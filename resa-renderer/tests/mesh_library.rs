@@ -0,0 +1,108 @@
+mod golden;
+mod support;
+
+use resa_renderer::mesh::{create_primitive_cube, create_primitive_quad, create_primitive_triangle, IndexFormat, Mesh, MeshSharing};
+use resa_renderer::vertex::{AttributeData, VertexAttribute};
+use resa_renderer::readback::FramePixels;
+use golden::{create_renderer, render_registered, RegisteredObject, FRAME_HEIGHT, FRAME_WIDTH};
+
+const CENTER: (u32, u32) = (FRAME_WIDTH / 2, FRAME_HEIGHT / 2);
+/// The color of material02, which the meshes are drawn with
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+/// A pixel above the center which the quad covers, but the triangle does not
+const QUAD_ONLY: (u32, u32) = (FRAME_WIDTH / 2 + 10, FRAME_HEIGHT / 2 - 9);
+
+fn draw_at_origin(mesh_id: u64) -> RegisteredObject {
+	RegisteredObject { mesh_id, material: "material02", position: [0.0; 3], angle: 0.0 }
+}
+
+/// The column of the right edge of a cube at the origin, whose front face is closest to the camera at z = 5.
+fn get_cube_edge_column(half_extent: f32) -> u32 {
+	let half_width = (5.0 - half_extent) * 22.5f32.to_radians().tan() * FRAME_WIDTH as f32 / FRAME_HEIGHT as f32;
+	(FRAME_WIDTH as f32 / 2.0 * (1.0 + half_extent / half_width)) as u32
+}
+
+fn is_drawn(frame: &FramePixels, (x, y): (u32, u32)) -> bool {
+	frame.get_pixel(x, y) == BLACK
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn meshes_with_more_vertices_than_u16_indices_are_drawn() {
+	let mut renderer = create_renderer();
+
+	// Only the last three vertices form a visible triangle, indices cut to u16 would point at the collapsed ones
+	let mut positions = vec![[0.0; 3]; 70_000];
+	positions[69_997..].copy_from_slice(&[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]]);
+	let mut mesh = Mesh::from_positions(positions, vec![0, 1, 2, 69_997, 69_998, 69_999]);
+	mesh.set_attribute(VertexAttribute::Uv0, AttributeData::Float2(vec![[0.0; 2]; 70_000])).unwrap();
+	assert_eq!(mesh.get_index_format(), Some(IndexFormat::U32));
+
+	let mesh_handle = renderer.register_mesh(mesh, MeshSharing::Unique);
+	let frame = render_registered(&mut renderer, &[draw_at_origin(mesh_handle.get_id())]);
+	assert_eq!(frame.get_pixel(CENTER.0, CENTER.1), BLACK);
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn shared_meshes_are_freed_with_their_last_handle() {
+	let mut renderer = create_renderer();
+
+	let shared = renderer.register_mesh(create_primitive_triangle(), MeshSharing::Shared);
+	let same_content = renderer.register_mesh(create_primitive_triangle(), MeshSharing::Shared);
+	let unique = renderer.register_mesh(create_primitive_triangle(), MeshSharing::Unique);
+	let other_content = renderer.register_mesh(create_primitive_quad(), MeshSharing::Shared);
+	assert_eq!(shared, same_content);
+	assert_ne!(shared.get_id(), unique.get_id());
+	assert_ne!(shared.get_id(), other_content.get_id());
+
+	// The shared mesh keeps the triangle, the quad did not replace it
+	let frame = render_registered(&mut renderer, &[draw_at_origin(shared.get_id())]);
+	assert!(is_drawn(&frame, CENTER) && !is_drawn(&frame, QUAD_ONLY));
+	let frame = render_registered(&mut renderer, &[draw_at_origin(other_content.get_id())]);
+	assert!(is_drawn(&frame, QUAD_ONLY));
+
+	// Released meshes are freed before the next frame is drawn, the mesh is still drawn for the remaining handle
+	let mesh_id = shared.get_id();
+	drop(shared);
+	let frame = render_registered(&mut renderer, &[draw_at_origin(mesh_id)]);
+	assert!(renderer.get_mesh_bounds(&mesh_id).is_some());
+	assert!(is_drawn(&frame, CENTER));
+
+	drop(same_content);
+	let frame = render_registered(&mut renderer, &[draw_at_origin(unique.get_id())]);
+	assert!(renderer.get_mesh_bounds(&mesh_id).is_none());
+	assert!(renderer.get_mesh_bounds(&unique.get_id()).is_some());
+	assert!(is_drawn(&frame, CENTER));
+}
+
+#[test]
+#[ignore = "renders on the gpu"]
+fn updated_meshes_outgrow_their_buffers() {
+	let mut renderer = create_renderer();
+
+	let dynamic = renderer.register_dynamic_mesh(create_primitive_triangle());
+	let static_mesh = renderer.register_mesh(create_primitive_triangle(), MeshSharing::Unique);
+	// Several updates per frame and meshes which need bigger buffers than the last one
+	for size in 1..5 {
+		let scale = size as f32;
+		let mut cube = create_primitive_cube();
+		let positions = cube.get_positions().iter().map(|position| position.map(|value| value * scale)).collect();
+		cube.set_attribute(VertexAttribute::Position, AttributeData::Float3(positions)).unwrap();
+		renderer.update_mesh(&dynamic, create_primitive_quad()).unwrap();
+		renderer.update_mesh(&dynamic, cube).unwrap();
+		renderer.update_mesh(&static_mesh, create_primitive_cube()).unwrap();
+
+		// Only the last update is drawn, the quad would reach beyond the smallest cube
+		let frame = render_registered(&mut renderer, &[draw_at_origin(dynamic.get_id())]);
+		let edge = get_cube_edge_column(0.5 * scale);
+		assert!(is_drawn(&frame, (edge - 2, CENTER.1)), "cube of size {} is cut off", size);
+		assert!(!is_drawn(&frame, (edge + 2, CENTER.1)), "cube of size {} is too large", size);
+		let bounds = renderer.get_mesh_bounds(&dynamic.get_id()).unwrap();
+		assert_eq!(bounds.max, [0.5 * scale; 3]);
+
+		// The cube covers the pixel, the triangle it replaced does not
+		let frame = render_registered(&mut renderer, &[draw_at_origin(static_mesh.get_id())]);
+		assert!(is_drawn(&frame, QUAD_ONLY));
+	}
+}
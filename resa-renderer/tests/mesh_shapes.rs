@@ -0,0 +1,84 @@
+use resa_renderer::error::RenderError;
+use resa_renderer::mesh_builder::MeshBuilder;
+use resa_renderer::shapes;
+
+/// Every triangle is counter clockwise seen from the side its vertex normals point to.
+fn assert_faces_outwards(builder: &MeshBuilder) {
+	let positions = builder.get_positions();
+	let normals = builder.get_normals();
+	for triangle in builder.get_indices().chunks_exact(3) {
+		let [a, b, c] = [0, 1, 2].map(|corner| positions[triangle[corner] as usize]);
+		let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+		let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+		let face_normal = [ab[1] * ac[2] - ab[2] * ac[1], ab[2] * ac[0] - ab[0] * ac[2], ab[0] * ac[1] - ab[1] * ac[0]];
+		let vertex_normals: f32 = triangle.iter().map(|index| {
+			let normal = normals[*index as usize];
+			normal[0] * face_normal[0] + normal[1] * face_normal[1] + normal[2] * face_normal[2]
+		}).sum();
+		assert!(vertex_normals > 0.0, "triangle {:?} faces inwards", triangle);
+	}
+}
+
+#[test]
+fn shapes_face_outwards_with_unit_normals() {
+	let heights: Vec<f32> = (0..16).map(|sample| (sample as f32 * 0.7).sin()).collect();
+	let builders = [
+		shapes::cuboid([1.0, 2.0, 3.0]),
+		shapes::uv_sphere(1.0, 16, 8),
+		shapes::icosphere(1.0, 2),
+		shapes::cylinder(1.0, 2.0, 12),
+		shapes::cone(1.0, 2.0, 12),
+		shapes::capsule(0.5, 2.0, 12, 4),
+		shapes::torus(2.0, 0.5, 24, 12),
+		shapes::plane(2.0, 2.0, 4, 4),
+		shapes::terrain(&heights, 4, 4, [4.0, 1.0, 4.0]).unwrap(),
+	];
+
+	for builder in builders.iter() {
+		assert_faces_outwards(builder);
+		for normal in builder.get_normals() {
+			let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+			assert!((length - 1.0).abs() < 1e-4);
+		}
+	}
+}
+
+#[test]
+fn uv_sphere_duplicates_the_seam() {
+	let sphere = shapes::uv_sphere(1.0, 8, 4);
+	assert_eq!(sphere.get_vertex_count(), 9 * 5);
+	// The triangles at the poles without area are left out
+	assert_eq!(sphere.get_indices().len(), (8 * 4 * 2 - 2 * 8) * 3);
+	assert!(sphere.get_uvs().iter().all(|uv| (0.0..=1.0).contains(&uv[0]) && (0.0..=1.0).contains(&uv[1])));
+}
+
+#[test]
+fn merged_and_mirrored_builders_keep_their_faces() {
+	let mut builder = shapes::cuboid([1.0; 3]);
+	builder.translate([2.0, 0.0, 0.0]);
+	builder.merge(&shapes::cone(1.0, 1.0, 8));
+	assert_eq!(builder.get_vertex_count(), 24 + 18 + 10);
+	assert!(builder.get_indices()[36..].iter().all(|index| *index >= 24));
+
+	// Mirroring turns the triangles, so they still face outwards
+	builder.scale([-1.0, 1.0, 1.0]);
+	assert_faces_outwards(&builder);
+	assert_eq!(builder.get_positions()[0][0], -1.5);
+}
+
+#[test]
+fn terrain_needs_a_height_per_sample() {
+	let result = shapes::terrain(&[0.0; 5], 3, 2, [1.0; 3]);
+	assert_eq!(result, Err(RenderError::InvalidHeightmap { columns: 3, rows: 2, samples: 5 }));
+
+	// 65536 * 65537 wraps around to 65536 in u32
+	let heights = vec![0.0; 65536];
+	let error = shapes::terrain(&heights, 65536, 65537, [1.0; 3]).unwrap_err();
+	assert_eq!(error, RenderError::InvalidHeightmap { columns: 65536, rows: 65537, samples: 65536 });
+	assert!(error.to_string().contains("4295032832 heights"));
+
+	let terrain = shapes::terrain(&[0.0, 0.0, 1.0, 1.0], 2, 2, [2.0, 1.0, 2.0]).unwrap();
+	assert_eq!(terrain.get_positions(), &[[-1.0, 0.0, -1.0], [1.0, 0.0, -1.0], [-1.0, 1.0, 1.0], [1.0, 1.0, 1.0]]);
+	let normal = terrain.get_normals()[0];
+	assert!(normal[1] > 0.0 && normal[2] < 0.0);
+}
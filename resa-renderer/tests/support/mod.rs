@@ -0,0 +1,9 @@
+//! Helpers shared by the integration tests. Every test crate which includes them only uses a part of them.
+#![allow(dead_code)]
+
+use glm::Matrix4;
+
+/// The columns of a glm matrix in the layout of the renderer.
+pub fn to_matrix_array(mat: Matrix4<f32>) -> [[f32; 4]; 4] {
+	[[mat.c0.x, mat.c0.y, mat.c0.z, mat.c0.w], [mat.c1.x, mat.c1.y, mat.c1.z, mat.c1.w], [mat.c2.x, mat.c2.y, mat.c2.z, mat.c2.w], [mat.c3.x, mat.c3.y, mat.c3.z, mat.c3.w]]
+}
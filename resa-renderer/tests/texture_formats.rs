@@ -0,0 +1,88 @@
+use resa_renderer::material::TextureFormat;
+use resa_renderer::texture_container::{parse_dds, parse_ktx2, BlockFormat};
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+
+/// KTX2 file with the levels of a 2d texture, the data format descriptor and key values are left out.
+fn create_ktx2(vk_format: u32, width: u32, height: u32, levels: &[Vec<u8>]) -> Vec<u8> {
+	let mut data = KTX2_IDENTIFIER.to_vec();
+	for value in [vk_format, 1, width, height, 0, 0, 1, levels.len() as u32, 0, 0, 0, 0, 0] {
+		data.extend(value.to_le_bytes());
+	}
+	data.extend([0u8; 16]);
+	let mut offset = (data.len() + levels.len() * 24) as u64;
+	for level in levels {
+		for value in [offset, level.len() as u64, level.len() as u64] {
+			data.extend(value.to_le_bytes());
+		}
+		offset += level.len() as u64;
+	}
+	for level in levels {
+		data.extend(level);
+	}
+	data
+}
+
+fn create_dds(four_cc: &[u8; 4], width: u32, height: u32, level_count: u32, dxgi_format: Option<u32>, level_data: &[u8]) -> Vec<u8> {
+	let mut header = [0u32; 31];
+	header[0] = 124;
+	header[1] = 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000;
+	header[2] = height;
+	header[3] = width;
+	header[6] = level_count;
+	header[18] = 32;
+	header[19] = 0x4;
+	header[20] = u32::from_le_bytes(*four_cc);
+
+	let mut data = b"DDS ".to_vec();
+	for value in header {
+		data.extend(value.to_le_bytes());
+	}
+	if let Some(dxgi_format) = dxgi_format {
+		for value in [dxgi_format, 3, 0, 1, 0] {
+			data.extend(value.to_le_bytes());
+		}
+	}
+	data.extend(level_data);
+	data
+}
+
+#[test]
+fn ktx2_levels_are_read_as_they_are() {
+	// Bc7 srgb of 8x4 pixels, two blocks and one block for the smaller levels
+	let levels = vec![vec![1u8; 32], vec![2u8; 16], vec![3u8; 16], vec![4u8; 16]];
+	let texture = parse_ktx2(&create_ktx2(146, 8, 4, &levels)).unwrap();
+	assert_eq!(texture.format, BlockFormat::Bc7);
+	assert_eq!((texture.width, texture.height), (8, 4));
+	assert_eq!(texture.levels, levels);
+
+	// Uncompressed formats are decoded through the image formats instead
+	assert!(parse_ktx2(&create_ktx2(43, 8, 4, &levels)).is_err());
+	let truncated = create_ktx2(146, 8, 4, &levels[..1]);
+	assert!(parse_ktx2(&truncated[..truncated.len() - 1]).is_err());
+	assert!(parse_ktx2(&create_ktx2(146, 8, 4, &[vec![0u8; 16]])).is_err());
+}
+
+#[test]
+fn dds_levels_follow_each_other() {
+	// Dxt1 of 8x8 pixels has levels of four, one and one block
+	let level_data: Vec<u8> = (0..48).collect();
+	let texture = parse_dds(&create_dds(b"DXT1", 8, 8, 3, None, &level_data)).unwrap();
+	assert_eq!(texture.format, BlockFormat::Bc1Rgba);
+	assert_eq!(texture.levels, [level_data[..32].to_vec(), level_data[32..40].to_vec(), level_data[40..].to_vec()]);
+
+	let texture = parse_dds(&create_dds(b"DX10", 4, 4, 1, Some(83), &[0u8; 16])).unwrap();
+	assert_eq!(texture.format, BlockFormat::Bc5);
+	assert_eq!(texture.levels.len(), 1);
+
+	assert!(parse_dds(&create_dds(b"DXT5", 8, 8, 1, None, &[0u8; 63])).is_err());
+	assert!(parse_dds(&create_dds(b"DX10", 4, 4, 1, Some(28), &[0u8; 64])).is_err());
+}
+
+#[test]
+fn texture_formats_are_chosen_by_extension() {
+	assert_eq!(TextureFormat::from_extension("JPG"), Some(TextureFormat::Jpeg));
+	assert_eq!(TextureFormat::from_extension("exr"), Some(TextureFormat::OpenExr));
+	assert_eq!(TextureFormat::from_extension("ktx2"), Some(TextureFormat::Ktx2));
+	assert_eq!(TextureFormat::from_extension("gif"), None);
+}
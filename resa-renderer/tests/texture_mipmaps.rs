@@ -0,0 +1,37 @@
+use image::{Rgba, Rgba32FImage, RgbaImage};
+use resa_renderer::material::ColorSpace;
+use resa_renderer::mipmap::{generate_float_mip_chain, generate_mip_chain, get_mip_level_count};
+
+#[test]
+fn mip_chains_end_at_one_pixel() {
+	assert_eq!(get_mip_level_count(1, 1), 1);
+	assert_eq!(get_mip_level_count(256, 64), 9);
+	assert_eq!(get_mip_level_count(5, 3), 3);
+
+	let levels = generate_mip_chain(&RgbaImage::new(256, 64), ColorSpace::Srgb);
+	let sizes: Vec<(u32, u32)> = levels.iter().map(|level| level.dimensions()).collect();
+	assert_eq!(sizes, [(128, 32), (64, 16), (32, 8), (16, 4), (8, 2), (4, 1), (2, 1), (1, 1)]);
+	assert!(generate_mip_chain(&RgbaImage::new(1, 1), ColorSpace::Srgb).is_empty());
+}
+
+#[test]
+fn srgb_mip_levels_average_in_linear_space() {
+	// Black and white columns, averaged in srgb they would be 128
+	let image = RgbaImage::from_fn(4, 2, |x, _| if x % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 51]) });
+	let levels = generate_mip_chain(&image, ColorSpace::Srgb);
+	assert_eq!(levels[0].dimensions(), (2, 1));
+	assert_eq!(*levels[0].get_pixel(1, 0), Rgba([188, 188, 188, 153]));
+	assert_eq!(*levels[1].get_pixel(0, 0), Rgba([188, 188, 188, 153]));
+
+	// Linear data like normal maps is averaged as it is
+	let levels = generate_mip_chain(&image, ColorSpace::Linear);
+	assert_eq!(*levels[0].get_pixel(0, 0), Rgba([128, 128, 128, 153]));
+}
+
+#[test]
+fn float_mip_levels_keep_values_above_one() {
+	let image = Rgba32FImage::from_fn(2, 2, |x, y| Rgba([(x + 2 * y) as f32 * 4.0, 0.0, 1.0, 1.0]));
+	let levels = generate_float_mip_chain(&image);
+	assert_eq!(levels.len(), 1);
+	assert_eq!(*levels[0].get_pixel(0, 0), Rgba([6.0, 0.0, 1.0, 1.0]));
+}
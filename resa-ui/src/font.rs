@@ -11,13 +11,14 @@ pub struct Font {
 	pub name: String,
 	pub size: f32,
 	pub atlas: Atlas,
+	font: rusttype::Font<'static>,
 }
 
 impl Font {
 	pub fn new(name: &str, font_size: f32, font_bytes: &[u8]) -> Self {
 		let characters = String::from("Hgllo"/*"AaBbCcDdEeFfGgHhIiJjKkLlMmNnOoPpQqRrSsTtUuVvWwXxYyZz0123456789.,:;!?'<>"*/);
 
-		let font = rusttype::Font::try_from_bytes(&font_bytes).unwrap();
+		let font = rusttype::Font::try_from_vec(font_bytes.to_vec()).unwrap();
 
 		let height = font_size as i32;
 		// scale the font initially
@@ -88,6 +89,43 @@ impl Font {
 				width: current_width_offset as u32,
 				height: height as u32,
 			},
+			font,
+		}
+	}
+
+	/// Draw a line of text in white, the coverage of the glyphs is the alpha of the pixels.
+	pub fn rasterize_text(&self, text: &str, font_size: f32) -> Atlas {
+		let scale = Scale::uniform(font_size);
+		let v_metrics = self.font.v_metrics(scale);
+		let glyphs: Vec<_> = self.font.layout(text, scale, point(0.0, v_metrics.ascent)).collect();
+
+		let width = glyphs.last()
+			.map_or(0.0, |glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+			.ceil().max(1.0) as u32;
+		let height = (v_metrics.ascent - v_metrics.descent).ceil().max(1.0) as u32;
+
+		let mut pixels = vec![0; (width * height * 4) as usize];
+		for glyph in glyphs.iter() {
+			let glyph_bb = match glyph.pixel_bounding_box() {
+				Some(glyph_bb) => glyph_bb,
+				None => continue,
+			};
+			glyph.draw(|x, y, v| {
+				let x = x as i32 + glyph_bb.min.x;
+				let y = y as i32 + glyph_bb.min.y;
+				if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+					return;
+				}
+				let index = (y as u32 * width + x as u32) as usize * 4;
+				let alpha = (v * 255.0) as u8;
+				pixels[index..index + 4].copy_from_slice(&[255, 255, 255, pixels[index + 3].max(alpha)]);
+			});
+		}
+
+		Atlas {
+			pixels,
+			width,
+			height,
 		}
 	}
 }
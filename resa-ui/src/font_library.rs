@@ -20,4 +20,10 @@ impl FontLibrary {
 		let font_atlas = self.fonts.iter().find(|font| font.name == name)?.atlas.clone();
 		Some((font_atlas.pixels, (font_atlas.width, font_atlas.height)))
 	}
+
+	/// Pixels of a line of text in the font with the name, white with the coverage as alpha.
+	pub fn render_text(&self, name: &str, text: &str, font_size: f32) -> Option<(Vec<u8>,(u32,u32))>{
+		let text_image = self.fonts.iter().find(|font| font.name == name)?.rasterize_text(text, font_size);
+		Some((text_image.pixels, (text_image.width, text_image.height)))
+	}
 }
\ No newline at end of file
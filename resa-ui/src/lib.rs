@@ -22,5 +22,10 @@ impl ResaUserInterface {
 			font_library,
 		}
 	}
+
+	/// Rasterize a line of text with a font of the resources, None if there is no font with the name.
+	pub fn render_text(&self, font_name: &str, text: &str, font_size: f32) -> Option<(Vec<u8>,(u32,u32))>{
+		self.font_library.render_text(font_name, text, font_size)
+	}
 }
 
@@ -3,12 +3,13 @@
 
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec2 uv;
+// Per instance model matrix, a mat4 takes the locations 2 to 5
+layout(location = 2) in mat4 model;
 layout(location = 0) out vec2 v_uv;
 
 layout(push_constant) uniform PushConstants{
    /* vec3 position;
     float scale;*/
-    mat4 model;
     mat4 view;
     mat4 projection;
 } push_constants;
@@ -17,5 +18,7 @@ layout(push_constant) uniform PushConstants{
 
 void main(){
     v_uv = uv;
-    gl_Position = push_constants.projection * push_constants.view * push_constants.model * vec4(position, 1.0);
+    gl_Position = push_constants.projection * push_constants.view * model * vec4(position, 1.0);
+    // Vulkan leaves the size of points undefined unless it is written
+    gl_PointSize = 1.0;
 }
\ No newline at end of file
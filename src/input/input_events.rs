@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode};
+
+/// Keyboard and mouse events of the application window, re-emitted into the world every frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+	Keyboard { scancode: u32, key: Option<VirtualKeyCode>, state: ElementState },
+	CharacterReceived(char),
+	MouseButton { button: MouseButton, state: ElementState },
+	CursorMoved { x: f64, y: f64 },
+	CursorEntered,
+	CursorLeft,
+	/// Scroll amount in lines, or in pixels for devices like touchpads.
+	MouseWheel { delta_x: f32, delta_y: f32, in_pixels: bool },
+}
+
+impl InputEvent {
+	/// Convert a winit window event to an input event, returns None for events that are not input.
+	pub(crate) fn from_winit(event: &winit::event::WindowEvent) -> Option<Self> {
+		use winit::event::WindowEvent as WinitEvent;
+
+		let event = match event {
+			WinitEvent::KeyboardInput { input, .. } => InputEvent::Keyboard {
+				scancode: input.scancode,
+				key: input.virtual_keycode,
+				state: input.state,
+			},
+			WinitEvent::ReceivedCharacter(character) => InputEvent::CharacterReceived(*character),
+			WinitEvent::MouseInput { button, state, .. } => InputEvent::MouseButton { button: *button, state: *state },
+			WinitEvent::CursorMoved { position, .. } => InputEvent::CursorMoved { x: position.x, y: position.y },
+			WinitEvent::CursorEntered { .. } => InputEvent::CursorEntered,
+			WinitEvent::CursorLeft { .. } => InputEvent::CursorLeft,
+			WinitEvent::MouseWheel { delta, .. } => match delta {
+				MouseScrollDelta::LineDelta(x, y) => InputEvent::MouseWheel { delta_x: *x, delta_y: *y, in_pixels: false },
+				MouseScrollDelta::PixelDelta(position) => InputEvent::MouseWheel {
+					delta_x: position.x as f32,
+					delta_y: position.y as f32,
+					in_pixels: true,
+				},
+			},
+			_ => return None,
+		};
+		Some(event)
+	}
+}
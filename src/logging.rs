@@ -0,0 +1,79 @@
+use std::env;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Logger of the application, receiving the diagnostics of all crates through the log facade.
+/// Every target can get its own level, the most specific target prefix wins,
+/// e.g. resa_renderer=warn hides the info output of the renderer but keeps its warnings.
+pub struct Logger {
+	default_level: LevelFilter,
+	target_levels: Vec<(String, LevelFilter)>,
+}
+
+impl Logger {
+	pub fn new(default_level: LevelFilter) -> Self {
+		Logger {
+			default_level,
+			target_levels: vec![],
+		}
+	}
+
+	/// Create the logger from a filter like "warn,resa_ecs=debug" in the given environment variable.
+	pub fn from_env(variable: &str, default_level: LevelFilter) -> Self {
+		let mut logger = Logger::new(default_level);
+		let filter = match env::var(variable) {
+			Ok(filter) => filter,
+			Err(_) => return logger,
+		};
+
+		for directive in filter.split(',').map(|directive| directive.trim()).filter(|directive| !directive.is_empty()) {
+			match directive.split_once('=') {
+				Some((target, level)) => match level.parse::<LevelFilter>() {
+					Ok(level) => logger = logger.with_target_level(target, level),
+					Err(_) => eprintln!("Ignored invalid log level {} for target {}", level, target),
+				},
+				None => match directive.parse::<LevelFilter>() {
+					Ok(level) => logger.default_level = level,
+					Err(_) => eprintln!("Ignored invalid log level {}", directive),
+				},
+			}
+		}
+		logger
+	}
+
+	pub fn with_target_level(mut self, target: &str, level: LevelFilter) -> Self {
+		self.target_levels.retain(|(existing, _)| existing != target);
+		self.target_levels.push((target.to_string(), level));
+		self
+	}
+
+	/// Install the logger as the global logger of the log facade. Can only be done once.
+	pub fn init(self) -> Result<(), SetLoggerError> {
+		let max_level = self.target_levels.iter().map(|(_, level)| *level).fold(self.default_level, |max, level| max.max(level));
+		log::set_boxed_logger(Box::new(self))?;
+		log::set_max_level(max_level);
+		Ok(())
+	}
+
+	fn get_level(&self, target: &str) -> LevelFilter {
+		self.target_levels.iter()
+			.filter(|(prefix, _)| target == prefix || target.starts_with(&format!("{}::", prefix)))
+			.max_by_key(|(prefix, _)| prefix.len())
+			.map(|(_, level)| *level)
+			.unwrap_or(self.default_level)
+	}
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= self.get_level(metadata.target())
+	}
+
+	fn log(&self, record: &Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+		eprintln!("[{:<5} {}] {}", record.level(), record.target(), record.args());
+	}
+
+	fn flush(&self) {}
+}
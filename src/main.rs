@@ -1,31 +1,86 @@
 extern crate core;
 
-use std::rc::Rc;
-use resa_renderer::mesh::{create_primitive_cube, create_primitive_quad, create_primitive_triangle};
-
+use std::path::{Path, PathBuf};
+use log::{error, info, LevelFilter};
+use resa_ecs::entity::Entity;
+use resa_ecs::schedule::{Schedule, Stage};
+use resa_ecs::world::World;
+use resa_renderer::mesh::{create_primitive_cube, create_primitive_quad, create_primitive_triangle, Mesh, MeshSharing};
+
+use logging::Logger;
 use rendering::camera::Camera;
+use rendering::RenderingSystem;
+use rendering::scene::instantiate_scene;
 use rendering::transform::Transform;
+use replay::{replay, Recording};
+use replay::world_hasher::WorldHasher;
+use resa_app::ResaApp;
+use resources::ResourceManager;
+use test_anim::{change_color, rotate_entity};
+use window::window_settings::WindowSettings;
 
 mod rendering;
 mod resa_app;
 mod event;
 mod test_anim;
 mod resources;
+mod window;
+mod time;
+mod profiling;
+mod logging;
+mod input;
+mod replay;
 
 
 fn main() {
-	let mut app = match resa_app::ResaApp::new("SkriptR", 640, 360) {
+	// The log output can be filtered with e.g. RESA_LOG=warn,resa_ecs=debug
+	if let Err(e) = Logger::from_env("RESA_LOG", LevelFilter::Info).init() {
+		eprintln!("Could not install the logger: {}", e);
+	}
+
+	let settings = WindowSettings {
+		title: "SkriptR".to_string(),
+		width: 640,
+		height: 360,
+		..Default::default()
+	};
+
+	// --replay recording.bin replays a recording without window and checks that it is deterministic.
+	if let Some(path) = get_argument_value("--replay") {
+		run_replay(&settings, Path::new(&path));
+		return;
+	}
+
+	let mut app = match ResaApp::new(settings) {
 		Some(window) => window,
 		None => return,
 	};
 
-	let world = Rc::clone(&app.world);
+	// --profile [trace.json] records frame timings, draws them on top of the scene and writes a chrome trace on exit.
+	let mut args = std::env::args().skip_while(|arg| arg != "--profile");
+	if args.next().is_some() {
+		app.enable_profiling(true, args.next().map(PathBuf::from));
+	}
 
-	let camera_entity = world.borrow_mut().new_entity();
-	let camera = Camera::new(45., [0.1, 100.], true);
-	let transform = Transform { position: [0.0, 0.0, 5.0], angle: 0.0, scale: 1.0 };
-	world.borrow_mut().add_component(&camera_entity, camera);
-	world.borrow_mut().add_component(&camera_entity, transform);
+	// --record recording.bin records the events and delta times of all frames for a later replay.
+	if let Some(path) = get_argument_value("--record") {
+		app.record_to(PathBuf::from(path), create_world_hasher());
+	}
+
+	spawn_scene(&mut app.world.borrow_mut(), Some(&mut app.rendering), &app.resource_loader);
+	add_systems(&mut app.schedule);
+
+	app.run_window_loop();
+}
+
+/// Spawn the entities of the scene. Without a rendering system the entities get no mesh renderers,
+/// so the same scene can be simulated headless.
+fn spawn_scene(world: &mut World, mut rendering: Option<&mut RenderingSystem>, resources: &ResourceManager) {
+	let camera_entity = world.new_entity();
+	let camera = Camera::perspective(45., [0.1, 100.]);
+	let transform = Transform { position: [0.0, 0.0, 5.0], angle: 0.0, ..Transform::idle() };
+	world.add_component(&camera_entity, camera).unwrap();
+	world.add_component(&camera_entity, transform).unwrap();
 
 	/*let material = Material {
 		name: "Material 01".to_string(),
@@ -62,34 +117,96 @@ fn main() {
 
 	// let materials = app.rendering.load_materials(&vec![material, material02, material03, /*material04*/]);
 
-	let entity01 = world.borrow_mut().new_entity();
-	let transform = Transform { position: [0., 0., 0.0], angle: 0.0, scale: 1.0 };
-	let mut mesh_renderer = app.rendering.create_mesh_renderer(create_primitive_triangle());
-	mesh_renderer.set_material("material01");
-	world.borrow_mut().add_component(&entity01, transform);
-	world.borrow_mut().add_component(&entity01, mesh_renderer);
-
-	let entity03 = world.borrow_mut().new_entity();
-	let transform = Transform { position: [-0.2, 0., -1.0], angle: 0.0, scale: 1.0 };
-	let mut mesh_renderer = app.rendering.create_mesh_renderer(create_primitive_triangle());
-	mesh_renderer.set_material("material02");
-	world.borrow_mut().add_component(&entity03, transform);
-	world.borrow_mut().add_component(&entity03, mesh_renderer);
-
-
-	let entity02 = world.borrow_mut().new_entity();
-	let transform = Transform { position: [0.8, 0.2, 0.0], angle: 0.0, scale: 1.0 };
-	let mut mesh_renderer = app.rendering.create_mesh_renderer(create_primitive_quad());
-	mesh_renderer.set_material("material03");
-	world.borrow_mut().add_component(&entity02, transform);
-	world.borrow_mut().add_component(&entity02, mesh_renderer);
-
-	let entity04 = world.borrow_mut().new_entity();
-	let transform = Transform { position: [-1.1, 1.0, 0.2], angle: 0.3, scale: 1.0 };
-	let mut mesh_renderer = app.rendering.create_mesh_renderer(create_primitive_cube());
-	mesh_renderer.set_material("material03");
-	world.borrow_mut().add_component(&entity04, transform);
-	world.borrow_mut().add_component(&entity04, mesh_renderer);
+	let transform = Transform { position: [0., 0., 0.0], angle: 0.0, ..Transform::idle() };
+	spawn_mesh(world, &mut rendering, transform, create_primitive_triangle(), "material01");
 
-	app.run_window_loop();
+	let transform = Transform { position: [-0.2, 0., -1.0], angle: 0.0, ..Transform::idle() };
+	spawn_mesh(world, &mut rendering, transform, create_primitive_triangle(), "material02");
+
+	let transform = Transform { position: [0.8, 0.2, 0.0], angle: 0.0, ..Transform::idle() };
+	spawn_mesh(world, &mut rendering, transform, create_primitive_quad(), "material03");
+
+	let transform = Transform { position: [-1.1, 1.0, 0.2], angle: 0.3, ..Transform::idle() };
+	spawn_mesh(world, &mut rendering, transform, create_primitive_cube(), "material03");
+
+	let transform = Transform { position: [1.4, -0.9, -0.5], angle: 0.6, scale: [0.5; 3], ..Transform::idle() };
+	spawn_mesh_asset(world, &mut rendering, transform, "crate");
+
+	match resources.get_scene("sample") {
+		Some(scene) => {
+			instantiate_scene(world, rendering, &scene);
+		}
+		None => error!("There is no scene sample in the resources"),
+	}
+}
+
+fn spawn_mesh(world: &mut World, rendering: &mut Option<&mut RenderingSystem>, transform: Transform, mesh: Mesh, material: &str) -> Entity {
+	let entity = world.new_entity();
+	world.add_component(&entity, transform).unwrap();
+	if let Some(rendering) = rendering {
+		let mut mesh_renderer = rendering.create_mesh_renderer(mesh, MeshSharing::Shared);
+		mesh_renderer.set_material(material);
+		world.add_component(&entity, mesh_renderer).unwrap();
+	}
+	entity
+}
+
+fn spawn_mesh_asset(world: &mut World, rendering: &mut Option<&mut RenderingSystem>, transform: Transform, name: &str) -> Entity {
+	let entity = world.new_entity();
+	world.add_component(&entity, transform).unwrap();
+	if let Some(rendering) = rendering {
+		match rendering.create_mesh_renderer_from_asset(name) {
+			Some(mesh_renderer) => world.add_component(&entity, mesh_renderer).unwrap(),
+			None => error!("There is no mesh {} in the resources", name),
+		}
+	}
+	entity
+}
+
+fn add_systems(schedule: &mut Schedule) {
+	schedule.add_system(Stage::Update, "rotate_entity", |world| rotate_entity(world, &Entity(4)));
+	schedule.add_system(Stage::Update, "change_color", |world| change_color(world, &Entity(2)));
+}
+
+/// The components checked for divergence when replaying a recording.
+fn create_world_hasher() -> WorldHasher {
+	let mut world_hasher = WorldHasher::new();
+	world_hasher.add_component::<Transform>();
+	world_hasher
+}
+
+fn run_replay(settings: &WindowSettings, path: &Path) {
+	let recording = match Recording::load(path) {
+		Ok(recording) => recording,
+		Err(e) => {
+			error!("{}", e);
+			return;
+		}
+	};
+
+	let resources = match ResourceManager::new() {
+		Ok(resources) => resources,
+		Err(e) => {
+			error!("Could not load the resources: {}", e);
+			return;
+		}
+	};
+
+	let mut world = ResaApp::create_world(settings);
+	let mut schedule = Schedule::new();
+	spawn_scene(&mut world, None, &resources);
+	add_systems(&mut schedule);
+
+	let report = replay(&recording, &mut world, &mut schedule, &create_world_hasher());
+	match report.divergence {
+		None => info!("Replayed {} frames without divergence", report.frames_replayed),
+		Some(divergence) => error!(
+			"Replay diverged in frame {}: expected hash {:016x}, got {:016x}",
+			divergence.frame, divergence.expected_hash, divergence.actual_hash
+		),
+	}
+}
+
+fn get_argument_value(name: &str) -> Option<String> {
+	std::env::args().skip_while(|arg| arg != name).nth(1)
 }
@@ -0,0 +1,106 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use crate::profiling::{Profiler, ScopeCategory};
+
+/// Write the recorded frames as complete events in the chrome trace event format,
+/// which can be opened with chrome://tracing or https://ui.perfetto.dev.
+pub fn write_chrome_trace(profiler: &Profiler, path: &Path) -> io::Result<()> {
+	fs::write(path, create_chrome_trace(profiler))
+}
+
+pub fn create_chrome_trace(profiler: &Profiler) -> String {
+	let mut events = vec![];
+	for frame in profiler.get_frames() {
+		events.push(create_event(&format!("Frame {}", frame.frame), ScopeCategory::Frame.get_name(), frame.start, frame.duration, frame.frame));
+		for scope in frame.scopes.iter() {
+			events.push(create_event(&scope.name, scope.category.get_name(), scope.start, scope.duration, frame.frame));
+		}
+		for (name, value) in frame.counters.iter() {
+			events.push(create_counter_event(name, frame.start, *value));
+		}
+	}
+	format!("{{\"traceEvents\":[{}],\"displayTimeUnit\":\"ms\"}}", events.join(","))
+}
+
+fn create_event(name: &str, category: &str, start: Duration, duration: Duration, frame: u64) -> String {
+	format!(
+		"{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":1,\"args\":{{\"frame\":{}}}}}",
+		escape_json(name),
+		category,
+		start.as_secs_f64() * 1_000_000.0,
+		duration.as_secs_f64() * 1_000_000.0,
+		frame,
+	)
+}
+
+/// Counter events are shown as a graph of the value over time.
+fn create_counter_event(name: &str, start: Duration, value: u64) -> String {
+	format!(
+		"{{\"name\":\"{}\",\"ph\":\"C\",\"ts\":{:.3},\"pid\":1,\"args\":{{\"value\":{}}}}}",
+		escape_json(name),
+		start.as_secs_f64() * 1_000_000.0,
+		value,
+	)
+}
+
+fn escape_json(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use crate::profiling::chrome_trace::{create_chrome_trace, escape_json};
+	use crate::profiling::{FrameProfile, ProfileScope, Profiler, ScopeCategory};
+
+	#[test]
+	fn json_strings_are_escaped() {
+		assert_eq!(escape_json("Update"), "Update");
+		assert_eq!(escape_json("say \"hi\""), "say \\\"hi\\\"");
+		assert_eq!(escape_json("C:\\resa\\trace"), "C:\\\\resa\\\\trace");
+		assert_eq!(escape_json("a\nb\r\tc"), "a\\nb\\r\\tc");
+		assert_eq!(escape_json("\u{0}\u{1b}\u{1f}"), "\\u0000\\u001b\\u001f");
+		assert_eq!(escape_json("größe ✓"), "größe ✓");
+	}
+
+	#[test]
+	fn frames_scopes_and_counters_become_trace_events() {
+		let mut profiler = Profiler::new(4);
+		assert_eq!(create_chrome_trace(&profiler), "{\"traceEvents\":[],\"displayTimeUnit\":\"ms\"}");
+
+		profiler.frames.push_back(FrameProfile {
+			frame: 7,
+			start: Duration::from_millis(1),
+			duration: Duration::from_micros(2500),
+			scopes: vec![ProfileScope {
+				name: "draw \"ui\"".to_string(),
+				category: ScopeCategory::Render,
+				start: Duration::from_micros(1500),
+				duration: Duration::from_micros(500),
+			}],
+			counters: vec![("drawn_objects".to_string(), 12)],
+		});
+		let expected = concat!(
+			"{\"traceEvents\":[",
+			"{\"name\":\"Frame 7\",\"cat\":\"frame\",\"ph\":\"X\",\"ts\":1000.000,\"dur\":2500.000,\"pid\":1,\"tid\":1,\"args\":{\"frame\":7}},",
+			"{\"name\":\"draw \\\"ui\\\"\",\"cat\":\"render\",\"ph\":\"X\",\"ts\":1500.000,\"dur\":500.000,\"pid\":1,\"tid\":1,\"args\":{\"frame\":7}},",
+			"{\"name\":\"drawn_objects\",\"ph\":\"C\",\"ts\":1000.000,\"pid\":1,\"args\":{\"value\":12}}",
+			"],\"displayTimeUnit\":\"ms\"}",
+		);
+		assert_eq!(create_chrome_trace(&profiler), expected);
+	}
+}
@@ -0,0 +1,251 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use resa_ecs::schedule::ScheduleTimings;
+use resa_renderer::profiling::RenderTiming;
+
+pub mod chrome_trace;
+pub mod overlay;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScopeCategory {
+	Frame,
+	Stage,
+	System,
+	Render,
+}
+
+impl ScopeCategory {
+	pub fn get_name(&self) -> &'static str {
+		match self {
+			ScopeCategory::Frame => "frame",
+			ScopeCategory::Stage => "stage",
+			ScopeCategory::System => "system",
+			ScopeCategory::Render => "render",
+		}
+	}
+}
+
+/// A timed section of a frame. The start is relative to the creation of the profiler.
+#[derive(Clone, Debug)]
+pub struct ProfileScope {
+	pub name: String,
+	pub category: ScopeCategory,
+	pub start: Duration,
+	pub duration: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct FrameProfile {
+	pub frame: u64,
+	pub start: Duration,
+	pub duration: Duration,
+	pub scopes: Vec<ProfileScope>,
+	/// Values counted during the frame, e.g. the number of drawn objects.
+	pub counters: Vec<(String, u64)>,
+}
+
+/// Collects the CPU timings of the systems, the ecs stages and the render stages
+/// of the most recent frames in a ring buffer.
+pub struct Profiler {
+	enabled: bool,
+	capacity: usize,
+	epoch: Instant,
+	frames: VecDeque<FrameProfile>,
+	current_frame: Option<(Instant, FrameProfile)>,
+	/// Draw the frame time statistics on top of the window.
+	pub show_overlay: bool,
+	/// The chrome trace of the recorded frames is written to this file when the app shuts down.
+	pub trace_path: Option<PathBuf>,
+}
+
+impl Profiler {
+	pub fn new(capacity: usize) -> Self {
+		Profiler {
+			enabled: false,
+			capacity,
+			epoch: Instant::now(),
+			frames: VecDeque::with_capacity(capacity),
+			current_frame: None,
+			show_overlay: false,
+			trace_path: None,
+		}
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+
+	pub fn set_enabled(&mut self, enabled: bool) {
+		self.enabled = enabled;
+		if !enabled {
+			self.current_frame = None;
+		}
+	}
+
+	pub fn begin_frame(&mut self, frame: u64) {
+		if !self.enabled {
+			return;
+		}
+		let start = Instant::now();
+		self.current_frame = Some((start, FrameProfile {
+			frame,
+			start: self.since_epoch(start),
+			duration: Duration::ZERO,
+			scopes: vec![],
+			counters: vec![],
+		}));
+	}
+
+	/// Record a scope of the current frame. Scopes outside of begin_frame and end_frame are ignored.
+	pub fn record(&mut self, name: &str, category: ScopeCategory, start: Instant, duration: Duration) {
+		let start = self.since_epoch(start);
+		if let Some((_, frame)) = self.current_frame.as_mut() {
+			frame.scopes.push(ProfileScope {
+				name: name.to_string(),
+				category,
+				start,
+				duration,
+			});
+		}
+	}
+
+	pub fn record_counter(&mut self, name: &str, value: u64) {
+		if let Some((_, frame)) = self.current_frame.as_mut() {
+			frame.counters.push((name.to_string(), value));
+		}
+	}
+
+	pub fn record_schedule(&mut self, timings: &ScheduleTimings) {
+		for stage in timings.stages.iter() {
+			self.record(&format!("{:?}", stage.stage), ScopeCategory::Stage, stage.start, stage.duration);
+		}
+		for system in timings.systems.iter() {
+			self.record(&system.name, ScopeCategory::System, system.start, system.duration);
+		}
+	}
+
+	pub fn record_render(&mut self, timings: &[RenderTiming]) {
+		for timing in timings.iter() {
+			self.record(&timing.label, ScopeCategory::Render, timing.start, timing.duration);
+		}
+	}
+
+	/// Finish the current frame and push it into the ring buffer, dropping the oldest frame if it is full.
+	pub fn end_frame(&mut self) {
+		let (start, mut frame) = match self.current_frame.take() {
+			Some(current) => current,
+			None => return,
+		};
+		frame.duration = start.elapsed();
+
+		if self.frames.len() == self.capacity {
+			self.frames.pop_front();
+		}
+		self.frames.push_back(frame);
+	}
+
+	pub fn get_frames(&self) -> impl Iterator<Item = &FrameProfile> {
+		self.frames.iter()
+	}
+
+	/// Get the value of a counter in the most recent frame.
+	pub fn get_last_counter(&self, name: &str) -> Option<u64> {
+		let frame = self.frames.back()?;
+		frame.counters.iter().find(|(counter, _)| counter == name).map(|(_, value)| *value)
+	}
+
+	/// Get the frame time below which the given percentage of the recorded frames lie.
+	pub fn get_frame_time_percentile(&self, percentile: f64) -> Option<Duration> {
+		if self.frames.is_empty() {
+			return None;
+		}
+		let mut durations: Vec<Duration> = self.frames.iter().map(|frame| frame.duration).collect();
+		durations.sort();
+
+		let rank = (percentile.clamp(0.0, 100.0) / 100.0 * durations.len() as f64).ceil() as usize;
+		Some(durations[rank.max(1) - 1])
+	}
+
+	/// Get the frames per second averaged over the recorded frames.
+	pub fn get_average_fps(&self) -> Option<f64> {
+		let total: Duration = self.frames.iter().map(|frame| frame.duration).sum();
+		if total.is_zero() {
+			return None;
+		}
+		Some(self.frames.len() as f64 / total.as_secs_f64())
+	}
+
+	fn since_epoch(&self, instant: Instant) -> Duration {
+		instant.saturating_duration_since(self.epoch)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use crate::profiling::{FrameProfile, Profiler};
+
+	/// Frames with a known duration, recorded frames measure the real time.
+	fn push_frames(profiler: &mut Profiler, durations_ms: &[u64]) {
+		for (frame, duration) in durations_ms.iter().enumerate() {
+			profiler.frames.push_back(FrameProfile {
+				frame: frame as u64,
+				start: Duration::ZERO,
+				duration: Duration::from_millis(*duration),
+				scopes: vec![],
+				counters: vec![],
+			});
+		}
+	}
+
+	#[test]
+	fn full_profilers_drop_the_oldest_frame() {
+		let mut profiler = Profiler::new(3);
+		profiler.begin_frame(0);
+		profiler.end_frame();
+		assert_eq!(profiler.get_frames().count(), 0);
+
+		profiler.set_enabled(true);
+		for frame in 1..=5 {
+			profiler.begin_frame(frame);
+			profiler.record_counter("drawn_objects", frame * 10);
+			profiler.end_frame();
+		}
+		let frames: Vec<u64> = profiler.get_frames().map(|frame| frame.frame).collect();
+		assert_eq!(frames, vec![3, 4, 5]);
+		assert_eq!(profiler.get_last_counter("drawn_objects"), Some(50));
+		assert_eq!(profiler.get_last_counter("culled_objects"), None);
+	}
+
+	#[test]
+	fn percentiles_are_taken_from_the_sorted_frame_times() {
+		let mut profiler = Profiler::new(16);
+		assert_eq!(profiler.get_frame_time_percentile(50.0), None);
+
+		push_frames(&mut profiler, &[7, 3, 10, 1, 5, 9, 2, 8, 4, 6]);
+		let percentile = |percentile: f64| profiler.get_frame_time_percentile(percentile).unwrap().as_millis();
+		assert_eq!(percentile(0.0), 1);
+		assert_eq!(percentile(10.0), 1);
+		assert_eq!(percentile(50.0), 5);
+		assert_eq!(percentile(90.0), 9);
+		assert_eq!(percentile(95.0), 10);
+		assert_eq!(percentile(100.0), 10);
+		// Percentiles out of range are clamped
+		assert_eq!(percentile(-5.0), 1);
+		assert_eq!(percentile(150.0), 10);
+	}
+
+	#[test]
+	fn average_fps_are_the_frames_per_recorded_time() {
+		let mut profiler = Profiler::new(16);
+		assert_eq!(profiler.get_average_fps(), None);
+
+		push_frames(&mut profiler, &[0, 0]);
+		assert_eq!(profiler.get_average_fps(), None);
+
+		push_frames(&mut profiler, &[10, 20, 30]);
+		let fps = profiler.get_average_fps().unwrap();
+		assert!((fps - 5.0 / 0.06).abs() < 1e-9, "{} fps", fps);
+	}
+}
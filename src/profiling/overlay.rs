@@ -0,0 +1,193 @@
+use log::warn;
+use winit::dpi::PhysicalSize;
+use resa_ecs::entity::Entity;
+use resa_ecs::world::World;
+use resa_renderer::material::{Color, Material, MaterialRef, PipelineState, SamplerSettings, Texture, TextureFilter, TextureFormat};
+use resa_renderer::mesh::Mesh;
+use resa_renderer::render_stage::RenderStage;
+use resa_renderer::vertex::{AttributeData, VertexAttribute};
+use resa_ui::ResaUserInterface;
+use crate::profiling::Profiler;
+use crate::rendering::camera::{Camera, OrthographicScaling};
+use crate::rendering::mesh_renderer::MeshRenderer;
+use crate::rendering::render_layers::RenderLayers;
+use crate::rendering::RenderingSystem;
+
+/// Only the camera of the overlay draws this layer, and it draws nothing else.
+const OVERLAY_LAYER: u32 = 31;
+const OVERLAY_NAME: &str = "profiler_overlay";
+const FONT_NAME: &str = "Arial";
+const FONT_SIZE: f32 = 16.0;
+/// Distance of the background to the top left corner of the window, in pixels
+const MARGIN: u32 = 8;
+/// Space between the text and the edge of its background, in pixels
+const PADDING: u32 = 4;
+const BACKGROUND_COLOR: [u8; 4] = [0, 0, 0, 170];
+
+struct OverlayEntities {
+	camera: Entity,
+	text: Entity,
+}
+
+/// Draws the frame rate and the frame time percentiles of the profiler in the top left corner of the window.
+/// The text is rasterized into a texture, which is drawn on a quad by an orthographic camera on top of all other cameras.
+/// It is only refreshed every refresh_interval seconds to keep it readable.
+pub struct ProfilerOverlay {
+	refresh_interval: f64,
+	last_refresh: f64,
+	entities: Option<OverlayEntities>,
+	material: Option<MaterialRef>,
+	/// Size of the text image and of the window the quad was placed for
+	text_size: (u32, u32),
+	window_size: PhysicalSize<u32>,
+	font_missing: bool,
+}
+
+impl ProfilerOverlay {
+	pub fn new(refresh_interval: f64) -> Self {
+		ProfilerOverlay {
+			refresh_interval,
+			last_refresh: 0.0,
+			entities: None,
+			material: None,
+			text_size: (0, 0),
+			window_size: PhysicalSize::new(0, 0),
+			font_missing: false,
+		}
+	}
+
+	pub fn update(&mut self, world: &mut World, rendering: &mut RenderingSystem, ui: &ResaUserInterface, profiler: &Profiler, elapsed_seconds: f64) {
+		if !profiler.is_enabled() || !profiler.show_overlay || self.font_missing {
+			self.hide(world);
+			return;
+		}
+
+		let refresh = self.entities.is_none() || elapsed_seconds - self.last_refresh >= self.refresh_interval;
+		if refresh {
+			let text = match ProfilerOverlay::get_text(profiler) {
+				Some(text) => text,
+				None => return,
+			};
+			let (pixels, (width, height)) = match ui.render_text(FONT_NAME, &text, FONT_SIZE) {
+				Some(text_image) => text_image,
+				None => {
+					warn!("The profiler overlay is not shown, there is no font {}", FONT_NAME);
+					self.font_missing = true;
+					return;
+				}
+			};
+			let (pixels, text_size) = add_background(&pixels, width, height);
+			let sampler = SamplerSettings { min_filter: TextureFilter::Nearest, mag_filter: TextureFilter::Nearest, mipmaps: false, ..SamplerSettings::default() };
+			rendering.register_texture(OVERLAY_NAME, pixels, TextureFormat::Custom(text_size), sampler);
+			self.text_size = text_size;
+			self.last_refresh = elapsed_seconds;
+		}
+
+		let window_size = rendering.get_window_size();
+		let quad = create_quad(self.text_size, window_size);
+		let text_entity = match &self.entities {
+			Some(entities) => entities.text,
+			None => {
+				self.show(world, rendering, quad);
+				self.window_size = window_size;
+				return;
+			}
+		};
+		if refresh || window_size != self.window_size {
+			if let Some(mesh_renderer) = world.get_component_mut::<MeshRenderer>(&text_entity) {
+				if let Err(e) = mesh_renderer.update_mesh(quad) {
+					warn!("Could not place the profiler overlay: {}", e);
+				}
+			}
+			self.window_size = window_size;
+		}
+	}
+
+	pub fn get_text(profiler: &Profiler) -> Option<String> {
+		let fps = profiler.get_average_fps()?;
+		let p50 = profiler.get_frame_time_percentile(50.0)?;
+		let p95 = profiler.get_frame_time_percentile(95.0)?;
+		let p99 = profiler.get_frame_time_percentile(99.0)?;
+		let mut text = format!(
+			"{:.0} fps | p50 {:.2} ms | p95 {:.2} ms | p99 {:.2} ms",
+			fps,
+			p50.as_secs_f64() * 1000.0,
+			p95.as_secs_f64() * 1000.0,
+			p99.as_secs_f64() * 1000.0,
+		);
+		if let (Some(drawn), Some(culled)) = (profiler.get_last_counter("drawn_objects"), profiler.get_last_counter("culled_objects")) {
+			text.push_str(&format!(" | {} drawn | {} culled", drawn, culled));
+		}
+		Some(text)
+	}
+
+	/// Spawn the camera and the text quad of the overlay. They have no transform, so the camera looks along
+	/// the negative z axis and one world unit is one pixel, with the origin in the center of the window.
+	fn show(&mut self, world: &mut World, rendering: &mut RenderingSystem, quad: Mesh) {
+		let material = *self.material.get_or_insert_with(|| rendering.register_material(Material {
+			name: OVERLAY_NAME.to_string(),
+			shader_id: 0,
+			render_stage: RenderStage::UI,
+			pipeline_state: PipelineState { depth_test: false, depth_write: false, ..PipelineState::default() },
+			color: Color::new(255, 255, 255, 255),
+			texture: Texture::Named(OVERLAY_NAME.to_string()),
+		}));
+
+		let camera_entity = world.new_entity();
+		let camera = Camera::orthographic(OrthographicScaling::PixelPerfect { pixels_per_unit: 1.0 }, [0.1, 10.0])
+			.with_priority(i32::MAX)
+			.with_layers(RenderLayers::layer(OVERLAY_LAYER));
+		world.add_component(&camera_entity, camera).unwrap();
+
+		let text_entity = world.new_entity();
+		let mut mesh_renderer = rendering.create_dynamic_mesh_renderer(quad);
+		mesh_renderer.material_id = Some(material);
+		world.add_component(&text_entity, mesh_renderer).unwrap();
+		world.add_component(&text_entity, RenderLayers::layer(OVERLAY_LAYER)).unwrap();
+
+		self.entities = Some(OverlayEntities { camera: camera_entity, text: text_entity });
+	}
+
+	/// Remove the entities of the overlay, the quad is freed with its mesh renderer.
+	fn hide(&mut self, world: &mut World) {
+		if let Some(entities) = self.entities.take() {
+			for entity in [entities.camera, entities.text] {
+				if let Err(e) = world.remove_entity(entity) {
+					warn!("Could not remove the profiler overlay: {}", e);
+				}
+			}
+		}
+	}
+}
+
+/// The white text over a dark background, which keeps it readable in front of bright scenes.
+fn add_background(text_pixels: &[u8], text_width: u32, text_height: u32) -> (Vec<u8>, (u32, u32)) {
+	let width = text_width + 2 * PADDING;
+	let height = text_height + 2 * PADDING;
+	let mut pixels = BACKGROUND_COLOR.repeat((width * height) as usize);
+	for y in 0..text_height {
+		for x in 0..text_width {
+			let text_alpha = text_pixels[((y * text_width + x) * 4 + 3) as usize] as f32 / 255.0;
+			let index = (((y + PADDING) * width + x + PADDING) * 4) as usize;
+			for (channel, value) in pixels[index..index + 4].iter_mut().enumerate() {
+				let text_value = if channel == 3 { 255.0 } else { text_pixels[((y * text_width + x) * 4) as usize + channel] as f32 };
+				*value = (text_value * text_alpha + *value as f32 * (1.0 - text_alpha)).round() as u8;
+			}
+		}
+	}
+	(pixels, (width, height))
+}
+
+/// Quad of the size of the text in the top left corner of the window. The y axis of the overlay camera points down.
+fn create_quad(text_size: (u32, u32), window_size: PhysicalSize<u32>) -> Mesh {
+	let left = -(window_size.width as f32) / 2.0 + MARGIN as f32;
+	let top = -(window_size.height as f32) / 2.0 + MARGIN as f32;
+	let right = left + text_size.0 as f32;
+	let bottom = top + text_size.1 as f32;
+	let depth = -1.0;
+
+	let mut quad = Mesh::from_positions(vec![[left, top, depth], [right, top, depth], [right, bottom, depth], [left, bottom, depth]], vec![0, 1, 2, 0, 2, 3]);
+	quad.set_attribute(VertexAttribute::Uv0, AttributeData::Float2(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]))
+		.expect("The quad has a uv for each of its vertices");
+	quad
+}
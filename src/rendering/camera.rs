@@ -1,20 +1,238 @@
+use glm::{GenSquareMat, Matrix4, Vector4};
+use winit::dpi::PhysicalSize;
+use resa_renderer::render_view::ViewportRect;
+use crate::rendering::camera_system;
+use crate::rendering::render_layers::RenderLayers;
+use crate::rendering::transform::Transform;
+
+/// How the view volume of an orthographic camera follows the size of its viewport, in world units.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OrthographicScaling {
+    /// The visible height stays the same, the width follows the aspect ratio.
+    FixedHeight(f32),
+    /// The visible width stays the same, the height follows the aspect ratio.
+    FixedWidth(f32),
+    /// One world unit covers the given amount of pixels, so the visible area grows with the viewport.
+    PixelPerfect { pixels_per_unit: f32 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    /// Uses the field of view of the camera.
+    Perspective,
+    Orthographic(OrthographicScaling),
+}
+
+/// A half line from the camera into the world, e.g. through the cursor for picking.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    /// Normalized direction.
+    pub direction: [f32; 3],
+}
+
+impl Ray {
+    pub fn get_point(&self, distance: f32) -> [f32; 3] {
+        [0, 1, 2].map(|axis| self.origin[axis] + self.direction[axis] * distance)
+    }
+}
+
 pub struct Camera{
+    /// Vertical field of view in degrees, only used by perspective cameras.
     pub fov: f32,
-    pub ratio: f32,
     pub range_min: f32,
     pub range_max: f32,
-    pub is_perspective: bool,
+    pub projection: Projection,
+    /// Cameras are drawn in ascending order of their priority, so higher ones are drawn on top.
+    pub priority: i32,
+    /// The part of the window the camera draws to, the aspect ratio of the projection follows its size.
+    pub viewport: ViewportRect,
+    /// Without a clear color the camera draws over the cameras with a lower priority.
+    pub clear_color: Option<[f32; 4]>,
+    /// Only entities on one of these layers are drawn by the camera.
+    pub layers: RenderLayers,
 }
 
 impl Camera {
-    pub fn new(fov: f32, range: [f32;2], is_perspective: bool) -> Self{
+    pub fn perspective(fov: f32, range: [f32;2]) -> Self{
         Camera{
             fov,
             range_min: range[0],
             range_max: range[1],
-            is_perspective,
-            ratio: 16.0 / 9.0,
+            projection: Projection::Perspective,
+            priority: 0,
+            viewport: ViewportRect::FULL,
+            clear_color: None,
+            layers: RenderLayers::default(),
+        }
+    }
+
+    pub fn orthographic(scaling: OrthographicScaling, range: [f32;2]) -> Self{
+        Camera{
+            projection: Projection::Orthographic(scaling),
+            ..Camera::perspective(45.0, range)
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: ViewportRect) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn with_clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = Some(clear_color);
+        self
+    }
+
+    pub fn with_layers(mut self, layers: RenderLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn is_perspective(&self) -> bool {
+        self.projection == Projection::Perspective
+    }
+
+    /// Size of the viewport of the camera in pixels, for a window of the given size.
+    pub fn get_viewport_size(&self, window_size: PhysicalSize<u32>) -> [f32; 2] {
+        [self.viewport.width * window_size.width as f32, self.viewport.height * window_size.height as f32]
+    }
+
+    /// The position in window pixels the world position is drawn at. None if the position is behind the camera.
+    /// Positions outside of the viewport are returned as well.
+    pub fn world_to_screen(&self, transform: &Transform, window_size: PhysicalSize<u32>, world_position: [f32; 3]) -> Option<[f32; 2]> {
+        let [x, y, z] = world_position;
+        let clip = self.get_view_projection(transform, window_size) * glm::vec4(x, y, z, 1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        Some(self.ndc_to_screen(window_size, [clip.x / clip.w, clip.y / clip.w]))
+    }
+
+    /// The ray through the given position in window pixels, starting at the near plane of the camera.
+    /// None if the viewport has no area.
+    pub fn screen_to_world_ray(&self, transform: &Transform, window_size: PhysicalSize<u32>, screen_position: [f32; 2]) -> Option<Ray> {
+        let [ndc_x, ndc_y] = self.screen_to_ndc(window_size, screen_position)?;
+        let inverse = self.get_view_projection(transform, window_size).inverse()?;
+
+        let unproject = |ndc_z: f32| -> Option<[f32; 3]> {
+            let point: Vector4<f32> = inverse * glm::vec4(ndc_x, ndc_y, ndc_z, 1.0);
+            if point.w == 0.0 {
+                return None;
+            }
+            Some([point.x / point.w, point.y / point.w, point.z / point.w])
+        };
+        // The depth range of the projection is -1 at the near plane to 1 at the far plane
+        let near = unproject(-1.0)?;
+        let far = unproject(1.0)?;
+
+        let direction = [far[0] - near[0], far[1] - near[1], far[2] - near[2]];
+        let length = (direction[0] * direction[0] + direction[1] * direction[1] + direction[2] * direction[2]).sqrt();
+        if length == 0.0 {
+            return None;
+        }
+        Some(Ray {
+            origin: near,
+            direction: direction.map(|value| value / length),
+        })
+    }
+
+    fn get_view_projection(&self, transform: &Transform, window_size: PhysicalSize<u32>) -> Matrix4<f32> {
+        camera_system::make_projection(self, self.get_viewport_size(window_size)) * camera_system::make_view(transform)
+    }
+
+    // The renderer does not flip the y axis, so normalized device coordinates of -1 are at the top of the viewport
+    fn ndc_to_screen(&self, window_size: PhysicalSize<u32>, ndc: [f32; 2]) -> [f32; 2] {
+        let [width, height] = self.get_viewport_size(window_size);
+        [
+            self.viewport.x * window_size.width as f32 + (ndc[0] + 1.0) * 0.5 * width,
+            self.viewport.y * window_size.height as f32 + (ndc[1] + 1.0) * 0.5 * height,
+        ]
+    }
+
+    fn screen_to_ndc(&self, window_size: PhysicalSize<u32>, screen_position: [f32; 2]) -> Option<[f32; 2]> {
+        let [width, height] = self.get_viewport_size(window_size);
+        if width <= 0.0 || height <= 0.0 {
+            return None;
         }
+        Some([
+            (screen_position[0] - self.viewport.x * window_size.width as f32) / width * 2.0 - 1.0,
+            (screen_position[1] - self.viewport.y * window_size.height as f32) / height * 2.0 - 1.0,
+        ])
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalSize;
+    use resa_renderer::render_view::ViewportRect;
+    use crate::rendering::camera::{Camera, OrthographicScaling};
+    use crate::rendering::transform::Transform;
+
+    const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize { width: 640, height: 360 };
+
+    fn camera_transform() -> Transform {
+        Transform { position: [0.0, 0.0, 5.0], ..Transform::idle() }
+    }
+
+    /// Equal up to the precision of f32, relative to the size of the values.
+    fn assert_near(actual: &[f32], expected: &[f32]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-3 * (1.0 + e.abs()), "{:?} is not {:?}", actual, expected);
+        }
+    }
+
+    /// World positions are drawn where the ray through their screen position passes them,
+    /// and points along the ray of a screen position are drawn at that screen position.
+    fn assert_round_trip(camera: &Camera) {
+        let transform = camera_transform();
+        for world_position in [[0.0, 0.0, 0.0], [0.3, -0.2, 0.0], [-1.0, 0.5, -2.0]] {
+            let screen_position = camera.world_to_screen(&transform, WINDOW_SIZE, world_position).unwrap();
+            let ray = camera.screen_to_world_ray(&transform, WINDOW_SIZE, screen_position).unwrap();
+            let to_point = [0, 1, 2].map(|axis| world_position[axis] - ray.origin[axis]);
+            let distance = to_point[0] * ray.direction[0] + to_point[1] * ray.direction[1] + to_point[2] * ray.direction[2];
+            assert!(distance > 0.0);
+            assert_near(&ray.get_point(distance), &world_position);
+        }
+
+        for screen_position in [[320.0, 180.0], [10.0, 350.0], [600.0, 20.0]] {
+            let ray = camera.screen_to_world_ray(&transform, WINDOW_SIZE, screen_position).unwrap();
+            for distance in [0.5, 3.0] {
+                let screen = camera.world_to_screen(&transform, WINDOW_SIZE, ray.get_point(distance)).unwrap();
+                assert_near(&screen, &screen_position);
+            }
+        }
+    }
+
+    #[test]
+    fn perspective_conversions_round_trip() {
+        assert_round_trip(&Camera::perspective(45.0, [0.1, 100.0]));
+        assert_round_trip(&Camera::perspective(60.0, [0.1, 100.0]).with_viewport(ViewportRect::new(0.5, 0.25, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn orthographic_conversions_round_trip() {
+        for scaling in [OrthographicScaling::FixedHeight(4.0), OrthographicScaling::FixedWidth(6.0), OrthographicScaling::PixelPerfect { pixels_per_unit: 100.0 }] {
+            assert_round_trip(&Camera::orthographic(scaling, [0.1, 100.0]));
+            assert_round_trip(&Camera::orthographic(scaling, [0.1, 100.0]).with_viewport(ViewportRect::new(0.0, 0.5, 0.25, 0.5)));
+        }
+    }
+
+    #[test]
+    fn pixel_perfect_cameras_map_units_to_pixels() {
+        let camera = Camera::orthographic(OrthographicScaling::PixelPerfect { pixels_per_unit: 100.0 }, [0.1, 100.0]);
+        let screen_position = camera.world_to_screen(&camera_transform(), WINDOW_SIZE, [1.0, 0.5, 0.0]).unwrap();
+        assert_near(&screen_position, &[420.0, 230.0]);
+    }
+
+    #[test]
+    fn positions_behind_perspective_cameras_are_not_on_screen() {
+        let camera = Camera::perspective(45.0, [0.1, 100.0]);
+        assert_eq!(camera.world_to_screen(&camera_transform(), WINDOW_SIZE, [0.0, 0.0, 10.0]), None);
+    }
+}
@@ -1,24 +1,51 @@
 use glm::{Matrix4, Vector3};
-use crate::rendering::camera::Camera;
+use crate::rendering::camera::{Camera, OrthographicScaling, Projection};
 use crate::rendering::transform::Transform;
 
-pub fn get_camera_projection_matrix(camera: &Camera) -> [[f32; 4]; 4]{
-    let mat =
-        glm::ext::perspective(glm::radians(camera.fov), camera.ratio, camera.range_min, camera.range_max);
-
-    to_matrix_array(mat)
+/// The projection for a viewport with the given size in pixels.
+pub fn get_camera_projection_matrix(camera: &Camera, viewport_size: [f32; 2]) -> [[f32; 4]; 4]{
+    to_matrix_array(make_projection(camera, viewport_size))
 }
 
 pub fn get_camera_view_matrix(transform: &Transform) -> [[f32; 4]; 4] {
+    to_matrix_array(make_view(transform))
+}
+
+pub(crate) fn make_projection(camera: &Camera, viewport_size: [f32; 2]) -> Matrix4<f32> {
+    let [width, height] = viewport_size;
+    let aspect_ratio = if height > 0.0 { width / height } else { 1.0 };
+    match camera.projection {
+        Projection::Perspective => glm::ext::perspective(glm::radians(camera.fov), aspect_ratio, camera.range_min, camera.range_max),
+        Projection::Orthographic(scaling) => {
+            let [view_width, view_height] = match scaling {
+                OrthographicScaling::FixedHeight(view_height) => [view_height * aspect_ratio, view_height],
+                OrthographicScaling::FixedWidth(view_width) => [view_width, view_width / aspect_ratio],
+                OrthographicScaling::PixelPerfect { pixels_per_unit } => [width / pixels_per_unit, height / pixels_per_unit],
+            };
+            make_orthographic(view_width, view_height, camera.range_min, camera.range_max)
+        }
+    }
+}
+
+pub(crate) fn make_view(transform: &Transform) -> Matrix4<f32> {
    
     let camera_pos: Vector3<f32> = glm::vec3(transform.position[0], transform.position[1], transform.position[2]);
     let look_point: Vector3<f32> = glm::vec3(0.0, 0.0, 0.0);
     let up_vector: Vector3<f32> = glm::vec3(0.0, 1.0, 0.0);
-    let view = glm::ext::look_at(camera_pos, look_point, up_vector);
+    glm::ext::look_at(camera_pos, look_point, up_vector)
+}
 
-    to_matrix_array(view)
+/// Centered orthographic projection with the same depth range as the perspective projection of glm.
+fn make_orthographic(width: f32, height: f32, near: f32, far: f32) -> Matrix4<f32> {
+    let depth = far - near;
+    Matrix4::new(
+        glm::vec4(2.0 / width, 0.0, 0.0, 0.0),
+        glm::vec4(0.0, 2.0 / height, 0.0, 0.0),
+        glm::vec4(0.0, 0.0, -2.0 / depth, 0.0),
+        glm::vec4(0.0, 0.0, -(far + near) / depth, 1.0),
+    )
 }
 
 fn to_matrix_array(mat :Matrix4<f32>) -> [[f32; 4]; 4]{
     return [[mat.c0.x, mat.c0.y, mat.c0.z, mat.c0.w], [mat.c1.x, mat.c1.y, mat.c1.z, mat.c1.w], [mat.c2.x, mat.c2.y, mat.c2.z, mat.c2.w], [mat.c3.x, mat.c3.y, mat.c3.z, mat.c3.w]];
-}
\ No newline at end of file
+}
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::info;
+use crate::rendering::RenderingSystem;
+
+/// Saves screenshots and sequences of numbered frames, which are captured in a fixed interval.
+pub struct FrameCapture {
+	pub directory: PathBuf,
+	/// Seconds between two frames of a sequence.
+	pub sequence_interval: f64,
+	sequence: Option<FrameSequence>,
+}
+
+struct FrameSequence {
+	directory: PathBuf,
+	next_capture_time: f64,
+	frame: u32,
+}
+
+impl FrameCapture {
+	pub fn new(directory: PathBuf, sequence_interval: f64) -> Self {
+		FrameCapture {
+			directory,
+			sequence_interval,
+			sequence: None,
+		}
+	}
+
+	/// Save the next rendered frame as screenshot_<timestamp>.png.
+	pub fn take_screenshot(&self, rendering: &mut RenderingSystem) {
+		let path = self.directory.join(format!("screenshot_{}.png", get_timestamp()));
+		rendering.capture_frame(path);
+	}
+
+	/// Start a sequence in a new directory, or stop the current one.
+	pub fn toggle_sequence(&mut self, time: f64) {
+		match self.sequence.take() {
+			Some(sequence) => info!("Stopped frame sequence after {} frames in {}", sequence.frame, sequence.directory.display()),
+			None => {
+				let directory = self.directory.join(format!("sequence_{}", get_timestamp()));
+				info!("Started frame sequence in {}", directory.display());
+				self.sequence = Some(FrameSequence {
+					directory,
+					next_capture_time: time,
+					frame: 0,
+				});
+			}
+		}
+	}
+
+	/// Capture the upcoming frame if a sequence is recorded and the interval passed since the last captured frame.
+	pub fn update(&mut self, time: f64, rendering: &mut RenderingSystem) {
+		let sequence = match self.sequence.as_mut() {
+			Some(sequence) => sequence,
+			None => return,
+		};
+		if time < sequence.next_capture_time {
+			return;
+		}
+
+		rendering.capture_frame(sequence.directory.join(format!("frame_{:05}.png", sequence.frame)));
+		sequence.frame += 1;
+		// Skip the captures a slow frame missed instead of catching up on them.
+		// Without a positive interval every frame is captured.
+		if self.sequence_interval > 0.0 {
+			let missed_captures = ((time - sequence.next_capture_time) / self.sequence_interval).floor() + 1.0;
+			sequence.next_capture_time += missed_captures * self.sequence_interval;
+		}
+	}
+}
+
+fn get_timestamp() -> u128 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_millis())
+}
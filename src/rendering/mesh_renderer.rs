@@ -1,24 +1,37 @@
 use std::cell::RefCell;
 use std::rc::Rc;
+use resa_renderer::error::RenderError;
 use resa_renderer::material::{Material, MaterialRef};
+use resa_renderer::mesh::{Mesh, MeshHandle};
 use resa_renderer::ResaRenderer;
 
+/// Draws a mesh with a material. The mesh is kept alive by the mesh renderer, it is freed when the last
+/// mesh renderer of it is removed, unless it is a mesh of the resources.
 pub struct MeshRenderer {
-	pub mesh_id: u64,
+	mesh: MeshHandle,
 	pub(crate) material_id: Option<MaterialRef>,
 	resa_renderer: Rc<RefCell<ResaRenderer>>,
 }
 
 impl MeshRenderer {
-	#[allow(dead_code)]
-	pub(crate) fn new(mesh: u64, resa_renderer: Rc<RefCell<ResaRenderer>>) -> Self {
+	pub(crate) fn new(mesh: MeshHandle, resa_renderer: Rc<RefCell<ResaRenderer>>) -> Self {
 		Self {
-			mesh_id: mesh,
+			mesh,
 			material_id: None,
 			resa_renderer,
 		}
 	}
 
+	pub fn get_mesh_id(&self) -> u64 {
+		self.mesh.get_id()
+	}
+
+	/// Replace the mesh, for all mesh renderers which share it. Meshes which change often should be
+	/// created with `RenderingSystem::create_dynamic_mesh_renderer`.
+	pub fn update_mesh(&mut self, mesh: Mesh) -> Result<(), RenderError> {
+		self.resa_renderer.borrow_mut().update_mesh(&self.mesh, mesh)
+	}
+
 	pub fn set_material(&mut self, material_name: &str){
 		self.material_id = self.resa_renderer.borrow().get_material_ref_from_name(material_name);
 	}
@@ -1,52 +1,104 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 use winit::dpi::PhysicalSize;
+use log::warn;
 use winit::window::Window;
 use resa_ecs::world::World;
-use resa_renderer::{RendererConfig, ResaRenderer};
-use resa_renderer::material::{Color, Material, MaterialRef, Texture};
-use resa_renderer::mesh::Mesh;
+use resa_renderer::bounds::Frustum;
+use resa_renderer::error::RenderError;
+use resa_renderer::{PresentMode, RendererConfig, ResaRenderer};
+use resa_renderer::material::{Color, CullMode, Material, MaterialRef, PipelineState, SamplerSettings, Texture, TextureFormat};
+use resa_renderer::mesh::{Mesh, MeshHandle, MeshSharing};
+use resa_renderer::profiling::RenderTiming;
 use resa_renderer::render_stage::RenderStage;
+use resa_renderer::render_view::RenderView;
+use resa_renderer::vertex::{AttributeData, VertexAttribute};
 use crate::rendering::camera::Camera;
 use crate::rendering::mesh_renderer::MeshRenderer;
-use crate::rendering::transform::{make_transform_matrix, Transform};
-use crate::resources::loaded_resources::LoadedMaterial;
+use crate::rendering::render_layers::RenderLayers;
+use crate::rendering::transform::{get_world_matrix, make_transform_matrix, Transform};
+use crate::resources::loaded_resources::{LoadedImage, LoadedMaterial, LoadedMesh, LoadedPrimitive, LoadedScene, LoadedSceneMaterial};
 use crate::resources::ResourceManager;
 
 pub mod mesh_renderer;
 mod camera_system;
 pub mod transform;
 pub mod camera;
+pub mod render_layers;
+pub mod frame_capture;
+pub mod scene;
 
 
+/// Number of mesh renderers of the last frame which were drawn or skipped because they were outside of the camera view.
+/// A mesh renderer seen by multiple cameras is counted once per camera.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RenderStats {
+	pub drawn_objects: u32,
+	pub culled_objects: u32,
+}
+
 pub struct RenderingSystem {
 	resa_renderer: Rc<RefCell<ResaRenderer>>,
 	reconfigure_swapchain: bool,
-	frames_drawn: u32,
-
+	extent: PhysicalSize<u32>,
+	stats: RenderStats,
+	/// The meshes of the resources by name, with the material they are drawn with. They stay loaded while the system exists.
+	mesh_assets: HashMap<String, (MeshHandle, Option<String>)>,
+	/// The mesh and material of every primitive of the scenes which were spawned, by scene name
+	scene_assets: HashMap<String, Vec<Vec<Option<(MeshHandle, Option<MaterialRef>)>>>>,
 }
 
 impl RenderingSystem {
-	pub fn new(window: &Window, size: PhysicalSize<u32>, resources: &ResourceManager) -> RenderingSystem {
+	pub fn new(window: &Window, size: PhysicalSize<u32>, present_mode: PresentMode, resources: &ResourceManager) -> Result<RenderingSystem, RenderError> {
 
 		let shaders = resources.get_shaders();
-		let materials = RenderingSystem::load_materials(&resources.get_materials());
+		let loaded_materials = resources.get_materials();
+		let materials = RenderingSystem::load_materials(&loaded_materials);
 		let config = RendererConfig{
 			extent: size,
 			shaders,
+			present_mode,
 		};
-		let mut renderer = ResaRenderer::new(window, config);
+		let mut renderer = ResaRenderer::new(window, config)?;
+		RenderingSystem::register_textures(&mut renderer, &loaded_materials, resources);
 		renderer.register_materials(&materials);
 
-		RenderingSystem {
+		let mut mesh_assets = HashMap::new();
+		for loaded_mesh in resources.get_meshes() {
+			let mesh = match RenderingSystem::create_mesh(&loaded_mesh) {
+				Ok(mesh) => mesh,
+				Err(e) => {
+					warn!("Skipped mesh {}: {}", loaded_mesh.name, e);
+					continue;
+				}
+			};
+			mesh_assets.insert(loaded_mesh.name, (renderer.register_mesh(mesh, MeshSharing::Shared), loaded_mesh.material));
+		}
+
+		Ok(RenderingSystem {
 			resa_renderer: Rc::new(RefCell::new(renderer)),
 			reconfigure_swapchain: true,
-			frames_drawn: 0,
-		}
+			extent: size,
+			stats: RenderStats::default(),
+			mesh_assets,
+			scene_assets: HashMap::new(),
+		})
 	}
 
-	pub fn set_dirty(&mut self) {
-		self.reconfigure_swapchain = true;
+	pub fn resize(&mut self, size: PhysicalSize<u32>) {
+		self.extent = size;
+		self.resa_renderer.borrow_mut().resize(size);
+	}
+
+	/// Size of the window in pixels, as needed by the screen conversions of the cameras.
+	pub fn get_window_size(&self) -> PhysicalSize<u32> {
+		self.extent
+	}
+
+	pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+		self.resa_renderer.borrow_mut().set_present_mode(present_mode);
 	}
 
 	pub fn render(&mut self, world: &Rc<RefCell<World>>) {
@@ -56,53 +108,254 @@ impl RenderingSystem {
 		}
 
 		let world_binding = world.borrow();
-		let meshes = world_binding.get_all_components_of_type::<MeshRenderer>().unwrap();
+		let meshes = world_binding.get_all_components_of_type::<MeshRenderer>().unwrap_or_default();
 
+		// Meshes without bounds are drawn, the renderer reports unknown meshes itself
 		let mut mesh_data = vec![];
 		for (mesh, entity) in meshes.iter() {
-			let transform = if let Some(t) = world.borrow().get_component::<Transform>(&entity) {
-				make_transform_matrix(&t)
-			} else {
-				make_transform_matrix(&Transform::idle())
-			};
+			let transform = get_world_matrix(&world_binding, &entity);
+			let layers = world_binding.get_component::<RenderLayers>(&entity).copied().unwrap_or_default();
+			let bounds = self.resa_renderer.borrow().get_mesh_bounds(&mesh.get_mesh_id()).map(|bounds| bounds.transform(&transform));
 			let mat_id = mesh.get_material_ref().unwrap_or(MaterialRef::default());
-			mesh_data.push((mesh.mesh_id, mat_id, transform))
+			mesh_data.push((mesh.get_mesh_id(), mat_id, transform, layers, bounds));
 		}
 
-		let (camera, cam_entity) = world_binding.get_all_components_of_type::<Camera>().unwrap()[0];
-		let cam_transform = world_binding.get_component::<Transform>(&cam_entity).unwrap();
+		// Cameras with a higher priority are drawn later, on top of the others
+		let mut cameras = world_binding.get_all_components_of_type::<Camera>().unwrap_or_default();
+		cameras.sort_by_key(|(camera, _)| camera.priority);
 
-		let view_matrix = camera_system::get_camera_view_matrix(&cam_transform);
-		let proj_matrix = camera_system::get_camera_projection_matrix(&camera);
+		self.stats = RenderStats::default();
+		let mut views = vec![];
+		for (camera, cam_entity) in cameras.iter() {
+			// A camera without transform has the identity view, it looks from the origin along the negative z axis
+			let view_matrix = match world_binding.get_component::<Transform>(&cam_entity) {
+				Some(cam_transform) => camera_system::get_camera_view_matrix(&cam_transform),
+				None => make_transform_matrix(&Transform::idle()),
+			};
+			let proj_matrix = camera_system::get_camera_projection_matrix(&camera, camera.get_viewport_size(self.extent));
+			let frustum = Frustum::from_view_projection(&view_matrix, &proj_matrix);
+
+			let mut render_objects = vec![];
+			for (mesh_id, mat_id, transform, layers, bounds) in mesh_data.iter() {
+				if !camera.layers.intersects(layers) {
+					continue;
+				}
+				if let Some(bounds) = bounds {
+					if !frustum.intersects(bounds) {
+						self.stats.culled_objects += 1;
+						continue;
+					}
+				}
+				render_objects.push((*mesh_id, *mat_id, *transform));
+				self.stats.drawn_objects += 1;
+			}
 
-		self.resa_renderer.borrow_mut().render(&mesh_data, view_matrix, proj_matrix);
-		self.frames_drawn += 1;
-		if self.frames_drawn % 10 == 0 {
-			self.frames_drawn = 0;
-			// println!("{}", self.resa_renderer.get_fps());
+			views.push(RenderView {
+				view_mat: view_matrix,
+				projection_mat: proj_matrix,
+				viewport: camera.viewport,
+				clear_color: camera.clear_color,
+				render_objects,
+			});
 		}
+
+		self.resa_renderer.borrow_mut().render(&views);
 	}
 
-	pub fn create_mesh_renderer(&mut self, mesh: Mesh) -> MeshRenderer {
-		let mesh_id = self.resa_renderer.borrow_mut().register_mesh(mesh);
-		MeshRenderer::new(mesh_id, self.resa_renderer.clone())
+	pub fn get_stats(&self) -> RenderStats {
+		self.stats
+	}
+
+	pub fn get_frame_timings(&self) -> Vec<RenderTiming> {
+		self.resa_renderer.borrow().get_frame_timings().to_vec()
+	}
+
+	/// Save the next rendered frame as png.
+	pub fn capture_frame(&mut self, path: PathBuf) {
+		self.resa_renderer.borrow_mut().capture_frame(path);
+	}
+
+	/// Mesh renderer of a new mesh. Shared meshes use the buffers of an existing shared mesh with the same content.
+	pub fn create_mesh_renderer(&mut self, mesh: Mesh, sharing: MeshSharing) -> MeshRenderer {
+		let mesh_handle = self.resa_renderer.borrow_mut().register_mesh(mesh, sharing);
+		MeshRenderer::new(mesh_handle, self.resa_renderer.clone())
+	}
+
+	/// Mesh renderer of a mesh which changes often, e.g. every frame, like debug geometry, terrain which is edited or particles.
+	pub fn create_dynamic_mesh_renderer(&mut self, mesh: Mesh) -> MeshRenderer {
+		let mesh_handle = self.resa_renderer.borrow_mut().register_dynamic_mesh(mesh);
+		MeshRenderer::new(mesh_handle, self.resa_renderer.clone())
+	}
+
+	/// Mesh renderer of a mesh of the resources, drawn with the material of its mtl file if it has one.
+	/// All mesh renderers of an asset share its mesh. None if there is no mesh with the name.
+	pub fn create_mesh_renderer_from_asset(&mut self, name: &str) -> Option<MeshRenderer> {
+		let (mesh_handle, material) = self.mesh_assets.get(name)?;
+		let mut mesh_renderer = MeshRenderer::new(mesh_handle.clone(), self.resa_renderer.clone());
+		if let Some(material) = material {
+			mesh_renderer.set_material(material);
+		}
+		Some(mesh_renderer)
+	}
+
+	pub(crate) fn create_mesh_renderer_with_material(&mut self, mesh_handle: MeshHandle, material: Option<MaterialRef>) -> MeshRenderer {
+		let mut mesh_renderer = MeshRenderer::new(mesh_handle, self.resa_renderer.clone());
+		mesh_renderer.material_id = material;
+		mesh_renderer
+	}
+
+	/// Register the meshes and materials of a scene the first time it is spawned.
+	/// Returns the mesh and material of every primitive, in the order of the meshes of the scene.
+	/// Primitives which could not be registered are None, so the indices match the primitives of the scene.
+	pub(crate) fn register_scene(&mut self, scene: &LoadedScene) -> Vec<Vec<Option<(MeshHandle, Option<MaterialRef>)>>> {
+		if let Some(primitives) = self.scene_assets.get(&scene.name) {
+			return primitives.clone();
+		}
+
+		let materials: Vec<Material> = scene.materials.iter().map(|material| RenderingSystem::create_scene_material(scene, material)).collect();
+		let material_refs = self.resa_renderer.borrow_mut().register_materials(&materials);
+
+		let mut primitives = vec![];
+		for (mesh_index, mesh) in scene.meshes.iter().enumerate() {
+			let mut mesh_primitives = vec![];
+			for primitive in mesh.iter() {
+				let mesh = match RenderingSystem::create_primitive_mesh(primitive) {
+					Ok(mesh) => mesh,
+					Err(e) => {
+						warn!("Skipped primitive of mesh {} in scene {}: {}", mesh_index, scene.name, e);
+						mesh_primitives.push(None);
+						continue;
+					}
+				};
+				let mesh_handle = self.resa_renderer.borrow_mut().register_mesh(mesh, MeshSharing::Shared);
+				mesh_primitives.push(Some((mesh_handle, primitive.material.and_then(|material| material_refs.get(material).copied()))));
+			}
+			primitives.push(mesh_primitives);
+		}
+
+		self.scene_assets.insert(scene.name.clone(), primitives.clone());
+		primitives
+	}
+
+	fn create_mesh(loaded_mesh: &LoadedMesh) -> Result<Mesh, RenderError> {
+		let mut mesh = Mesh::from_positions(loaded_mesh.positions.clone(), loaded_mesh.indices.clone());
+		mesh.set_attribute(VertexAttribute::Normal, AttributeData::Float3(loaded_mesh.normals.clone()))?;
+		if let Some(uvs) = &loaded_mesh.uvs {
+			mesh.set_attribute(VertexAttribute::Uv0, AttributeData::Float2(uvs.clone()))?;
+		}
+		RenderingSystem::add_missing_uvs(&mut mesh)?;
+		Ok(mesh)
+	}
+
+	fn create_primitive_mesh(primitive: &LoadedPrimitive) -> Result<Mesh, RenderError> {
+		let mut mesh = Mesh::non_indexed(primitive.positions.clone());
+		mesh.set_indices(primitive.indices.clone());
+		mesh.set_topology(primitive.topology);
+		for (attribute, data) in primitive.attributes.iter() {
+			mesh.set_attribute(*attribute, data.clone())?;
+		}
+		RenderingSystem::add_missing_uvs(&mut mesh)?;
+		Ok(mesh)
+	}
+
+	/// The base shader reads the uvs, so meshes without them get a uv of zero everywhere.
+	fn add_missing_uvs(mesh: &mut Mesh) -> Result<(), RenderError> {
+		if mesh.get_attribute(VertexAttribute::Uv0).is_none() {
+			mesh.set_attribute(VertexAttribute::Uv0, AttributeData::Float2(vec![[0.0, 0.0]; mesh.get_vertex_count()]))?;
+		}
+		Ok(())
+	}
+
+	/// Materials of a scene are named after the scene, so they do not collide with the materials of the resources.
+	fn create_scene_material(scene: &LoadedScene, material: &LoadedSceneMaterial) -> Material {
+		let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+		let [r, g, b, a] = material.base_color;
+		let texture = match material.base_color_texture.and_then(|image| scene.images.get(image)) {
+			Some(image) => Texture::Pending(image.rgba.clone(), TextureFormat::Custom((image.width, image.height)), material.base_color_sampler),
+			None => Texture::None,
+		};
+		Material {
+			name: format!("{}/{}", scene.name, material.name),
+			shader_id: 0,
+			render_stage: if material.alpha_blend { RenderStage::Transparent } else { RenderStage::Opaque },
+			// glTF faces are counter clockwise, which the flipped y axis of the projection turns into the clockwise front faces of the pipelines
+			pipeline_state: PipelineState {
+				cull: if material.double_sided { CullMode::None } else { CullMode::Back },
+				..PipelineState::default()
+			},
+			color: Color::new(to_byte(r), to_byte(g), to_byte(b), to_byte(a)),
+			texture,
+		}
+	}
+
+	/// Register a material which is created at runtime instead of loaded from the resources.
+	pub fn register_material(&mut self, material: Material) -> MaterialRef {
+		self.resa_renderer.borrow_mut().register_materials(&[material])[0]
+	}
+
+	/// Register a texture which materials reference by name, registering it again replaces its image and sampler settings.
+	pub fn register_texture(&mut self, name: &str, data: Vec<u8>, format: TextureFormat, sampler: SamplerSettings) {
+		self.resa_renderer.borrow_mut().register_texture(name, data, format, sampler);
+	}
+
+	/// Replace the image of a texture of the resources, for all materials which use it.
+	pub fn update_texture(&mut self, name: &str, image: &LoadedImage) {
+		self.resa_renderer.borrow_mut().update_texture(name, image.image_data.clone(), image.image_format.clone());
+	}
+
+	/// Register the images the materials reference as textures, each of them once.
+	/// Materials whose image does not exist are drawn with the missing texture of the renderer.
+	/// The sampler settings of a material replace the ones of its image, for all materials which use the image.
+	fn register_textures(renderer: &mut ResaRenderer, loaded_materials: &[LoadedMaterial], resources: &ResourceManager) {
+		let mut texture_names: Vec<&str> = loaded_materials.iter().map(|material| material.texture.as_str()).filter(|name| !name.is_empty()).collect();
+		texture_names.sort_unstable();
+		texture_names.dedup();
+		for name in texture_names {
+			let image = match resources.get_image(name, false) {
+				Some(image) => image,
+				None => {
+					warn!("There is no image {} in the resources", name);
+					continue;
+				}
+			};
+
+			let mut material_samplers = loaded_materials.iter().filter(|material| material.texture == name).filter_map(|material| material.sampler.map(|sampler| (&material.name, sampler)));
+			let sampler = match material_samplers.next() {
+				Some((material_name, sampler)) => {
+					if material_samplers.any(|(_, other)| other != sampler) {
+						warn!("Materials sample the texture {} differently, it is sampled like in material {}", name, material_name);
+					}
+					sampler
+				}
+				None => image.sampler,
+			};
+			renderer.register_texture(name, image.image_data, image.image_format, sampler);
+		}
 	}
 
 	fn load_materials(loaded_materials: &[LoadedMaterial]) -> Vec<Material> {
-		let materials: Vec<Material> = loaded_materials.iter().map(|loaded_mat|
-			Material {
+		let materials: Vec<Material> = loaded_materials.iter().filter_map(|loaded_mat| {
+			let render_stage = match RenderStage::get_stage_form_index(loaded_mat.stage) {
+				Ok(render_stage) => render_stage,
+				Err(e) => {
+					warn!("Skipped material {}: {}", loaded_mat.name, e);
+					return None;
+				}
+			};
+			Some(Material {
 				name: loaded_mat.name.clone(),
 				shader_id: loaded_mat.shader.clone() as u32,
-				render_stage: RenderStage::get_stage_form_index(loaded_mat.stage),
+				render_stage,
+				pipeline_state: loaded_mat.pipeline_state,
 				color: Color {
 					r: loaded_mat.color[0],
 					g: loaded_mat.color[1],
 					b: loaded_mat.color[2],
 					a: loaded_mat.color[3],
 				},
-				texture: Texture::None /*if loaded_mat.texture.len() == 0 { Texture::None } else {Texture::Pending(loaded_mat.texture)}*/,
-			}
-		).collect();
+				texture: if loaded_mat.texture.is_empty() { Texture::None } else { Texture::Named(loaded_mat.texture.clone()) },
+			})
+		}).collect();
 		materials
 
 		// self.resa_renderer.borrow_mut().register_materials(&materials)
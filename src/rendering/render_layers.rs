@@ -0,0 +1,32 @@
+/// The layers an entity is drawn on, as bit mask of up to 32 layers. Cameras only draw entities which
+/// share at least one layer with them. Entities without this component are on the default layer 0.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RenderLayers(pub u32);
+
+impl RenderLayers {
+    pub const ALL: RenderLayers = RenderLayers(u32::MAX);
+    pub const NONE: RenderLayers = RenderLayers(0);
+
+    /// Only the given layer, layers above 31 do not exist and result in no layer.
+    pub fn layer(layer: u32) -> Self {
+        RenderLayers(1u32.checked_shl(layer).unwrap_or(0))
+    }
+
+    pub fn with(self, layer: u32) -> Self {
+        RenderLayers(self.0 | RenderLayers::layer(layer).0)
+    }
+
+    pub fn contains(&self, layer: u32) -> bool {
+        self.0 & RenderLayers::layer(layer).0 != 0
+    }
+
+    pub fn intersects(&self, other: &RenderLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        RenderLayers::layer(0)
+    }
+}
@@ -0,0 +1,125 @@
+use resa_ecs::entity::Entity;
+use resa_ecs::world::World;
+use crate::rendering::RenderingSystem;
+use crate::rendering::transform::{Parent, Transform};
+use crate::resources::loaded_resources::LoadedScene;
+
+/// The entities of a spawned scene. The skins and animations of the scene reference their nodes
+/// by index, `nodes` maps them to the entities.
+pub struct SceneInstance {
+	/// Parent of the root nodes, moving it moves the whole scene
+	pub root: Entity,
+	/// The entity of every node of the scene file, None for nodes which are not part of the scene
+	pub nodes: Vec<Option<Entity>>,
+}
+
+/// Spawn the node hierarchy of a scene as entities with a `Transform` and a `Parent`.
+/// Nodes with a mesh get a `MeshRenderer` for their primitive, or one child entity per primitive if they have several.
+/// Without rendering system the same entities are spawned, only the mesh renderers are left out.
+pub fn instantiate_scene(world: &mut World, mut rendering: Option<&mut RenderingSystem>, scene: &LoadedScene) -> SceneInstance {
+	let scene_primitives = match rendering.as_mut() {
+		Some(rendering) => rendering.register_scene(scene),
+		None => vec![],
+	};
+
+	spawn_nodes(world, scene, |world, entity, mesh, primitive| {
+		let registered = scene_primitives.get(mesh).and_then(|primitives| primitives.get(primitive)).cloned().flatten();
+		if let (Some(rendering), Some((mesh_handle, material))) = (rendering.as_mut(), registered) {
+			world.add_component(entity, rendering.create_mesh_renderer_with_material(mesh_handle, material)).unwrap();
+		}
+	})
+}
+
+/// Spawn the entities of the nodes and of their primitives. `attach_primitive` gets the entity, mesh and primitive index
+/// of every primitive to add the components which draw it. The entities do not depend on what it adds, so replays
+/// of headless worlds match the worlds of the app.
+fn spawn_nodes(world: &mut World, scene: &LoadedScene, mut attach_primitive: impl FnMut(&mut World, &Entity, usize, usize)) -> SceneInstance {
+	let root = world.new_entity();
+	world.add_component(&root, Transform::idle()).unwrap();
+
+	let mut nodes = vec![None; scene.nodes.len()];
+	let mut pending: Vec<(usize, Entity)> = scene.root_nodes.iter().rev().map(|node| (*node, root)).collect();
+	while let Some((node_index, parent)) = pending.pop() {
+		// A node can only have one parent, files that reuse nodes are spawned as far as they are valid
+		if nodes[node_index].is_some() {
+			continue;
+		}
+		let node = &scene.nodes[node_index];
+		let entity = world.new_entity();
+		nodes[node_index] = Some(entity);
+
+		let transform = Transform {
+			position: node.translation,
+			angle: 0.0,
+			rotation: node.rotation,
+			scale: node.scale,
+		};
+		world.add_component(&entity, transform).unwrap();
+		world.add_component(&entity, Parent(parent)).unwrap();
+
+		if let Some(mesh) = node.mesh {
+			let primitive_count = scene.meshes.get(mesh).map_or(0, Vec::len);
+			if primitive_count == 1 {
+				attach_primitive(world, &entity, mesh, 0);
+			} else {
+				for primitive in 0..primitive_count {
+					let primitive_entity = world.new_entity();
+					world.add_component(&primitive_entity, Transform::idle()).unwrap();
+					world.add_component(&primitive_entity, Parent(entity)).unwrap();
+					attach_primitive(world, &primitive_entity, mesh, primitive);
+				}
+			}
+		}
+
+		pending.extend(node.children.iter().rev().map(|child| (*child, entity)));
+	}
+
+	SceneInstance {
+		root,
+		nodes,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use resa_ecs::world::World;
+	use crate::create_world_hasher;
+	use crate::rendering::scene::{instantiate_scene, spawn_nodes};
+	use crate::rendering::transform::{Parent, Transform};
+	use crate::resources::ResourceManager;
+
+	#[test]
+	fn nodes_are_spawned_below_their_parents() {
+		let scene = ResourceManager::new().unwrap().get_scene("sample").unwrap();
+		let mut world = World::new();
+		let instance = instantiate_scene(&mut world, None, &scene);
+
+		let quad = instance.nodes[0].unwrap();
+		let spinning_quad = instance.nodes[1].unwrap();
+		assert_eq!(world.get_component::<Parent>(&quad), Some(&Parent(instance.root)));
+		assert_eq!(world.get_component::<Parent>(&spinning_quad), Some(&Parent(quad)));
+		assert!(world.get_component::<Parent>(&instance.root).is_none());
+		assert_eq!(world.get_component::<Transform>(&spinning_quad).unwrap().position, [0.0, 1.2, 0.0]);
+	}
+
+	#[test]
+	fn scenes_have_the_same_entities_with_and_without_rendering() {
+		let scene = ResourceManager::new().unwrap().get_scene("sample").unwrap();
+		let mut headless = World::new();
+		instantiate_scene(&mut headless, None, &scene);
+
+		// Stands in for the mesh renderers, which need a gpu
+		let mut drawn = World::new();
+		let mut drawn_primitives = vec![];
+		spawn_nodes(&mut drawn, &scene, |world, entity, mesh, primitive| {
+			world.add_component(entity, (mesh, primitive)).unwrap();
+			drawn_primitives.push(*entity);
+		});
+
+		// Both nodes use the mesh with two primitives, which are spawned as children
+		assert_eq!(drawn_primitives.len(), 4);
+		assert_eq!(headless.get_all_components_of_type::<Transform>().unwrap().len(), 7);
+		let world_hasher = create_world_hasher();
+		assert_eq!(world_hasher.hash(&headless), world_hasher.hash(&drawn));
+	}
+}
@@ -1,7 +1,20 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use log::warn;
+use resa_ecs::entity::Entity;
+use resa_ecs::world::World;
+use crate::replay::world_hasher::ReplayHash;
+
+/// Parent chains longer than this are treated as cycle.
+const MAX_HIERARCHY_DEPTH: usize = 256;
+
 pub struct Transform {
     pub position: [f32; 3],
+    /// Rotation around the y axis in radians, applied after `rotation`.
     pub angle: f32,
-    pub scale: f32,
+    /// Rotation as quaternion x, y, z, w.
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
 }
 
 impl Transform {
@@ -9,20 +22,77 @@ impl Transform {
         Transform{
             position: [0.0, 0.0, 0.0],
             angle: 0.0,
-            scale: 1.0,
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            scale: [1.0, 1.0, 1.0],
         }
     }
 }
 
+impl ReplayHash for Transform {
+    fn replay_hash(&self, state: &mut DefaultHasher) {
+        for value in self.position.iter().chain(self.rotation.iter()).chain(self.scale.iter()) {
+            value.to_bits().hash(state);
+        }
+        self.angle.to_bits().hash(state);
+    }
+}
+
+/// The transform of an entity with a parent is relative to the transform of the parent.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Parent(pub Entity);
+
 pub fn make_transform_matrix(transform: &Transform) -> [[f32; 4]; 4]{
-    let c = transform.angle.cos() * transform.scale;
-    let s = transform.angle.sin() * transform.scale;
+    let c = transform.angle.cos();
+    let s = transform.angle.sin();
     let [dx, dy, dz] = transform.position;
 
-    [
+    let yaw = [
         [c, 0.0, s, 0.0],
-        [0.0, transform.scale, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
         [-s, 0.0, c, 0.0],
         [dx, dy, dz, 1.0],
-    ]
-}
\ No newline at end of file
+    ];
+    let [x, y, z, w] = transform.rotation;
+    let [sx, sy, sz] = transform.scale;
+    let rotation_scale = [
+        [(1.0 - 2.0 * (y * y + z * z)) * sx, 2.0 * (x * y + z * w) * sx, 2.0 * (x * z - y * w) * sx, 0.0],
+        [2.0 * (x * y - z * w) * sy, (1.0 - 2.0 * (x * x + z * z)) * sy, 2.0 * (y * z + x * w) * sy, 0.0],
+        [2.0 * (x * z + y * w) * sz, 2.0 * (y * z - x * w) * sz, (1.0 - 2.0 * (x * x + y * y)) * sz, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    multiply_matrices(&yaw, &rotation_scale)
+}
+
+/// The transform of the entity combined with the transforms of all its parents.
+/// Entities without transform are at the origin of their parent.
+pub fn get_world_matrix(world: &World, entity: &Entity) -> [[f32; 4]; 4] {
+    let local_matrix = |entity: &Entity| match world.get_component::<Transform>(entity) {
+        Some(transform) => make_transform_matrix(transform),
+        None => make_transform_matrix(&Transform::idle()),
+    };
+
+    let mut matrix = local_matrix(entity);
+    let mut current = *entity;
+    for _ in 0..MAX_HIERARCHY_DEPTH {
+        match world.get_component::<Parent>(&current) {
+            Some(Parent(parent)) => {
+                matrix = multiply_matrices(&local_matrix(parent), &matrix);
+                current = *parent;
+            }
+            None => return matrix,
+        }
+    }
+    warn!("The parents of entity {} form a cycle", entity.0);
+    matrix
+}
+
+/// Product of two column major matrices, b is applied first.
+pub fn multiply_matrices(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut result = [[0.0; 4]; 4];
+    for (column, b_column) in result.iter_mut().zip(b) {
+        for (row, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+        }
+    }
+    result
+}
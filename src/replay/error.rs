@@ -0,0 +1,29 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum ReplayError {
+	Io { path: PathBuf, source: io::Error },
+	Encoding { path: PathBuf, source: bincode::Error },
+}
+
+impl fmt::Display for ReplayError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			ReplayError::Io { path, source } => write!(f, "Could not access recording {}: {}", path.display(), source),
+			ReplayError::Encoding { path, source } => write!(f, "Could not encode or decode recording {}: {}", path.display(), source),
+		}
+	}
+}
+
+impl Error for ReplayError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			ReplayError::Io { source, .. } => Some(source),
+			ReplayError::Encoding { source, .. } => Some(source),
+		}
+	}
+}
@@ -0,0 +1,272 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use resa_ecs::schedule::Schedule;
+use resa_ecs::world::World;
+use crate::input::input_events::InputEvent;
+use crate::replay::error::ReplayError;
+use crate::replay::world_hasher::WorldHasher;
+use crate::time::Time;
+use crate::window::window_events::WindowEvent;
+
+pub mod error;
+pub mod world_hasher;
+
+/// Everything the systems received in one frame, and the hash of the world after the systems ran.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedFrame {
+	pub delta_seconds: f64,
+	pub input_events: Vec<InputEvent>,
+	pub window_events: Vec<WindowEvent>,
+	pub state_hash: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Recording {
+	pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+	pub fn load(path: &Path) -> Result<Self, ReplayError> {
+		let file = File::open(path).map_err(|source| ReplayError::Io { path: path.to_path_buf(), source })?;
+		bincode::deserialize_from(BufReader::new(file)).map_err(|source| ReplayError::Encoding { path: path.to_path_buf(), source })
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+		let file = File::create(path).map_err(|source| ReplayError::Io { path: path.to_path_buf(), source })?;
+		bincode::serialize_into(BufWriter::new(file), self).map_err(|source| ReplayError::Encoding { path: path.to_path_buf(), source })
+	}
+}
+
+/// Records the frames of a running app, the recording is written to the path when the app shuts down.
+pub struct Recorder {
+	path: PathBuf,
+	world_hasher: WorldHasher,
+	recording: Recording,
+}
+
+impl Recorder {
+	pub fn new(path: PathBuf, world_hasher: WorldHasher) -> Self {
+		Recorder {
+			path,
+			world_hasher,
+			recording: Recording::default(),
+		}
+	}
+
+	/// Record the events of the current frame. Has to be called after the systems ran and before the events are cleared.
+	pub fn record_frame(&mut self, world: &World, delta_seconds: f64) {
+		self.recording.frames.push(RecordedFrame {
+			delta_seconds,
+			input_events: get_events::<InputEvent>(world),
+			window_events: get_events::<WindowEvent>(world),
+			state_hash: self.world_hasher.hash(world),
+		});
+	}
+
+	pub fn get_path(&self) -> &Path {
+		&self.path
+	}
+
+	pub fn save(&self) -> Result<(), ReplayError> {
+		self.recording.save(&self.path)
+	}
+}
+
+/// The first frame in which the hash of the replayed world differs from the recorded one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Divergence {
+	pub frame: usize,
+	pub expected_hash: u64,
+	pub actual_hash: u64,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ReplayReport {
+	pub frames_replayed: usize,
+	pub divergence: Option<Divergence>,
+}
+
+/// Replay a recording without window and renderer. Every frame advances the time by the recorded delta,
+/// sends the recorded events and runs the schedule. Stops at the first frame that diverges from the recording.
+pub fn replay(recording: &Recording, world: &mut World, schedule: &mut Schedule, world_hasher: &WorldHasher) -> ReplayReport {
+	for (index, frame) in recording.frames.iter().enumerate() {
+		if let Some(time) = world.get_resource_mut::<Time>() {
+			time.advance(frame.delta_seconds);
+		}
+		for event in frame.window_events.iter() {
+			world.send_event(event.clone());
+		}
+		for event in frame.input_events.iter() {
+			world.send_event(event.clone());
+		}
+
+		schedule.run(world);
+		let actual_hash = world_hasher.hash(world);
+		world.clear_events();
+
+		if actual_hash != frame.state_hash {
+			return ReplayReport {
+				frames_replayed: index + 1,
+				divergence: Some(Divergence {
+					frame: index,
+					expected_hash: frame.state_hash,
+					actual_hash,
+				}),
+			};
+		}
+	}
+
+	ReplayReport {
+		frames_replayed: recording.frames.len(),
+		divergence: None,
+	}
+}
+
+fn get_events<EventType: Clone + 'static>(world: &World) -> Vec<EventType> {
+	match world.get_events::<EventType>() {
+		Some(events) => events.iter().cloned().collect(),
+		None => vec![],
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::hash_map::DefaultHasher;
+	use std::env;
+	use std::hash::Hash;
+	use std::path::PathBuf;
+	use winit::event::{ElementState, MouseButton};
+	use resa_ecs::schedule::{Schedule, Stage};
+	use resa_ecs::world::World;
+	use crate::input::input_events::InputEvent;
+	use crate::replay::{replay, Divergence, RecordedFrame, Recorder, Recording};
+	use crate::replay::world_hasher::{ReplayHash, WorldHasher};
+	use crate::resa_app::ResaApp;
+	use crate::time::Time;
+	use crate::window::window_events::WindowEvent;
+	use crate::window::window_settings::WindowSettings;
+
+	struct Position(f64);
+
+	impl ReplayHash for Position {
+		fn replay_hash(&self, state: &mut DefaultHasher) {
+			self.0.to_bits().hash(state);
+		}
+	}
+
+	/// Moves all positions by the delta time, faster for every input event of the frame.
+	fn move_positions(world: &mut World) {
+		let delta_seconds = world.get_resource::<Time>().map_or(0.0, |time| time.delta_seconds);
+		let speed = 1.0 + world.get_events::<InputEvent>().map_or(0, |events| events.iter().count()) as f64;
+		if let Some(positions) = world.get_all_components_of_type_mut::<Position>() {
+			for (position, _) in positions {
+				position.0 += delta_seconds * speed;
+			}
+		}
+	}
+
+	fn create_world() -> World {
+		let mut world = ResaApp::create_world(&WindowSettings::default());
+		for start in [0.0, 10.0] {
+			let entity = world.new_entity();
+			world.add_component(&entity, Position(start)).unwrap();
+		}
+		world
+	}
+
+	fn create_schedule() -> Schedule {
+		let mut schedule = Schedule::new();
+		schedule.add_system(Stage::Update, "move_positions", move_positions);
+		schedule
+	}
+
+	fn create_world_hasher() -> WorldHasher {
+		let mut world_hasher = WorldHasher::new();
+		world_hasher.add_component::<Position>();
+		world_hasher
+	}
+
+	fn get_frames() -> Vec<(f64, Vec<InputEvent>)> {
+		vec![
+			(0.016, vec![]),
+			(0.017, vec![InputEvent::CursorMoved { x: 12.5, y: 40.0 }]),
+			(0.015, vec![InputEvent::CursorEntered, InputEvent::CharacterReceived('r')]),
+			(0.016, vec![]),
+			(0.018, vec![InputEvent::MouseWheel { delta_x: 0.0, delta_y: -1.0, in_pixels: false }]),
+		]
+	}
+
+	/// Run the frames like the app does and record them.
+	fn record(world: &mut World, schedule: &mut Schedule) -> Recording {
+		let mut recorder = Recorder::new(PathBuf::new(), create_world_hasher());
+		for (delta_seconds, input_events) in get_frames() {
+			world.get_resource_mut::<Time>().unwrap().advance(delta_seconds);
+			for event in input_events {
+				world.send_event(event);
+			}
+			schedule.run(world);
+			recorder.record_frame(world, delta_seconds);
+			world.clear_events();
+		}
+		recorder.recording
+	}
+
+	#[test]
+	fn recordings_keep_their_frames_when_saved() {
+		let mut recording = record(&mut create_world(), &mut create_schedule());
+		recording.frames[1].window_events = vec![WindowEvent::Resized { width: 640, height: 360 }, WindowEvent::Focused(false)];
+		recording.frames[3].input_events.push(InputEvent::MouseButton { button: MouseButton::Left, state: ElementState::Pressed });
+
+		let path = env::temp_dir().join(format!("resa_replay_test_{}.bin", std::process::id()));
+		recording.save(&path).unwrap();
+		let loaded = Recording::load(&path);
+		std::fs::remove_file(&path).unwrap();
+		let loaded = loaded.unwrap();
+
+		assert_eq!(loaded.frames.len(), recording.frames.len());
+		for (loaded, saved) in loaded.frames.iter().zip(recording.frames.iter()) {
+			let RecordedFrame { delta_seconds, input_events, window_events, state_hash } = saved;
+			assert_eq!(loaded.delta_seconds.to_bits(), delta_seconds.to_bits());
+			assert_eq!(&loaded.input_events, input_events);
+			assert_eq!(&loaded.window_events, window_events);
+			assert_eq!(loaded.state_hash, *state_hash);
+		}
+	}
+
+	#[test]
+	fn unchanged_simulations_replay_without_divergence() {
+		let recording = record(&mut create_world(), &mut create_schedule());
+		// The positions change every frame, so every frame has its own hash
+		assert!(recording.frames.windows(2).all(|frames| frames[0].state_hash != frames[1].state_hash));
+
+		let report = replay(&recording, &mut create_world(), &mut create_schedule(), &create_world_hasher());
+		assert_eq!(report.frames_replayed, recording.frames.len());
+		assert_eq!(report.divergence, None);
+	}
+
+	#[test]
+	fn replays_report_the_first_frame_that_diverges() {
+		let recording = record(&mut create_world(), &mut create_schedule());
+
+		// The third frame, time advances before the systems run
+		let mut schedule = create_schedule();
+		schedule.add_system(Stage::Update, "nudge", |world| {
+			if world.get_resource::<Time>().unwrap().frame == 3 {
+				let positions = world.get_all_components_of_type_mut::<Position>().unwrap();
+				positions.into_iter().next().unwrap().0.0 += 1e-9;
+			}
+		});
+		let mut world = create_world();
+		let report = replay(&recording, &mut world, &mut schedule, &create_world_hasher());
+
+		assert_eq!(report.frames_replayed, 3);
+		assert_eq!(report.divergence, Some(Divergence {
+			frame: 2,
+			expected_hash: recording.frames[2].state_hash,
+			actual_hash: create_world_hasher().hash(&world),
+		}));
+		assert_ne!(recording.frames[2].state_hash, create_world_hasher().hash(&world));
+	}
+}
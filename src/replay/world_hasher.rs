@@ -0,0 +1,116 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use resa_ecs::world::World;
+
+/// Components that take part in the determinism check of a replay.
+/// Floats have to be hashed by their bits, so the hash only matches if the values are bit identical.
+pub trait ReplayHash {
+	fn replay_hash(&self, state: &mut DefaultHasher);
+}
+
+/// Hashes the chosen component types of all entities of a world, ordered by entity.
+pub struct WorldHasher {
+	component_hashers: Vec<fn(&World, &mut DefaultHasher)>,
+}
+
+impl WorldHasher {
+	pub fn new() -> Self {
+		WorldHasher {
+			component_hashers: vec![],
+		}
+	}
+
+	pub fn add_component<ComponentType: ReplayHash + 'static>(&mut self) {
+		self.component_hashers.push(WorldHasher::hash_components::<ComponentType>);
+	}
+
+	pub fn hash(&self, world: &World) -> u64 {
+		let mut state = DefaultHasher::new();
+		for hash_components in self.component_hashers.iter() {
+			hash_components(world, &mut state);
+		}
+		state.finish()
+	}
+
+	fn hash_components<ComponentType: ReplayHash + 'static>(world: &World, state: &mut DefaultHasher) {
+		let mut components = match world.get_all_components_of_type::<ComponentType>() {
+			Some(components) => components,
+			None => return,
+		};
+		components.sort_by_key(|(_, entity)| entity.0);
+
+		components.len().hash(state);
+		for (component, entity) in components {
+			entity.hash(state);
+			component.replay_hash(state);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::hash_map::DefaultHasher;
+	use std::hash::Hash;
+	use resa_ecs::world::World;
+	use crate::replay::world_hasher::{ReplayHash, WorldHasher};
+
+	struct Health(u32);
+	struct Speed(f32);
+
+	impl ReplayHash for Health {
+		fn replay_hash(&self, state: &mut DefaultHasher) {
+			self.0.hash(state);
+		}
+	}
+
+	impl ReplayHash for Speed {
+		fn replay_hash(&self, state: &mut DefaultHasher) {
+			self.0.to_bits().hash(state);
+		}
+	}
+
+	fn create_world_hasher() -> WorldHasher {
+		let mut world_hasher = WorldHasher::new();
+		world_hasher.add_component::<Health>();
+		world_hasher.add_component::<Speed>();
+		world_hasher
+	}
+
+	#[test]
+	fn hashes_do_not_depend_on_the_insertion_order() {
+		let mut first = World::new();
+		let (a, b) = (first.new_entity(), first.new_entity());
+		first.add_component(&a, Health(3)).unwrap();
+		first.add_component(&a, Speed(1.5)).unwrap();
+		first.add_component(&b, Health(7)).unwrap();
+
+		// The entities end up in other archetypes with other column orders
+		let mut second = World::new();
+		let (a, b) = (second.new_entity(), second.new_entity());
+		second.add_component(&b, Health(7)).unwrap();
+		second.add_component(&a, Speed(1.5)).unwrap();
+		second.add_component(&a, Health(3)).unwrap();
+
+		let world_hasher = create_world_hasher();
+		assert_eq!(world_hasher.hash(&first), world_hasher.hash(&second));
+	}
+
+	#[test]
+	fn hashes_change_with_the_components() {
+		let world_hasher = create_world_hasher();
+		let mut world = World::new();
+		let entity = world.new_entity();
+		world.add_component(&entity, Health(3)).unwrap();
+		let hash = world_hasher.hash(&world);
+
+		world.get_component_mut::<Health>(&entity).unwrap().0 = 4;
+		assert_ne!(world_hasher.hash(&world), hash);
+
+		// The same values on another entity are another state
+		let mut other_entity_world = World::new();
+		other_entity_world.new_entity();
+		let other_entity = other_entity_world.new_entity();
+		other_entity_world.add_component(&other_entity, Health(4)).unwrap();
+		assert_ne!(world_hasher.hash(&other_entity_world), world_hasher.hash(&world));
+	}
+}
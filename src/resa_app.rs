@@ -1,16 +1,29 @@
 use std::cell::RefCell;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::Instant;
+use log::{error, info};
 use winit::dpi::{LogicalSize, PhysicalSize};
-use winit::event::{Event, WindowEvent};
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent as WinitWindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::{WindowBuilder, Window};
-use resa_ecs::entity::Entity;
+use winit::window::Window;
+use resa_ecs::schedule::Schedule;
 use resa_ecs::world::World;
 use resa_ui::ResaUserInterface;
+use crate::input::input_events::InputEvent;
+use crate::profiling::chrome_trace::write_chrome_trace;
+use crate::profiling::overlay::ProfilerOverlay;
+use crate::profiling::Profiler;
+use crate::rendering::frame_capture::FrameCapture;
 use crate::rendering::RenderingSystem;
+use crate::replay::Recorder;
+use crate::replay::world_hasher::WorldHasher;
 use crate::resources::ResourceManager;
-use crate::test_anim::{change_color, rotate_entity};
+use crate::time::Time;
+use crate::window::window_events::WindowEvent;
+use crate::window::window_settings::WindowSettings;
 
 #[allow(dead_code)]
 pub struct ResaApp {
@@ -22,88 +35,240 @@ pub struct ResaApp {
 	pub rendering: RenderingSystem,
 	pub ui: ResaUserInterface,
 	pub world: Rc<RefCell<World>>,
+	pub schedule: Schedule,
 	pub resource_loader: ResourceManager,
+	pub profiler: Profiler,
+	profiler_overlay: ProfilerOverlay,
+	recorder: Option<Recorder>,
+	pub frame_capture: FrameCapture,
+	applied_window_settings: WindowSettings,
 }
 
 impl ResaApp {
-	pub fn new(name: &str, width: u32, height: u32) -> Option<Self> {
+	pub fn new(settings: WindowSettings) -> Option<Self> {
 		let event_loop = EventLoop::new();
-		let resource_manager = ResourceManager::new()?;
+		let resource_manager = match ResourceManager::new() {
+			Ok(resource_manager) => resource_manager,
+			Err(e) => {
+				error!("Could not load the resources: {}", e);
+				return None;
+			}
+		};
 
 		let primary_monitor = event_loop.primary_monitor()?;
 
 		let dpi = primary_monitor.scale_factor();
-		let logical_size = LogicalSize::new(width, height);
-		let physical_size = logical_size.to_physical(dpi);
-
-		let window = match WindowBuilder::new()
-			.with_title(name)
-			.with_inner_size(logical_size)
-			.with_always_on_top(true)
-			.build(&event_loop) {
+		let logical_size = settings.logical_size();
+
+		let window = match settings.create_window_builder(dpi).build(&event_loop) {
 			Ok(win) => win,
 			Err(e) => {
-				println!("{}", e);
+				error!("Could not create the window: {}", e);
 				return None;
 			}
 		};
+		settings.apply_initial(&window);
+		let physical_size = window.inner_size();
 
-		let renderer = RenderingSystem::new(&window, physical_size.clone(), &resource_manager);
+		let renderer = match RenderingSystem::new(&window, physical_size.clone(), settings.present_mode, &resource_manager) {
+			Ok(renderer) => renderer,
+			Err(e) => {
+				error!("Could not create the renderer: {}", e);
+				return None;
+			}
+		};
 
 		let ui_system = ResaUserInterface::new(resource_manager.get_fonts());
 
-		let world = Rc::new(RefCell::new(World::new()));
+		let world = ResaApp::create_world(&settings);
 
 		Some(ResaApp {
-			name: name.to_string(),
+			name: settings.title.clone(),
 			logical_size,
 			physical_size,
 			event_loop,
 			window,
 			rendering: renderer,
 			ui: ui_system,
-			world,
+			world: Rc::new(RefCell::new(world)),
+			schedule: Schedule::new(),
 			resource_loader: resource_manager,
+			profiler: Profiler::new(300),
+			profiler_overlay: ProfilerOverlay::new(0.5),
+			recorder: None,
+			frame_capture: FrameCapture::new(PathBuf::from("captures"), 1.0 / 30.0),
+			applied_window_settings: settings,
 		})
 	}
 
+	/// Create a world with the resources and events the app provides to its systems.
+	/// Used for the windowed app as well as for headless replays.
+	pub fn create_world(settings: &WindowSettings) -> World {
+		let mut world = World::new();
+		world.insert_resource(settings.clone());
+		world.insert_resource(Time::default());
+		world.add_event::<WindowEvent>();
+		world.add_event::<InputEvent>();
+		world
+	}
+
+	/// Record the delta time and the events of every frame, together with the hash of the world after the systems ran.
+	/// The recording is written to the path when the app shuts down and can be replayed with replay::replay.
+	pub fn record_to(&mut self, path: PathBuf, world_hasher: WorldHasher) {
+		self.recorder = Some(Recorder::new(path, world_hasher));
+	}
+
+	/// Register a state type with its initial value. Systems for the states can be added to the schedule
+	/// with add_system_on_enter, add_system_on_update and add_system_on_exit.
+	#[allow(dead_code)]
+	pub fn add_state<S: Copy + Eq + Hash + Debug + 'static>(&mut self, initial: S) {
+		self.schedule.add_state(&mut self.world.borrow_mut(), initial);
+	}
+
+	/// Record the timings of the systems and render stages of the most recent frames.
+	/// If a trace path is given, the recorded frames are written to it as chrome trace when the app shuts down.
+	pub fn enable_profiling(&mut self, show_overlay: bool, trace_path: Option<PathBuf>) {
+		self.profiler.set_enabled(true);
+		self.profiler.show_overlay = show_overlay;
+		self.profiler.trace_path = trace_path;
+		self.schedule.set_timings_enabled(true);
+	}
+
+	/// Runs the event loop until the window is closed.
+	/// F12 saves a screenshot and F9 starts or stops the capture of a frame sequence, both into the frame capture directory.
 	#[allow(unused)]
 	pub fn run_window_loop(mut self) {
 		let system_time = Instant::now();
 		let mut last_time = 0.0;
 		self.event_loop.run(move |event, _, control_flow| {
 			match event {
-				Event::WindowEvent { event, .. } => match event {
-					WindowEvent::CloseRequested => {
-						println!("Requested shutdown!");
-						*control_flow = ControlFlow::ExitWithCode(0);
+				Event::WindowEvent { event, .. } => {
+					if let Some(window_event) = WindowEvent::from_winit(&event) {
+						self.world.borrow_mut().send_event(window_event);
 					}
-					WindowEvent::Resized(dims) => {
-						self.physical_size = PhysicalSize::new(dims.width, dims.height);
-						self.rendering.set_dirty();
+					if let Some(input_event) = InputEvent::from_winit(&event) {
+						self.world.borrow_mut().send_event(input_event);
 					}
-					WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-						// set new surface scale here!
 
-						self.rendering.set_dirty();
+					match event {
+						WinitWindowEvent::CloseRequested => {
+							info!("Requested shutdown!");
+							ResaApp::write_profiler_trace(&self.profiler);
+							ResaApp::write_recording(&self.recorder);
+							*control_flow = ControlFlow::ExitWithCode(0);
+						}
+						WinitWindowEvent::Resized(dims) => {
+							self.physical_size = PhysicalSize::new(dims.width, dims.height);
+							self.logical_size = dims.to_logical(self.applied_window_settings.scale_factor(self.window.scale_factor()));
+							ResaApp::sync_window_size(&self.world, &mut self.applied_window_settings, &self.logical_size);
+							self.rendering.resize(self.physical_size);
+						}
+						WinitWindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+							// Keep the logical size of the window, the scale factor override takes precedence over the monitor.
+							*new_inner_size = self.applied_window_settings.physical_size(scale_factor);
+							self.physical_size = *new_inner_size;
+							self.rendering.resize(self.physical_size);
+						}
+						WinitWindowEvent::KeyboardInput { input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(key), .. }, .. } => {
+							let time = system_time.elapsed().as_secs_f64();
+							ResaApp::handle_capture_key(key, time, &mut self.frame_capture, &mut self.rendering);
+						}
+						_ => (),
 					}
-					_ => (),
 				}
 				Event::MainEventsCleared => self.window.request_redraw(),
 				Event::RedrawRequested(_) => {
 					let current_time = system_time.elapsed().as_secs_f64();
 					let delta_time = (current_time - last_time);
 					last_time = current_time;
-					let entity: Entity = Entity(4);
-					rotate_entity(&Rc::clone(&self.world), &entity, &delta_time );
 
-					let entity = Entity(2);
-					change_color(&Rc::clone(&self.world), &entity, &delta_time);
+					{
+						let mut world = self.world.borrow_mut();
+						let time = world.get_resource_mut::<Time>().unwrap();
+						time.advance(delta_time);
+						self.profiler.begin_frame(time.frame);
+						self.schedule.run(&mut world);
+						if let Some(recorder) = self.recorder.as_mut() {
+							recorder.record_frame(&world, delta_time);
+						}
+					}
+					if let Some(timings) = self.schedule.get_timings() {
+						self.profiler.record_schedule(timings);
+					}
 
+					ResaApp::apply_window_settings(&self.window, &self.world, &mut self.rendering, &mut self.applied_window_settings);
+					self.frame_capture.update(current_time, &mut self.rendering);
+					self.profiler_overlay.update(&mut self.world.borrow_mut(), &mut self.rendering, &self.ui, &self.profiler, current_time);
 					self.rendering.render(&Rc::clone(&self.world));
+					self.world.borrow_mut().clear_events();
+
+					if self.profiler.is_enabled() {
+						self.profiler.record_render(&self.rendering.get_frame_timings());
+						let stats = self.rendering.get_stats();
+						self.profiler.record_counter("drawn_objects", stats.drawn_objects as u64);
+						self.profiler.record_counter("culled_objects", stats.culled_objects as u64);
+						self.profiler.end_frame();
+					}
 				}
 				_ => (),
 			}
 		});
 	}
+
+	/// Apply the changes systems made to the window settings resource since the last frame.
+	fn apply_window_settings(window: &Window, world: &Rc<RefCell<World>>, rendering: &mut RenderingSystem, applied_settings: &mut WindowSettings) {
+		let settings = match world.borrow().get_resource::<WindowSettings>() {
+			Some(settings) => settings.clone(),
+			None => return,
+		};
+		if &settings == applied_settings {
+			return;
+		}
+
+		settings.apply_changes(window, applied_settings);
+		if settings.present_mode != applied_settings.present_mode {
+			rendering.set_present_mode(settings.present_mode);
+		}
+		*applied_settings = settings;
+	}
+
+	fn handle_capture_key(key: VirtualKeyCode, time: f64, frame_capture: &mut FrameCapture, rendering: &mut RenderingSystem) {
+		match key {
+			VirtualKeyCode::F12 => frame_capture.take_screenshot(rendering),
+			VirtualKeyCode::F9 => frame_capture.toggle_sequence(time),
+			_ => (),
+		}
+	}
+
+	fn write_profiler_trace(profiler: &Profiler) {
+		let path = match (profiler.is_enabled(), &profiler.trace_path) {
+			(true, Some(path)) => path,
+			_ => return,
+		};
+		match write_chrome_trace(profiler, path) {
+			Ok(_) => info!("Wrote profiler trace to {}", path.display()),
+			Err(e) => error!("Could not write profiler trace to {}: {}", path.display(), e),
+		}
+	}
+
+	fn write_recording(recorder: &Option<Recorder>) {
+		let recorder = match recorder {
+			Some(recorder) => recorder,
+			None => return,
+		};
+		match recorder.save() {
+			Ok(_) => info!("Wrote recording to {}", recorder.get_path().display()),
+			Err(e) => error!("{}", e),
+		}
+	}
+
+	/// Write the size of a window that was resized by the user back into the window settings.
+	fn sync_window_size(world: &Rc<RefCell<World>>, applied_settings: &mut WindowSettings, logical_size: &LogicalSize<u32>) {
+		applied_settings.width = logical_size.width;
+		applied_settings.height = logical_size.height;
+		if let Some(settings) = world.borrow_mut().get_resource_mut::<WindowSettings>() {
+			settings.width = logical_size.width;
+			settings.height = logical_size.height;
+		}
+	}
 }
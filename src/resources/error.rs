@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::io;
+use std::path::PathBuf;
+use resa_renderer::error::ShaderError;
+
+#[derive(Debug)]
+pub enum ResourceError {
+	/// The resources directory does not exist in the current working directory.
+	MissingDirectory(PathBuf),
+	Io { path: PathBuf, source: io::Error },
+	/// The file name does not consist of a name and a file type, e.g. base.vert
+	InvalidFileName(String),
+	Shader(ShaderError),
+	InvalidMaterial { name: String, reason: String },
+	InvalidMesh { name: String, reason: String },
+	InvalidScene { name: String, reason: String },
+	InvalidSampler { name: String, reason: String },
+}
+
+impl fmt::Display for ResourceError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			ResourceError::MissingDirectory(path) => write!(f, "Resource directory {} does not exist", path.display()),
+			ResourceError::Io { path, source } => write!(f, "Could not read {}: {}", path.display(), source),
+			ResourceError::InvalidFileName(name) => write!(f, "Could not use {}, expected a file name like name.type", name),
+			ResourceError::Shader(e) => write!(f, "{}", e),
+			ResourceError::InvalidMaterial { name, reason } => write!(f, "Material {} is invalid: {}", name, reason),
+			ResourceError::InvalidMesh { name, reason } => write!(f, "Mesh {} is invalid: {}", name, reason),
+			ResourceError::InvalidScene { name, reason } => write!(f, "Scene {} is invalid: {}", name, reason),
+			ResourceError::InvalidSampler { name, reason } => write!(f, "Sampler settings of {} are invalid: {}", name, reason),
+		}
+	}
+}
+
+impl Error for ResourceError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			ResourceError::Io { source, .. } => Some(source),
+			ResourceError::Shader(e) => Some(e),
+			_ => None,
+		}
+	}
+}
@@ -0,0 +1,269 @@
+use std::path::Path;
+use gltf::animation::util::ReadOutputs;
+use gltf::buffer::Data;
+use gltf::image::Format;
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+use log::warn;
+use resa_renderer::material::{SamplerSettings, TextureFilter, TextureWrap};
+use resa_renderer::mesh::Topology;
+use resa_renderer::vertex::{AttributeData, VertexAttribute};
+use crate::resources::error::ResourceError;
+use crate::resources::loaded_resources::{AnimationValues, Interpolation, LoadedAnimation, LoadedAnimationChannel, LoadedNode, LoadedPrimitive, LoadedScene, LoadedSceneImage, LoadedSceneMaterial, LoadedSkin};
+
+const IDENTITY: [[f32; 4]; 4] = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+/// Load a .gltf or .glb file with its buffers and images, which are either embedded or files next to it.
+/// Only the default scene is used, or the first one if the file has no default scene.
+pub(crate) fn load_gltf(name: &str, path: &Path) -> Result<LoadedScene, ResourceError> {
+	let invalid = |reason: String| ResourceError::InvalidScene { name: name.to_string(), reason };
+	let (document, buffers, images) = gltf::import(path).map_err(|e| invalid(e.to_string()))?;
+
+	let scene = document.default_scene().or_else(|| document.scenes().next()).ok_or_else(|| invalid("the file has no scene".to_string()))?;
+	let root_nodes = scene.nodes().map(|node| node.index()).collect();
+
+	let nodes: Vec<LoadedNode> = document.nodes().map(|node| {
+		let (translation, rotation, scale) = node.transform().decomposed();
+		LoadedNode {
+			name: node.name().map(str::to_string),
+			translation,
+			rotation,
+			scale,
+			children: node.children().map(|child| child.index()).collect(),
+			mesh: node.mesh().map(|mesh| mesh.index()),
+			skin: node.skin().map(|skin| skin.index()),
+		}
+	}).collect();
+
+	// Primitives that can not be read are skipped, the rest of the mesh is still drawn
+	let meshes: Vec<Vec<LoadedPrimitive>> = document.meshes().map(|mesh| {
+		mesh.primitives().filter_map(|primitive| match load_primitive(&primitive, &buffers) {
+			Ok(primitive) => Some(primitive),
+			Err(reason) => {
+				warn!("Skipped primitive {} of mesh {} in scene {}: {}", primitive.index(), mesh.index(), name, reason);
+				None
+			}
+		}).collect()
+	}).collect();
+
+	let materials: Vec<LoadedSceneMaterial> = document.materials().map(|material| {
+		let pbr = material.pbr_metallic_roughness();
+		LoadedSceneMaterial {
+			name: material.name().map(str::to_string).unwrap_or_else(|| format!("material{}", material.index().unwrap_or(0))),
+			base_color: pbr.base_color_factor(),
+			base_color_texture: pbr.base_color_texture().map(|info| info.texture().source().index()),
+			base_color_sampler: pbr.base_color_texture().map_or_else(SamplerSettings::default, |info| load_sampler(&info.texture().sampler())),
+			alpha_blend: material.alpha_mode() == AlphaMode::Blend,
+			double_sided: material.double_sided(),
+		}
+	}).collect();
+
+	// Images with fewer channels are converted to rgba, the 16 bit and float images are replaced, so the texture indices stay valid
+	let images: Vec<LoadedSceneImage> = images.into_iter().enumerate().map(|(index, image)| {
+		let converted = to_rgba_image(image);
+		if converted.is_none() {
+			warn!("Image {} of scene {} has an unsupported format, it is replaced with a white pixel", index, name);
+		}
+		converted.unwrap_or(LoadedSceneImage { width: 1, height: 1, rgba: vec![255; 4] })
+	}).collect();
+
+	let skins: Vec<LoadedSkin> = document.skins().map(|skin| {
+		let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+		let inverse_bind_matrices = skin.reader(|buffer| Some(&buffers[buffer.index()]))
+			.read_inverse_bind_matrices()
+			.map(|matrices| matrices.collect())
+			.unwrap_or_else(|| vec![IDENTITY; joints.len()]);
+		LoadedSkin {
+			name: skin.name().map(str::to_string),
+			joints,
+			inverse_bind_matrices,
+			skeleton: skin.skeleton().map(|node| node.index()),
+		}
+	}).collect();
+
+	let animations: Vec<LoadedAnimation> = document.animations().map(|animation| {
+		let channels = animation.channels().filter_map(|channel| {
+			let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+			let times = reader.read_inputs()?.collect();
+			let values = match reader.read_outputs()? {
+				ReadOutputs::Translations(translations) => AnimationValues::Translations(translations.collect()),
+				ReadOutputs::Rotations(rotations) => AnimationValues::Rotations(rotations.into_f32().collect()),
+				ReadOutputs::Scales(scales) => AnimationValues::Scales(scales.collect()),
+				ReadOutputs::MorphTargetWeights(weights) => AnimationValues::MorphTargetWeights(weights.into_f32().collect()),
+			};
+			let interpolation = match channel.sampler().interpolation() {
+				gltf::animation::Interpolation::Step => Interpolation::Step,
+				gltf::animation::Interpolation::Linear => Interpolation::Linear,
+				gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+			};
+			Some(LoadedAnimationChannel {
+				node: channel.target().node().index(),
+				interpolation,
+				times,
+				values,
+			})
+		}).collect();
+		LoadedAnimation {
+			name: animation.name().map(str::to_string),
+			channels,
+		}
+	}).collect();
+
+	Ok(LoadedScene {
+		name: name.to_string(),
+		nodes,
+		root_nodes,
+		meshes,
+		materials,
+		images,
+		skins,
+		animations,
+	})
+}
+
+fn load_primitive(primitive: &gltf::Primitive, buffers: &[Data]) -> Result<LoadedPrimitive, String> {
+	let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+	let positions: Vec<[f32; 3]> = reader.read_positions().ok_or_else(|| "the primitive has no positions".to_string())?.collect();
+
+	let mut attributes = vec![];
+	if let Some(normals) = reader.read_normals() {
+		attributes.push((VertexAttribute::Normal, AttributeData::Float3(normals.collect())));
+	}
+	if let Some(tangents) = reader.read_tangents() {
+		attributes.push((VertexAttribute::Tangent, AttributeData::Float4(tangents.collect())));
+	}
+	if let Some(uvs) = reader.read_tex_coords(0) {
+		attributes.push((VertexAttribute::Uv0, AttributeData::Float2(uvs.into_f32().collect())));
+	}
+	if let Some(uvs) = reader.read_tex_coords(1) {
+		attributes.push((VertexAttribute::Uv1, AttributeData::Float2(uvs.into_f32().collect())));
+	}
+	if let Some(colors) = reader.read_colors(0) {
+		attributes.push((VertexAttribute::Color, AttributeData::Float4(colors.into_rgba_f32().collect())));
+	}
+	if let Some(joints) = reader.read_joints(0) {
+		attributes.push((VertexAttribute::JointIndices, AttributeData::UShort4(joints.into_u16().collect())));
+	}
+	if let Some(weights) = reader.read_weights(0) {
+		attributes.push((VertexAttribute::JointWeights, AttributeData::Float4(weights.into_f32().collect())));
+	}
+
+	let indices: Option<Vec<u32>> = reader.read_indices().map(|indices| indices.into_u32().collect());
+	let vertex_count = positions.len() as u32;
+	// Line loops and triangle fans have no topology of their own, they are drawn as line strip and triangle list
+	let (topology, indices) = match primitive.mode() {
+		Mode::Points => (Topology::PointList, indices),
+		Mode::Lines => (Topology::LineList, indices),
+		Mode::LineStrip => (Topology::LineStrip, indices),
+		Mode::Triangles => (Topology::TriangleList, indices),
+		Mode::TriangleStrip => (Topology::TriangleStrip, indices),
+		Mode::LineLoop => {
+			let mut indices = indices.unwrap_or_else(|| (0..vertex_count).collect());
+			if let Some(first) = indices.first().copied() {
+				indices.push(first);
+			}
+			(Topology::LineStrip, Some(indices))
+		}
+		Mode::TriangleFan => {
+			let fan = indices.unwrap_or_else(|| (0..vertex_count).collect());
+			let triangles = (1..fan.len().saturating_sub(1)).flat_map(|i| [fan[0], fan[i], fan[i + 1]]).collect();
+			(Topology::TriangleList, Some(triangles))
+		}
+	};
+
+	if let Some(index) = indices.iter().flatten().find(|index| **index >= vertex_count) {
+		return Err(format!("index {} is out of range, there are {} vertices", index, vertex_count));
+	}
+
+	Ok(LoadedPrimitive {
+		positions,
+		attributes,
+		indices,
+		topology,
+		material: primitive.material().index(),
+	})
+}
+
+/// The image with four bytes per pixel, None for the 16 bit and float formats.
+fn to_rgba_image(image: gltf::image::Data) -> Option<LoadedSceneImage> {
+	let pixels = image.pixels;
+	let rgba = match image.format {
+		Format::R8G8B8A8 => pixels,
+		Format::R8G8B8 => pixels.chunks_exact(3).flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255]).collect(),
+		// Gray scale images, with and without alpha
+		Format::R8G8 => pixels.chunks_exact(2).flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]]).collect(),
+		Format::R8 => pixels.iter().flat_map(|value| [*value, *value, *value, 255]).collect(),
+		_ => return None,
+	};
+	Some(LoadedSceneImage {
+		width: image.width,
+		height: image.height,
+		rgba,
+	})
+}
+
+/// glTF samplers wrap each axis on its own, the wrap mode of the u axis is used for both.
+/// Without min filter the mip levels are filtered linearly.
+fn load_sampler(sampler: &gltf::texture::Sampler) -> SamplerSettings {
+	let (min_filter, mip_filter, mipmaps) = match sampler.min_filter() {
+		Some(MinFilter::Nearest) => (TextureFilter::Nearest, TextureFilter::Nearest, false),
+		Some(MinFilter::Linear) => (TextureFilter::Linear, TextureFilter::Linear, false),
+		Some(MinFilter::NearestMipmapNearest) => (TextureFilter::Nearest, TextureFilter::Nearest, true),
+		Some(MinFilter::LinearMipmapNearest) => (TextureFilter::Linear, TextureFilter::Nearest, true),
+		Some(MinFilter::NearestMipmapLinear) => (TextureFilter::Nearest, TextureFilter::Linear, true),
+		Some(MinFilter::LinearMipmapLinear) | None => (TextureFilter::Linear, TextureFilter::Linear, true),
+	};
+	SamplerSettings {
+		min_filter,
+		mag_filter: match sampler.mag_filter() {
+			Some(MagFilter::Nearest) => TextureFilter::Nearest,
+			Some(MagFilter::Linear) | None => TextureFilter::Linear,
+		},
+		mip_filter,
+		wrap: match sampler.wrap_s() {
+			WrappingMode::ClampToEdge => TextureWrap::Clamp,
+			WrappingMode::MirroredRepeat => TextureWrap::MirroredRepeat,
+			WrappingMode::Repeat => TextureWrap::Repeat,
+		},
+		mipmaps,
+		..SamplerSettings::default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+	use resa_renderer::mesh::Topology;
+	use crate::resources::gltf_loader::load_gltf;
+
+	#[test]
+	fn sample_scene_is_loaded() {
+		let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/scenes/sample.gltf");
+		let scene = load_gltf("sample", &path).unwrap();
+
+		assert_eq!(scene.name, "sample");
+		assert_eq!(scene.nodes.len(), 2);
+		assert_eq!(scene.root_nodes, vec![0]);
+		assert_eq!(scene.nodes[0].children, vec![1]);
+		assert!(scene.nodes[1].children.is_empty());
+		assert_eq!((scene.nodes[0].mesh, scene.nodes[1].mesh), (Some(0), Some(0)));
+		assert_eq!(scene.nodes[0].translation, [-1.5, -1.0, 0.0]);
+		assert_eq!(scene.nodes[1].scale, [0.5, 0.5, 0.5]);
+
+		assert_eq!(scene.meshes.len(), 1);
+		let primitives = &scene.meshes[0];
+		assert_eq!(primitives.len(), 2);
+		assert_eq!((primitives[0].material, primitives[1].material), (Some(0), Some(1)));
+		for primitive in primitives {
+			assert_eq!(primitive.positions.len(), 3);
+			assert!(primitive.indices.is_none());
+			assert_eq!(primitive.topology, Topology::TriangleList);
+		}
+
+		let material_names: Vec<&str> = scene.materials.iter().map(|material| material.name.as_str()).collect();
+		assert_eq!(material_names, vec!["red", "blue"]);
+		assert!(scene.materials.iter().all(|material| material.double_sided && !material.alpha_blend));
+		assert!(scene.images.is_empty() && scene.skins.is_empty() && scene.animations.is_empty());
+	}
+}
@@ -1,10 +1,14 @@
-use resa_renderer::material::TextureFormat;
+use resa_renderer::material::{PipelineState, SamplerSettings, TextureFormat};
+use resa_renderer::mesh::Topology;
+use resa_renderer::vertex::{AttributeData, VertexAttribute};
 
 #[derive(Clone)]
 pub struct LoadedImage{
 	pub image_name: String,
 	pub image_data: Vec<u8>,
 	pub image_format: TextureFormat,
+	/// From the .sampler file with the name of the image, the default settings if there is none
+	pub sampler: SamplerSettings,
 }
 
 #[derive(Clone)]
@@ -20,4 +24,120 @@ pub struct LoadedMaterial{
 	pub stage: usize,
 	pub color: [u8; 4],
 	pub texture: String,
-}
\ No newline at end of file
+	/// Sampler settings of the texture, which replace the ones of the image
+	pub sampler: Option<SamplerSettings>,
+	pub pipeline_state: PipelineState,
+}
+
+#[derive(Clone)]
+pub struct LoadedMesh{
+	pub name: String,
+	pub positions: Vec<[f32; 3]>,
+	pub normals: Vec<[f32; 3]>,
+	pub uvs: Option<Vec<[f32; 2]>>,
+	pub indices: Vec<u32>,
+	/// The material the mesh is drawn with, from its mtl file or a .mat file with the same name.
+	pub material: Option<String>,
+	/// The materials of the mtl files of the mesh.
+	pub mtl_materials: Vec<LoadedMaterial>,
+}
+
+/// A glTF scene with everything needed to spawn it into a world. The nodes, meshes, materials and images
+/// reference each other by their index in the scene.
+#[derive(Clone)]
+pub struct LoadedScene{
+	pub name: String,
+	pub nodes: Vec<LoadedNode>,
+	/// The nodes without parent
+	pub root_nodes: Vec<usize>,
+	/// Every mesh consists of one or more primitives
+	pub meshes: Vec<Vec<LoadedPrimitive>>,
+	pub materials: Vec<LoadedSceneMaterial>,
+	pub images: Vec<LoadedSceneImage>,
+	pub skins: Vec<LoadedSkin>,
+	pub animations: Vec<LoadedAnimation>,
+}
+
+#[derive(Clone)]
+pub struct LoadedNode{
+	pub name: Option<String>,
+	pub translation: [f32; 3],
+	/// Quaternion x, y, z, w
+	pub rotation: [f32; 4],
+	pub scale: [f32; 3],
+	pub children: Vec<usize>,
+	pub mesh: Option<usize>,
+	pub skin: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct LoadedPrimitive{
+	pub positions: Vec<[f32; 3]>,
+	pub attributes: Vec<(VertexAttribute, AttributeData)>,
+	pub indices: Option<Vec<u32>>,
+	pub topology: Topology,
+	/// Primitives without material are drawn with the default material
+	pub material: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct LoadedSceneMaterial{
+	pub name: String,
+	/// Linear rgba factor, multiplied with the base color texture
+	pub base_color: [f32; 4],
+	pub base_color_texture: Option<usize>,
+	pub base_color_sampler: SamplerSettings,
+	pub alpha_blend: bool,
+	pub double_sided: bool,
+}
+
+/// Decoded image with four bytes per pixel.
+#[derive(Clone)]
+pub struct LoadedSceneImage{
+	pub width: u32,
+	pub height: u32,
+	pub rgba: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub struct LoadedSkin{
+	pub name: Option<String>,
+	/// The nodes which are the joints of the skin
+	pub joints: Vec<usize>,
+	/// One matrix per joint, identity matrices if the file has none
+	pub inverse_bind_matrices: Vec<[[f32; 4]; 4]>,
+	pub skeleton: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct LoadedAnimation{
+	pub name: Option<String>,
+	pub channels: Vec<LoadedAnimationChannel>,
+}
+
+/// Key frames of one property of one node.
+#[derive(Clone)]
+pub struct LoadedAnimationChannel{
+	pub node: usize,
+	pub interpolation: Interpolation,
+	/// Time of the key frames in seconds
+	pub times: Vec<f32>,
+	/// With cubic spline interpolation every key frame has an in tangent, a value and an out tangent
+	pub values: AnimationValues,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Interpolation{
+	Step,
+	Linear,
+	CubicSpline,
+}
+
+#[derive(Clone)]
+pub enum AnimationValues{
+	Translations(Vec<[f32; 3]>),
+	Rotations(Vec<[f32; 4]>),
+	Scales(Vec<[f32; 3]>),
+	/// The weights of all morph targets, one after another for every key frame
+	MorphTargetWeights(Vec<f32>),
+}
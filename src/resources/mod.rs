@@ -1,11 +1,16 @@
+use log::warn;
 use resa_renderer::shader::ShaderRef;
-use crate::resources::loaded_resources::{LoadedImage, LoadedMaterial};
+use crate::resources::error::ResourceError;
+use crate::resources::loaded_resources::{LoadedImage, LoadedMaterial, LoadedMesh, LoadedScene};
 use crate::resources::resource_loader::ResourceLoader;
 use crate::resources::static_cache::StaticResourceCache;
 
 pub mod loaded_resources;
 pub mod resource_loader;
+pub mod error;
 mod static_cache;
+mod obj;
+mod gltf_loader;
 
 
 pub struct ResourceManager{
@@ -17,13 +22,13 @@ pub struct ResourceManager{
 }
 
 impl ResourceManager {
-	pub fn new() -> Option<Self>{
+	pub fn new() -> Result<Self, ResourceError>{
 
 		let resource_loader = ResourceLoader::new()?;
 		let mut static_cache = StaticResourceCache::new();
-		static_cache.load_from_disk(&resource_loader);
+		static_cache.load_from_disk(&resource_loader)?;
 
-		Some(Self{
+		Ok(Self{
 			static_loader: resource_loader,
 			static_cache,
 		})
@@ -39,10 +44,14 @@ impl ResourceManager {
 
 	pub fn get_materials(&self) -> Vec<LoadedMaterial>{ self.static_cache.get_materials() }
 
+	pub fn get_meshes(&self) -> Vec<LoadedMesh>{ self.static_cache.get_meshes() }
+
+	pub fn get_scene(&self, name: &str) -> Option<LoadedScene>{ self.static_cache.get_scene(name) }
+
 	pub fn get_image(&self, name: &str, streaming: bool)-> Option<LoadedImage>{
 
 		if streaming{
-			println!("Streaming images is not implemented yet!");
+			warn!("Streaming images is not implemented yet!");
 			return None;
 		}
 
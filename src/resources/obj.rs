@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use resa_renderer::material::PipelineState;
+use crate::resources::error::ResourceError;
+use crate::resources::loaded_resources::LoadedMaterial;
+
+/// Geometry of a Wavefront obj file. The faces are triangulated and every unique combination
+/// of position, uv and normal of the file is one vertex.
+pub(crate) struct ObjGeometry {
+	pub positions: Vec<[f32; 3]>,
+	pub normals: Vec<[f32; 3]>,
+	/// None if no face vertex references a uv
+	pub uvs: Option<Vec<[f32; 2]>>,
+	pub indices: Vec<u32>,
+	/// The mtl files named by mtllib, relative to the obj file
+	pub material_libraries: Vec<String>,
+	/// The materials named by usemtl, in the order of their first use
+	pub materials: Vec<String>,
+}
+
+/// The indices of a face vertex into the positions, uvs and normals of the file.
+type FaceVertex = (usize, Option<usize>, Option<usize>);
+
+pub(crate) fn parse_obj(name: &str, obj_str: &str) -> Result<ObjGeometry, ResourceError> {
+	let invalid = |line: usize, reason: String| ResourceError::InvalidMesh { name: name.to_string(), reason: format!("line {}: {}", line + 1, reason) };
+
+	let mut file_positions: Vec<[f32; 3]> = vec![];
+	let mut file_uvs: Vec<[f32; 2]> = vec![];
+	let mut file_normals: Vec<[f32; 3]> = vec![];
+	let mut faces: Vec<[FaceVertex; 3]> = vec![];
+	let mut material_libraries = vec![];
+	let mut materials: Vec<String> = vec![];
+
+	for (line_index, row) in obj_str.lines().enumerate() {
+		let row = row.split('#').next().unwrap_or("").trim();
+		let mut values = row.split_whitespace();
+		let keyword = match values.next() {
+			Some(keyword) => keyword,
+			None => continue,
+		};
+		let values: Vec<&str> = values.collect();
+
+		match keyword {
+			"v" => {
+				// An optional w component or vertex color after x, y and z is ignored
+				let position = parse_floats(&values, 3).ok_or_else(|| invalid(line_index, format!("{} is not a position", row)))?;
+				file_positions.push([position[0], position[1], position[2]]);
+			}
+			"vt" => {
+				let uv = parse_floats(&values, 1).ok_or_else(|| invalid(line_index, format!("{} is not a texture coordinate", row)))?;
+				// Obj files have the origin of the texture at the bottom left, the renderer at the top left
+				file_uvs.push([uv[0], 1.0 - uv.get(1).copied().unwrap_or(0.0)]);
+			}
+			"vn" => {
+				let normal = parse_floats(&values, 3).ok_or_else(|| invalid(line_index, format!("{} is not a normal", row)))?;
+				file_normals.push([normal[0], normal[1], normal[2]]);
+			}
+			"f" => {
+				if values.len() < 3 {
+					return Err(invalid(line_index, "a face needs at least three vertices".to_string()));
+				}
+				let face = values.iter()
+					.map(|value| parse_face_vertex(value, file_positions.len(), file_uvs.len(), file_normals.len()))
+					.collect::<Result<Vec<FaceVertex>, String>>()
+					.map_err(|reason| invalid(line_index, reason))?;
+
+				// Polygons are split into a fan around their first vertex, which is only correct for convex polygons
+				for i in 1..face.len() - 1 {
+					faces.push([face[0], face[i], face[i + 1]]);
+				}
+			}
+			"mtllib" => material_libraries.extend(values.iter().map(|library| library.to_string())),
+			"usemtl" => {
+				let material = values.join(" ");
+				if !materials.contains(&material) {
+					materials.push(material);
+				}
+			}
+			// Objects, groups, smoothing groups, lines and points are not supported
+			_ => {}
+		}
+	}
+
+	if faces.is_empty() {
+		return Err(ResourceError::InvalidMesh { name: name.to_string(), reason: "the file has no faces".to_string() });
+	}
+
+	// Every unique combination of position, uv and normal becomes one vertex
+	let mut vertex_indices = HashMap::<FaceVertex, u32>::new();
+	let mut vertices: Vec<FaceVertex> = vec![];
+	let mut indices = Vec::with_capacity(faces.len() * 3);
+	for face_vertex in faces.iter().flatten() {
+		let index = *vertex_indices.entry(*face_vertex).or_insert_with(|| {
+			vertices.push(*face_vertex);
+			vertices.len() as u32 - 1
+		});
+		indices.push(index);
+	}
+
+	let positions: Vec<[f32; 3]> = vertices.iter().map(|(position, _, _)| file_positions[*position]).collect();
+	let uvs = if vertices.iter().any(|(_, uv, _)| uv.is_some()) {
+		Some(vertices.iter().map(|(_, uv, _)| uv.map(|uv| file_uvs[uv]).unwrap_or([0.0, 0.0])).collect())
+	} else {
+		None
+	};
+	let normals = generate_missing_normals(&positions, &indices, vertices.iter().map(|(_, _, normal)| normal.map(|normal| file_normals[normal])).collect());
+
+	Ok(ObjGeometry {
+		positions,
+		normals,
+		uvs,
+		indices,
+		material_libraries,
+		materials,
+	})
+}
+
+/// Parse the materials of a mtl file. Only the diffuse color, the opacity and the diffuse texture are used,
+/// the materials are drawn with the first shader.
+pub(crate) fn parse_mtl(name: &str, mtl_str: &str) -> Result<Vec<LoadedMaterial>, ResourceError> {
+	let invalid = |line: usize, reason: String| ResourceError::InvalidMaterial { name: name.to_string(), reason: format!("line {}: {}", line + 1, reason) };
+	let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+	let mut materials: Vec<LoadedMaterial> = vec![];
+	for (line_index, row) in mtl_str.lines().enumerate() {
+		let row = row.split('#').next().unwrap_or("").trim();
+		let mut values = row.split_whitespace();
+		let keyword = match values.next() {
+			Some(keyword) => keyword,
+			None => continue,
+		};
+		let values: Vec<&str> = values.collect();
+
+		if keyword == "newmtl" {
+			materials.push(LoadedMaterial {
+				name: values.join(" "),
+				shader: 0,
+				stage: 1,
+				color: [255, 255, 255, 255],
+				texture: "".to_string(),
+				sampler: None,
+				pipeline_state: PipelineState::default(),
+			});
+			continue;
+		}
+
+		let material = match materials.last_mut() {
+			Some(material) => material,
+			None => continue,
+		};
+		match keyword {
+			"Kd" => {
+				let color = parse_floats(&values, 3).ok_or_else(|| invalid(line_index, format!("{} is not a diffuse color", row)))?;
+				material.color = [to_byte(color[0]), to_byte(color[1]), to_byte(color[2]), material.color[3]];
+			}
+			"d" | "Tr" => {
+				let value = parse_floats(&values, 1).ok_or_else(|| invalid(line_index, format!("{} is not an opacity", row)))?[0];
+				let opacity = if keyword == "d" { value } else { 1.0 - value };
+				material.color[3] = to_byte(opacity);
+				material.stage = if opacity < 1.0 { 2 } else { 1 };
+			}
+			"map_Kd" => {
+				// The texture is referenced by its image name without the file type, like in .mat files
+				let file = values.last().copied().unwrap_or("");
+				material.texture = file.rsplit(['/', '\\']).next().unwrap_or(file).split('.').next().unwrap_or("").to_string();
+			}
+			_ => {}
+		}
+	}
+	Ok(materials)
+}
+
+/// At least `count` floats, None if there are fewer or one of them is not a number.
+fn parse_floats(values: &[&str], count: usize) -> Option<Vec<f32>> {
+	if values.len() < count {
+		return None;
+	}
+	values.iter().map(|value| value.parse::<f32>().ok()).collect()
+}
+
+/// Resolve a face vertex like 3, 3/1, 3//2 or 3/1/2. Indices start at 1, negative indices count back from the last element.
+fn parse_face_vertex(value: &str, position_count: usize, uv_count: usize, normal_count: usize) -> Result<FaceVertex, String> {
+	let resolve = |index: &str, count: usize, kind: &str| -> Result<usize, String> {
+		let index = index.parse::<i64>().map_err(|_| format!("{} is not a {} index", index, kind))?;
+		let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+		if resolved < 0 || resolved >= count as i64 {
+			return Err(format!("{} index {} is out of range, there are {} so far", kind, index, count));
+		}
+		Ok(resolved as usize)
+	};
+
+	let parts: Vec<&str> = value.split('/').collect();
+	if parts.len() > 3 {
+		return Err(format!("{} is not a face vertex", value));
+	}
+	let position = resolve(parts[0], position_count, "position")?;
+	let uv = match parts.get(1) {
+		Some(uv) if !uv.is_empty() => Some(resolve(uv, uv_count, "texture coordinate")?),
+		_ => None,
+	};
+	let normal = match parts.get(2) {
+		Some(normal) if !normal.is_empty() => Some(resolve(normal, normal_count, "normal")?),
+		_ => None,
+	};
+	Ok((position, uv, normal))
+}
+
+/// Vertices without a normal get the sum of the normals of the triangles they belong to,
+/// weighted by the area of the triangles. The triangles are expected to be counter clockwise.
+fn generate_missing_normals(positions: &[[f32; 3]], indices: &[u32], normals: Vec<Option<[f32; 3]>>) -> Vec<[f32; 3]> {
+	if normals.iter().all(|normal| normal.is_some()) {
+		return normals.into_iter().flatten().collect();
+	}
+
+	let mut generated = vec![[0.0f32; 3]; positions.len()];
+	for triangle in indices.chunks_exact(3) {
+		let [a, b, c] = [positions[triangle[0] as usize], positions[triangle[1] as usize], positions[triangle[2] as usize]];
+		let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+		let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+		// The length of the cross product is twice the area of the triangle
+		let face_normal = [
+			ab[1] * ac[2] - ab[2] * ac[1],
+			ab[2] * ac[0] - ab[0] * ac[2],
+			ab[0] * ac[1] - ab[1] * ac[0],
+		];
+		for index in triangle {
+			for (normal, face) in generated[*index as usize].iter_mut().zip(face_normal) {
+				*normal += face;
+			}
+		}
+	}
+
+	normals.into_iter().zip(generated).map(|(normal, generated)| {
+		normal.unwrap_or_else(|| {
+			let length = (generated[0] * generated[0] + generated[1] * generated[1] + generated[2] * generated[2]).sqrt();
+			if length > 0.0 {
+				[generated[0] / length, generated[1] / length, generated[2] / length]
+			} else {
+				// Only part of degenerated triangles
+				[0.0, 0.0, 1.0]
+			}
+		})
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::resources::error::ResourceError;
+	use crate::resources::obj::{parse_mtl, parse_obj};
+
+	const QUAD: &str = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 3 4
+";
+
+	#[test]
+	fn polygons_are_split_into_a_fan() {
+		let geometry = parse_obj("quad", QUAD).unwrap();
+		assert_eq!(geometry.positions.len(), 4);
+		assert_eq!(geometry.indices, vec![0, 1, 2, 0, 2, 3]);
+		assert!(geometry.uvs.is_none());
+	}
+
+	#[test]
+	fn vertices_are_shared_by_position_uv_and_normal() {
+		let obj = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 1
+vn 0 0 1
+f 1/1/1 2/1/1 3/1/1
+f 1/1/1 3/1/1 2/2/1
+";
+		let geometry = parse_obj("shared", obj).unwrap();
+		// The second face reuses all vertices but the one with the other uv
+		assert_eq!(geometry.positions, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]]);
+		assert_eq!(geometry.indices, vec![0, 1, 2, 0, 2, 3]);
+		// The v axis is flipped, the origin of the renderer is at the top left
+		assert_eq!(geometry.uvs, Some(vec![[0.0, 1.0], [0.0, 1.0], [0.0, 1.0], [1.0, 0.0]]));
+		assert_eq!(geometry.normals, vec![[0.0, 0.0, 1.0]; 4]);
+	}
+
+	#[test]
+	fn missing_normals_are_generated_from_the_faces() {
+		let geometry = parse_obj("quad", QUAD).unwrap();
+		assert_eq!(geometry.normals, vec![[0.0, 0.0, 1.0]; 4]);
+
+		// Only the vertex without normal gets a generated one
+		let obj = "
+v 0 0 0
+v 0 0 1
+v 0 1 0
+vn 1 0 0
+f 1//1 2//1 3
+";
+		let geometry = parse_obj("partial", obj).unwrap();
+		assert_eq!(geometry.normals, vec![[1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [-1.0, 0.0, 0.0]]);
+	}
+
+	#[test]
+	fn negative_indices_count_back_from_the_last_element() {
+		let obj = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f -3 -2 -1
+";
+		assert_eq!(parse_obj("relative", obj).unwrap().positions, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+	}
+
+	#[test]
+	fn bad_indices_are_errors() {
+		let out_of_range = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 4
+";
+		let not_a_number = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 x
+";
+		// Normals have to be declared before the face which uses them
+		let missing_normal = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1//1 2//1 3//1
+vn 0 0 1
+";
+		for (obj, reason) in [
+			(out_of_range, "line 5: position index 4 is out of range, there are 3 so far"),
+			(not_a_number, "line 5: x is not a position index"),
+			(missing_normal, "line 5: normal index 1 is out of range, there are 0 so far"),
+			("f 0 1 2", "line 1: position index 0 is out of range, there are 0 so far"),
+		] {
+			match parse_obj("bad", obj) {
+				Err(ResourceError::InvalidMesh { reason: actual, .. }) => assert_eq!(actual, reason),
+				_ => panic!("{:?} was parsed", obj),
+			}
+		}
+	}
+
+	#[test]
+	fn materials_read_color_opacity_and_texture() {
+		let mtl = "
+newmtl Wood
+Kd 1.0 0.5 0.0
+map_Kd textures/Wood.png
+newmtl Glass
+d 0.5
+";
+		let materials = parse_mtl("materials", mtl).unwrap();
+		assert_eq!(materials.len(), 2);
+		assert_eq!((materials[0].name.as_str(), materials[0].color, materials[0].texture.as_str(), materials[0].stage), ("Wood", [255, 128, 0, 255], "Wood", 1));
+		assert_eq!((materials[1].name.as_str(), materials[1].color, materials[1].stage), ("Glass", [255, 255, 255, 128], 2));
+	}
+}
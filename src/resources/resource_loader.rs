@@ -1,43 +1,41 @@
 use std::{env, fs};
 use std::collections::HashMap;
-use std::fs::DirEntry;
-use resa_renderer::material::{TextureFormat};
+use std::fs::{DirEntry, ReadDir};
+use std::path::PathBuf;
+use log::warn;
+use resa_renderer::material::{BlendMode, BorderColor, ColorSpace, CullMode, DepthBias, PipelineState, PolygonMode, SamplerSettings, TextureFilter, TextureFormat, TextureWrap};
 use resa_renderer::shader::ShaderRef;
-use crate::resources::loaded_resources::{LoadedFont, LoadedImage, LoadedMaterial};
+use crate::resources::error::ResourceError;
+use crate::resources::loaded_resources::{LoadedFont, LoadedImage, LoadedMaterial, LoadedMesh, LoadedScene};
+use crate::resources::{gltf_loader, obj};
 
 pub struct ResourceLoader {
-	resources_path: String,
+	resources_path: PathBuf,
 }
 
 impl ResourceLoader {
-	pub(crate) fn new() -> Option<Self> {
-		let current_directory = env::current_dir().unwrap();
+	pub(crate) fn new() -> Result<Self, ResourceError> {
+		let current_directory = env::current_dir().map_err(|source| ResourceError::Io { path: PathBuf::from("."), source })?;
 		let ressource_directory = current_directory.join("resources");
 
 		if !fs::metadata(&ressource_directory).is_ok() {
-			return None;
+			return Err(ResourceError::MissingDirectory(ressource_directory));
 		}
 
-		Some(Self {
-			resources_path: ressource_directory.as_path().to_str().unwrap().to_string(),
+		Ok(Self {
+			resources_path: ressource_directory,
 		})
 	}
 
-	pub(crate) fn load_all_shaders(&self) -> Option<Vec<ShaderRef>> {
-		let shader_dir = self.resources_path.clone() + "/shaders";
-		let shader_paths = fs::read_dir(shader_dir).unwrap();
+	pub(crate) fn load_all_shaders(&self) -> Result<Vec<ShaderRef>, ResourceError> {
+		let shader_paths = self.read_resource_dir("shaders")?;
 
 		let mut shader_collection = HashMap::<String, Vec<(String, String)>>::new();
 		for shader_file in shader_paths {
 			if let Ok(file) = shader_file {
 				let (name, file_type) = self.get_filename_and_type(&file)?;
-				let shader = match fs::read_to_string(file.path().as_path()) {
-					Ok(content) => content,
-					Err(e) => {
-						println!("Could not read shader file! Error {}", e);
-						return None;
-					}
-				};
+				let shader = fs::read_to_string(file.path().as_path())
+					.map_err(|source| ResourceError::Io { path: file.path(), source })?;
 
 				if shader_collection.contains_key(&name) {
 					shader_collection.get_mut(&name).unwrap().push((file_type, shader));
@@ -49,50 +47,88 @@ impl ResourceLoader {
 
 		let mut shaders = Vec::<ShaderRef>::new();
 		for (name, shaders_to_compile) in shader_collection {
-			let shader_ref = ShaderRef::new(name.to_string(), &shaders_to_compile)?;
+			let shader_ref = ShaderRef::new(name, &shaders_to_compile).map_err(ResourceError::Shader)?;
 			shaders.push(shader_ref);
 		}
-		Some(shaders)
+		Ok(shaders)
 	}
 
-	pub fn load_images(&self) -> Vec<LoadedImage> {
-		let image_dir = self.resources_path.clone() + "/images";
-		let image_paths = fs::read_dir(image_dir).unwrap();
+	/// Load all png, jpeg, tga, bmp, hdr, exr, ktx2 and dds images, images that can not be read are skipped.
+	/// The sampler settings of an image can be set in a .sampler file with its name, e.g. Wood.sampler next to Wood.png.
+	pub fn load_images(&self) -> Result<Vec<LoadedImage>, ResourceError> {
+		let image_paths = self.read_resource_dir("images")?;
 
 		let mut images: Vec<LoadedImage> = vec![];
+		let mut sampler_files: Vec<(String, DirEntry)> = vec![];
 		for image_path in image_paths {
 			if let Ok(file) = image_path {
-				let (name, _) = match self.get_filename_and_type(&file) {
-					Some(result) => result,
-					None => continue,
+				let (name, file_type) = match self.get_filename_and_type(&file) {
+					Ok(result) => result,
+					Err(e) => {
+						warn!("Skipped image: {}", e);
+						continue;
+					}
+				};
+				if file_type == "sampler" {
+					sampler_files.push((name, file));
+					continue;
+				}
+				let image_format = match TextureFormat::from_extension(&file_type) {
+					Some(image_format) => image_format,
+					None => {
+						warn!("Skipped image {}: {} is not a supported image format", name, file_type);
+						continue;
+					}
 				};
 				let image = match self.read_file_to_bytes(&file) {
-					Some(image) => image,
-					None => continue,
+					Ok(image) => image,
+					Err(e) => {
+						warn!("Skipped image: {}", e);
+						continue;
+					}
 				};
 
 				images.push(
 					LoadedImage {
 						image_name: name,
 						image_data: image,
-						image_format: TextureFormat::Png,
+						image_format,
+						sampler: SamplerSettings::default(),
 					}
 				);
 			}
 		}
-		images
+
+		// Images with invalid sampler settings keep the default ones
+		for (name, file) in sampler_files {
+			let sampler = match self.load_sampler(&name, &file) {
+				Ok(sampler) => sampler,
+				Err(e) => {
+					warn!("Skipped sampler settings: {}", e);
+					continue;
+				}
+			};
+			match images.iter_mut().find(|image| image.image_name == name) {
+				Some(image) => image.sampler = sampler,
+				None => warn!("Skipped sampler settings of {}, there is no image with the name", name),
+			}
+		}
+		Ok(images)
 	}
 
-	pub fn load_fonts(&self) -> Vec<LoadedFont> {
-		let font_dir = self.resources_path.clone() + "/fonts";
-		let font_paths = fs::read_dir(font_dir).unwrap();
+	/// Load all ttf fonts, fonts that can not be read are skipped.
+	pub fn load_fonts(&self) -> Result<Vec<LoadedFont>, ResourceError> {
+		let font_paths = self.read_resource_dir("fonts")?;
 
 		let mut fonts: Vec<LoadedFont> = vec![];
 		for font_path in font_paths {
 			if let Ok(file) = font_path {
 				let (name, file_type) = match self.get_filename_and_type(&file) {
-					Some(result) => result,
-					None => continue,
+					Ok(result) => result,
+					Err(e) => {
+						warn!("Skipped font: {}", e);
+						continue;
+					}
 				};
 
 				if file_type != "ttf".to_string() {
@@ -100,8 +136,11 @@ impl ResourceLoader {
 				}
 
 				let font = match self.read_file_to_bytes(&file) {
-					Some(font) => font,
-					None => continue,
+					Ok(font) => font,
+					Err(e) => {
+						warn!("Skipped font: {}", e);
+						continue;
+					}
 				};
 
 
@@ -114,76 +153,169 @@ impl ResourceLoader {
 			}
 		}
 
-		fonts
+		Ok(fonts)
 	}
 
-	pub fn load_materials(&self) -> Vec<LoadedMaterial> {
-		let material_dir = self.resources_path.clone() + "/materials";
-		let material_path = fs::read_dir(material_dir).unwrap();
+	/// Load all materials, materials that can not be read or parsed are skipped.
+	pub fn load_materials(&self) -> Result<Vec<LoadedMaterial>, ResourceError> {
+		let material_path = self.read_resource_dir("materials")?;
 
 		let mut materials = vec![];
 		for material_file in material_path {
 			if let Ok(file) = material_file {
-				let (name, _file_type) = match self.get_filename_and_type(&file) {
-					Some(result) => result,
-					None => continue,
+				let material = match self.load_material(&file) {
+					Ok(mat) => mat,
+					Err(e) => {
+						warn!("Skipped material: {}", e);
+						continue;
+					}
 				};
-				let material_str = match fs::read_to_string(file.path().as_path()) {
-					Ok(str) => str,
-					Err(_e) => {
-						println!("Could not read material {}", name);
+
+				materials.push(material);
+			}
+		}
+		Ok(materials)
+	}
+
+	/// Load all obj meshes with the materials of their mtl files, meshes that can not be read or parsed are skipped.
+	pub fn load_meshes(&self) -> Result<Vec<LoadedMesh>, ResourceError> {
+		let mesh_paths = self.read_resource_dir("meshes")?;
+
+		let mut meshes = vec![];
+		for mesh_file in mesh_paths {
+			if let Ok(file) = mesh_file {
+				// The mtl files are loaded with the meshes that reference them
+				if file.path().extension().map_or(false, |extension| extension == "mtl") {
+					continue;
+				}
+				let mesh = match self.load_mesh(&file) {
+					Ok(mesh) => mesh,
+					Err(e) => {
+						warn!("Skipped mesh: {}", e);
 						continue;
 					}
 				};
 
-				let material = match self.parse_material(&name, &material_str) {
-					Some(mat) => mat,
-					None => continue
+				meshes.push(mesh);
+			}
+		}
+		Ok(meshes)
+	}
+
+	/// Load all gltf and glb scenes, scenes that can not be read are skipped.
+	/// Their buffers and images can be next to them in the scenes directory.
+	pub fn load_scenes(&self) -> Result<Vec<LoadedScene>, ResourceError> {
+		let scene_paths = self.read_resource_dir("scenes")?;
+
+		let mut scenes = vec![];
+		for scene_path in scene_paths {
+			if let Ok(file) = scene_path {
+				let (name, file_type) = match self.get_filename_and_type(&file) {
+					Ok(result) => result,
+					Err(e) => {
+						warn!("Skipped scene: {}", e);
+						continue;
+					}
 				};
 
-				materials.push(material);
+				if file_type != "gltf" && file_type != "glb" {
+					continue;
+				}
+
+				match gltf_loader::load_gltf(&name, &file.path()) {
+					Ok(scene) => scenes.push(scene),
+					Err(e) => warn!("Skipped scene: {}", e),
+				}
 			}
 		}
-		materials
+		Ok(scenes)
 	}
 
-	fn get_filename_and_type(&self, file: &DirEntry) -> Option<(String, String)> {
-		let filename = file.file_name().to_str()?.to_string();
+	fn read_resource_dir(&self, directory: &str) -> Result<ReadDir, ResourceError> {
+		let path = self.resources_path.join(directory);
+		fs::read_dir(&path).map_err(|source| ResourceError::Io { path, source })
+	}
+
+	fn load_material(&self, file: &DirEntry) -> Result<LoadedMaterial, ResourceError> {
+		let (name, _file_type) = self.get_filename_and_type(file)?;
+		let material_str = fs::read_to_string(file.path().as_path())
+			.map_err(|source| ResourceError::Io { path: file.path(), source })?;
+
+		self.parse_material(&name, &material_str)
+	}
+
+	fn load_sampler(&self, name: &str, file: &DirEntry) -> Result<SamplerSettings, ResourceError> {
+		let sampler_str = fs::read_to_string(file.path().as_path())
+			.map_err(|source| ResourceError::Io { path: file.path(), source })?;
+
+		let mut sampler = SamplerSettings::default();
+		for (key, value) in parse_key_value_pairs(&sampler_str) {
+			let known = parse_sampler_setting(&key, &value, &mut sampler)
+				.map_err(|reason| ResourceError::InvalidSampler { name: name.to_string(), reason })?;
+			if !known {
+				warn!("Sampler settings of {} have the unknown key {}", name, key);
+			}
+		}
+		Ok(sampler)
+	}
+
+	fn load_mesh(&self, file: &DirEntry) -> Result<LoadedMesh, ResourceError> {
+		let (name, file_type) = self.get_filename_and_type(file)?;
+		if file_type != "obj" {
+			return Err(ResourceError::InvalidMesh { name, reason: format!("{} is not a supported mesh format, expected obj", file_type) });
+		}
+		let obj_str = fs::read_to_string(file.path().as_path())
+			.map_err(|source| ResourceError::Io { path: file.path(), source })?;
+		let geometry = obj::parse_obj(&name, &obj_str)?;
+
+		// A mesh without its materials is still usable, it is drawn with the default material
+		let mut mtl_materials = vec![];
+		for library in geometry.material_libraries.iter() {
+			let path = self.resources_path.join("meshes").join(library);
+			let materials = fs::read_to_string(&path)
+				.map_err(|source| ResourceError::Io { path, source })
+				.and_then(|mtl_str| obj::parse_mtl(library, &mtl_str));
+			match materials {
+				Ok(materials) => mtl_materials.extend(materials),
+				Err(e) => warn!("Skipped materials of mesh {}: {}", name, e),
+			}
+		}
+
+		if geometry.materials.len() > 1 {
+			warn!("Mesh {} uses {} materials, it is drawn with the first one {}", name, geometry.materials.len(), geometry.materials[0]);
+		}
+
+		Ok(LoadedMesh {
+			name,
+			positions: geometry.positions,
+			normals: geometry.normals,
+			uvs: geometry.uvs,
+			indices: geometry.indices,
+			material: geometry.materials.into_iter().next(),
+			mtl_materials,
+		})
+	}
+
+	fn get_filename_and_type(&self, file: &DirEntry) -> Result<(String, String), ResourceError> {
+		let filename = file.file_name().to_string_lossy().to_string();
 		let filename_parts: Vec<&str> = filename.split('.').collect();
 
 		if filename_parts.len() != 2 {
-			println!("Could not use {}", filename);
-			return None;
+			return Err(ResourceError::InvalidFileName(filename));
 		}
 
 		let name = filename_parts[0].to_string();
 		let file_type = filename_parts[1].to_string();
-		Some((name, file_type))
+		Ok((name, file_type))
 	}
 
-	fn read_file_to_bytes(&self, file: &DirEntry) -> Option<Vec<u8>> {
-		Some(match fs::read(file.path().as_path()) {
-			Ok(content) => content,
-			Err(e) => {
-				println!("Could not read shader file! Error {}", e);
-				return None;
-			}
-		})
+	fn read_file_to_bytes(&self, file: &DirEntry) -> Result<Vec<u8>, ResourceError> {
+		let path = file.path();
+		fs::read(&path).map_err(|source| ResourceError::Io { path, source })
 	}
 
-	fn parse_material(&self, name: &str, material_str: &str) -> Option<LoadedMaterial> {
-		let rows = material_str.split("\n");
-		let mut key_value_pairs: Vec<(String, String)> = Vec::new();
-		for row in rows {
-			if row.len() == 0 {
-				continue;
-			}
-			let pair: Vec<&str> = row.split(":").collect();
-			if pair.len() != 2 {
-				continue;
-			}
-			key_value_pairs.push((pair[0].to_string(), pair[1].to_string()));
-		}
+	fn parse_material(&self, name: &str, material_str: &str) -> Result<LoadedMaterial, ResourceError> {
+		let key_value_pairs = parse_key_value_pairs(material_str);
 
 		let mut mat = LoadedMaterial {
 			name: name.to_string(),
@@ -191,25 +323,142 @@ impl ResourceLoader {
 			stage: 0,
 			color: [0, 0, 0, 0],
 			texture: "".to_string(),
+			sampler: None,
+			pipeline_state: PipelineState::default(),
 		};
 
+		let invalid = |reason: String| ResourceError::InvalidMaterial { name: name.to_string(), reason };
 		for (key, value) in key_value_pairs {
+			// The sampler settings of the texture, the ones which are not set keep their default
+			let mut sampler = mat.sampler.unwrap_or_default();
+			if parse_sampler_setting(&key, &value, &mut sampler).map_err(invalid)? {
+				mat.sampler = Some(sampler);
+				continue;
+			}
+
 			match key.as_str() {
-				"shader" => { mat.shader = value.parse::<usize>().unwrap() }
-				"stage" => { mat.stage = value.parse::<usize>().unwrap() }
+				"shader" => { mat.shader = value.trim().parse::<usize>().map_err(|_| invalid(format!("{} is not a shader index", value)))? }
+				"stage" => { mat.stage = value.trim().parse::<usize>().map_err(|_| invalid(format!("{} is not a render stage index", value)))? }
 				"color" => {
-					let color_values: Vec<u8> = value.split(",").map(|split| split.parse::<u8>().unwrap()).collect();
+					let color_values = value.split(",").map(|split| split.trim().parse::<u8>()).collect::<Result<Vec<u8>, _>>()
+						.map_err(|_| invalid(format!("{} is not a list of color bytes", value)))?;
 					if color_values.len() != 4 {
-						println!("Color value of material {} is not four byte long", name);
-						return None;
+						return Err(invalid("color value is not four byte long".to_string()));
 					}
 					mat.color = [color_values[0], color_values[1], color_values[2], color_values[3]];
 				}
 				"texture" => { mat.texture = value }
+				"blend" => {
+					mat.pipeline_state.blend = match value.trim() {
+						"opaque" => BlendMode::Opaque,
+						"alpha" => BlendMode::Alpha,
+						"additive" => BlendMode::Additive,
+						"premultiplied" => BlendMode::Premultiplied,
+						"multiply" => BlendMode::Multiply,
+						_ => return Err(invalid(format!("{} is not a blend mode, expected opaque, alpha, additive, premultiplied or multiply", value))),
+					}
+				}
+				"depth_test" => { mat.pipeline_state.depth_test = value.trim().parse::<bool>().map_err(|_| invalid(format!("depth_test {} is not true or false", value)))? }
+				"depth_write" => { mat.pipeline_state.depth_write = value.trim().parse::<bool>().map_err(|_| invalid(format!("depth_write {} is not true or false", value)))? }
+				"cull" => {
+					mat.pipeline_state.cull = match value.trim() {
+						"none" => CullMode::None,
+						"front" => CullMode::Front,
+						"back" => CullMode::Back,
+						_ => return Err(invalid(format!("{} is not a cull mode, expected none, front or back", value))),
+					}
+				}
+				"polygon" => {
+					mat.pipeline_state.polygon = match value.trim() {
+						"fill" => PolygonMode::Fill,
+						"wireframe" => PolygonMode::Wireframe,
+						_ => return Err(invalid(format!("{} is not a polygon mode, expected fill or wireframe", value))),
+					}
+				}
+				"depth_bias" => {
+					// constant factor, slope factor and an optional clamp
+					let factors = value.split(",").map(|split| split.trim().parse::<f32>()).collect::<Result<Vec<f32>, _>>()
+						.map_err(|_| invalid(format!("{} is not a list of depth bias factors", value)))?;
+					if factors.len() != 2 && factors.len() != 3 {
+						return Err(invalid("depth_bias needs a constant factor, a slope factor and optionally a clamp".to_string()));
+					}
+					mat.pipeline_state.depth_bias = Some(DepthBias {
+						constant_factor: factors[0],
+						slope_factor: factors[1],
+						clamp: factors.get(2).copied().unwrap_or(0.0),
+					});
+				}
 				_ => {}
 			}
 		}
 
-		Some(mat)
+		Ok(mat)
+	}
+}
+
+fn parse_key_value_pairs(file_str: &str) -> Vec<(String, String)> {
+	let rows = file_str.split("\n");
+	let mut key_value_pairs: Vec<(String, String)> = Vec::new();
+	for row in rows {
+		if row.len() == 0 {
+			continue;
+		}
+		let pair: Vec<&str> = row.split(":").collect();
+		if pair.len() != 2 {
+			continue;
+		}
+		key_value_pairs.push((pair[0].to_string(), pair[1].to_string()));
+	}
+	key_value_pairs
+}
+
+/// Apply a sampler setting of a .sampler or .mat file. Returns false if the key is not a sampler setting.
+/// The color space tells whether the colors of the image are srgb, like the ones of color textures, or linear, like normal maps.
+fn parse_sampler_setting(key: &str, value: &str, sampler: &mut SamplerSettings) -> Result<bool, String> {
+	let parse_filter = |value: &str| match value.trim() {
+		"nearest" => Ok(TextureFilter::Nearest),
+		"linear" => Ok(TextureFilter::Linear),
+		_ => Err(format!("{} is not a filter, expected nearest or linear", value)),
+	};
+	match key {
+		// Both the min and the mag filter
+		"filter" => {
+			sampler.min_filter = parse_filter(value)?;
+			sampler.mag_filter = sampler.min_filter;
+		}
+		"min_filter" => { sampler.min_filter = parse_filter(value)? }
+		"mag_filter" => { sampler.mag_filter = parse_filter(value)? }
+		"mip_filter" => { sampler.mip_filter = parse_filter(value)? }
+		"wrap" => {
+			sampler.wrap = match value.trim() {
+				"repeat" => TextureWrap::Repeat,
+				"mirrored_repeat" => TextureWrap::MirroredRepeat,
+				"clamp" => TextureWrap::Clamp,
+				"border" => TextureWrap::Border,
+				_ => return Err(format!("{} is not a wrap mode, expected repeat, mirrored_repeat, clamp or border", value)),
+			}
+		}
+		"anisotropy" => {
+			sampler.anisotropy = value.trim().parse::<u8>().ok().filter(|anisotropy| *anisotropy >= 1)
+				.ok_or_else(|| format!("anisotropy {} is not a number from 1 to 255", value))?;
+		}
+		"border_color" => {
+			sampler.border_color = match value.trim() {
+				"transparent_black" => BorderColor::TransparentBlack,
+				"opaque_black" => BorderColor::OpaqueBlack,
+				"opaque_white" => BorderColor::OpaqueWhite,
+				_ => return Err(format!("{} is not a border color, expected transparent_black, opaque_black or opaque_white", value)),
+			}
+		}
+		"mipmaps" => { sampler.mipmaps = value.trim().parse::<bool>().map_err(|_| format!("mipmaps {} is not true or false", value))? }
+		"color_space" => {
+			sampler.color_space = match value.trim() {
+				"srgb" => ColorSpace::Srgb,
+				"linear" => ColorSpace::Linear,
+				_ => return Err(format!("{} is not a color space, expected srgb or linear", value)),
+			}
+		}
+		_ => return Ok(false),
 	}
+	Ok(true)
 }
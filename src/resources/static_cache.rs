@@ -1,5 +1,6 @@
 use resa_renderer::shader::ShaderRef;
-use crate::resources::loaded_resources::{LoadedFont, LoadedImage, LoadedMaterial};
+use crate::resources::error::ResourceError;
+use crate::resources::loaded_resources::{LoadedFont, LoadedImage, LoadedMaterial, LoadedMesh, LoadedScene};
 use crate::resources::resource_loader::ResourceLoader;
 
 pub struct StaticResourceCache {
@@ -7,7 +8,8 @@ pub struct StaticResourceCache {
 	fonts: Vec<LoadedFont>,
 	shaders: Vec<ShaderRef>,
 	materials: Vec<LoadedMaterial>,
-	//meshes: Vec<LoadedMesh>
+	meshes: Vec<LoadedMesh>,
+	scenes: Vec<LoadedScene>,
 }
 
 impl StaticResourceCache {
@@ -17,14 +19,28 @@ impl StaticResourceCache {
 			fonts: Vec::new(),
 			shaders: Vec::new(),
 			materials: Vec::new(),
+			meshes: Vec::new(),
+			scenes: Vec::new(),
 		}
 	}
 
-	pub fn load_from_disk(&mut self, loader: &ResourceLoader) {
-		self.shaders = loader.load_all_shaders().unwrap();
-		self.images = loader.load_images();
-		self.fonts = loader.load_fonts();
-		self.materials = loader.load_materials();
+	pub fn load_from_disk(&mut self, loader: &ResourceLoader) -> Result<(), ResourceError> {
+		self.shaders = loader.load_all_shaders()?;
+		self.images = loader.load_images()?;
+		self.fonts = loader.load_fonts()?;
+		self.materials = loader.load_materials()?;
+		self.meshes = loader.load_meshes()?;
+		self.scenes = loader.load_scenes()?;
+
+		// The .mat files take precedence over the mtl materials with the same name
+		for mesh in self.meshes.iter() {
+			for material in mesh.mtl_materials.iter() {
+				if !self.materials.iter().any(|loaded| loaded.name == material.name) {
+					self.materials.push(material.clone());
+				}
+			}
+		}
+		Ok(())
 	}
 
 	pub fn get_shaders(&self) -> Vec<ShaderRef>{
@@ -41,4 +57,10 @@ impl StaticResourceCache {
 	}
 
 	pub fn get_materials(&self) -> Vec<LoadedMaterial>{ self.materials.clone()	}
+
+	pub fn get_meshes(&self) -> Vec<LoadedMesh>{ self.meshes.clone() }
+
+	pub fn get_scene(&self, scene_name: &str) -> Option<LoadedScene>{
+		Some(self.scenes.iter().find(|scene| scene.name == scene_name)?.clone())
+	}
 }
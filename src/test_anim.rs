@@ -1,22 +1,22 @@
-use std::cell::RefCell;
-use std::rc::Rc;
 use resa_ecs::entity::Entity;
 use resa_ecs::world::World;
 use resa_renderer::material::Color;
 use crate::rendering::mesh_renderer::MeshRenderer;
 use crate::rendering::transform::Transform;
+use crate::time::Time;
 
-pub fn rotate_entity(world: &Rc<RefCell<World>>, entity: &Entity, delta_time: &f64){
-
-	let mut world_binding = world.borrow_mut();
-	let mut transform: &mut Transform = world_binding.get_component_mut::<Transform>(&entity).unwrap();
-	transform.angle += (1.0 * (*delta_time)) as f32;
-
+pub fn rotate_entity(world: &mut World, entity: &Entity){
+	let delta_time = world.get_resource::<Time>().unwrap().delta_seconds;
+	let mut transform: &mut Transform = world.get_component_mut::<Transform>(&entity).unwrap();
+	transform.angle += (1.0 * delta_time) as f32;
 }
 
-pub fn change_color(world: &Rc<RefCell<World>>, entity: &Entity, _delta_time: &f64){
-	let mut world_binding = world.borrow_mut();
-	let mesh_renderer: &mut MeshRenderer = world_binding.get_component_mut::<MeshRenderer>(&entity).unwrap();
+/// Headless worlds have no mesh renderers, their entities keep their color.
+pub fn change_color(world: &mut World, entity: &Entity){
+	let mesh_renderer: &mut MeshRenderer = match world.get_component_mut::<MeshRenderer>(&entity) {
+		Some(mesh_renderer) => mesh_renderer,
+		None => return,
+	};
 	let mut material = mesh_renderer.get_material();
 
 	let red = 1.0  ;
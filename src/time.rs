@@ -0,0 +1,15 @@
+/// Resource holding the frame timing of the application.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Time {
+	pub delta_seconds: f64,
+	pub elapsed_seconds: f64,
+	pub frame: u64,
+}
+
+impl Time {
+	pub fn advance(&mut self, delta_seconds: f64) {
+		self.delta_seconds = delta_seconds;
+		self.elapsed_seconds += delta_seconds;
+		self.frame += 1;
+	}
+}
@@ -0,0 +1,2 @@
+pub mod window_settings;
+pub mod window_events;
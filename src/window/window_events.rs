@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+
+/// Events of the application window, re-emitted into the world every frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WindowEvent {
+	CloseRequested,
+	Focused(bool),
+	Resized { width: u32, height: u32 },
+	Moved { x: i32, y: i32 },
+	ScaleFactorChanged { scale_factor: f64 },
+	FileHovered(PathBuf),
+	FileHoverCancelled,
+	FileDropped(PathBuf),
+}
+
+impl WindowEvent {
+	/// Convert a winit window event to a window event, returns None for events that are not forwarded to the world.
+	pub(crate) fn from_winit(event: &winit::event::WindowEvent) -> Option<Self> {
+		use winit::event::WindowEvent as WinitEvent;
+
+		let event = match event {
+			WinitEvent::CloseRequested => WindowEvent::CloseRequested,
+			WinitEvent::Focused(focused) => WindowEvent::Focused(*focused),
+			WinitEvent::Resized(size) => WindowEvent::Resized { width: size.width, height: size.height },
+			WinitEvent::Moved(position) => WindowEvent::Moved { x: position.x, y: position.y },
+			WinitEvent::ScaleFactorChanged { scale_factor, .. } => WindowEvent::ScaleFactorChanged { scale_factor: *scale_factor },
+			WinitEvent::HoveredFile(path) => WindowEvent::FileHovered(path.clone()),
+			WinitEvent::HoveredFileCancelled => WindowEvent::FileHoverCancelled,
+			WinitEvent::DroppedFile(path) => WindowEvent::FileDropped(path.clone()),
+			_ => return None,
+		};
+		Some(event)
+	}
+}
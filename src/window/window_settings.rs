@@ -0,0 +1,140 @@
+use log::warn;
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::window::{CursorGrabMode, Fullscreen, Window, WindowBuilder};
+use resa_renderer::PresentMode;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WindowMode {
+	Windowed,
+	BorderlessFullscreen,
+	Fullscreen,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CursorGrab {
+	None,
+	Confined,
+	Locked,
+}
+
+/// Resource describing the application window. It is applied when the app is created
+/// and can be changed by systems at runtime, the changes will be applied after the frame's systems ran.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowSettings {
+	pub title: String,
+	pub width: u32,
+	pub height: u32,
+	pub resizable: bool,
+	pub always_on_top: bool,
+	pub mode: WindowMode,
+	pub present_mode: PresentMode,
+	pub cursor_grab: CursorGrab,
+	pub cursor_visible: bool,
+	/// Overrides the scale factor of the monitor, so the physical size of the window is logical size * override.
+	pub scale_factor_override: Option<f64>,
+}
+
+impl Default for WindowSettings {
+	fn default() -> Self {
+		WindowSettings {
+			title: "Resa".to_string(),
+			width: 1280,
+			height: 720,
+			resizable: true,
+			always_on_top: false,
+			mode: WindowMode::Windowed,
+			present_mode: PresentMode::Fifo,
+			cursor_grab: CursorGrab::None,
+			cursor_visible: true,
+			scale_factor_override: None,
+		}
+	}
+}
+
+impl WindowSettings {
+	pub fn logical_size(&self) -> LogicalSize<u32> {
+		LogicalSize::new(self.width, self.height)
+	}
+
+	/// Get the physical size of the window for the given monitor scale factor, respecting the scale factor override.
+	pub fn physical_size(&self, monitor_scale_factor: f64) -> PhysicalSize<u32> {
+		self.logical_size().to_physical(self.scale_factor(monitor_scale_factor))
+	}
+
+	pub fn scale_factor(&self, monitor_scale_factor: f64) -> f64 {
+		self.scale_factor_override.unwrap_or(monitor_scale_factor)
+	}
+
+	pub(crate) fn create_window_builder(&self, monitor_scale_factor: f64) -> WindowBuilder {
+		WindowBuilder::new()
+			.with_title(&self.title)
+			.with_inner_size(self.physical_size(monitor_scale_factor))
+			.with_resizable(self.resizable)
+			.with_always_on_top(self.always_on_top)
+	}
+
+	/// Apply the settings which can not be set by the window builder.
+	pub(crate) fn apply_initial(&self, window: &Window) {
+		window.set_fullscreen(self.get_fullscreen(window));
+		self.apply_cursor_grab(window);
+		window.set_cursor_visible(self.cursor_visible);
+	}
+
+	/// Apply all settings that differ from the previously applied settings.
+	pub(crate) fn apply_changes(&self, window: &Window, previous: &WindowSettings) {
+		if self.title != previous.title {
+			window.set_title(&self.title);
+		}
+		if self.resizable != previous.resizable {
+			window.set_resizable(self.resizable);
+		}
+		if self.always_on_top != previous.always_on_top {
+			window.set_always_on_top(self.always_on_top);
+		}
+		if self.width != previous.width || self.height != previous.height || self.scale_factor_override != previous.scale_factor_override {
+			window.set_inner_size(self.physical_size(window.scale_factor()));
+		}
+		if self.mode != previous.mode {
+			window.set_fullscreen(self.get_fullscreen(window));
+		}
+		if self.cursor_grab != previous.cursor_grab {
+			self.apply_cursor_grab(window);
+		}
+		if self.cursor_visible != previous.cursor_visible {
+			window.set_cursor_visible(self.cursor_visible);
+		}
+	}
+
+	fn apply_cursor_grab(&self, window: &Window) {
+		let grab_mode = match self.cursor_grab {
+			CursorGrab::None => CursorGrabMode::None,
+			CursorGrab::Confined => CursorGrabMode::Confined,
+			CursorGrab::Locked => CursorGrabMode::Locked,
+		};
+		if let Err(e) = window.set_cursor_grab(grab_mode) {
+			warn!("Could not set cursor grab mode {:?}: {}", self.cursor_grab, e);
+		}
+	}
+
+	fn get_fullscreen(&self, window: &Window) -> Option<Fullscreen> {
+		match self.mode {
+			WindowMode::Windowed => None,
+			WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(None)),
+			WindowMode::Fullscreen => {
+				let monitor = window.current_monitor()?;
+				let requested_size = self.physical_size(monitor.scale_factor());
+				let video_mode = monitor.video_modes()
+					.find(|mode| mode.size() == requested_size)
+					.or_else(|| monitor.video_modes().next());
+
+				match video_mode {
+					Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+					None => {
+						warn!("No video mode available for exclusive fullscreen, using borderless fullscreen instead");
+						Some(Fullscreen::Borderless(Some(monitor)))
+					}
+				}
+			}
+		}
+	}
+}